@@ -0,0 +1,885 @@
+use syntax::{SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken};
+
+#[derive(Debug)]
+pub struct Root(SyntaxNode);
+
+impl Root {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == SyntaxKind::Root {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    pub fn stmts(&self) -> impl Iterator<Item = Stmt> {
+        self.0.children().filter_map(Stmt::cast)
+    }
+
+    pub fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+}
+
+#[derive(Debug)]
+pub enum Stmt {
+    VariableDef(VariableDef),
+    Assignment(Assignment),
+    FnDef(FnDef),
+    ModDef(ModDef),
+    CompDef(CompDef),
+    ReturnStmt(ReturnStmt),
+    Expr(Expr),
+}
+
+impl Stmt {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        let result = match node.kind() {
+            SyntaxKind::VariableDef => Self::VariableDef(VariableDef(node)),
+            SyntaxKind::Assignment => Self::Assignment(Assignment(node)),
+            SyntaxKind::FnDef => Self::FnDef(FnDef(node)),
+            SyntaxKind::ModDef => Self::ModDef(ModDef(node)),
+            SyntaxKind::CompDef => Self::CompDef(CompDef(node)),
+            SyntaxKind::ReturnStmt => Self::ReturnStmt(ReturnStmt(node)),
+            _ => Self::Expr(Expr::cast(node)?),
+        };
+
+        Some(result)
+    }
+
+    pub fn syntax(&self) -> &SyntaxNode {
+        match self {
+            Self::VariableDef(def) => &def.0,
+            Self::Assignment(assignment) => &assignment.0,
+            Self::FnDef(def) => &def.0,
+            Self::ModDef(def) => &def.0,
+            Self::CompDef(def) => &def.0,
+            Self::ReturnStmt(ret) => &ret.0,
+            Self::Expr(expr) => expr.syntax(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct VariableDef(SyntaxNode);
+
+impl VariableDef {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .find(|token| token.kind() == SyntaxKind::Ident)
+    }
+
+    pub fn value(&self) -> Option<Expr> {
+        self.0.children().find_map(Expr::cast)
+    }
+}
+
+#[derive(Debug)]
+pub struct Assignment(SyntaxNode);
+
+impl Assignment {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0
+            .first_token()
+            .filter(|token| token.kind() == SyntaxKind::Ident)
+    }
+
+    pub fn value(&self) -> Option<Expr> {
+        self.0.children().find_map(Expr::cast)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FnDef(SyntaxNode);
+
+impl FnDef {
+    pub fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0
+            .first_token()
+            .filter(|token| token.kind() == SyntaxKind::Ident)
+    }
+
+    pub fn params(&self) -> impl Iterator<Item = Param> {
+        self.0
+            .children()
+            .find(|node| node.kind() == SyntaxKind::ParamList)
+            .into_iter()
+            .flat_map(params)
+    }
+
+    pub fn ret_type(&self) -> Option<RetType> {
+        self.0
+            .children()
+            .find(|node| node.kind() == SyntaxKind::RetType)
+            .map(RetType)
+    }
+
+    pub fn body(&self) -> Option<Block> {
+        self.0
+            .children()
+            .find(|node| node.kind() == SyntaxKind::Block)
+            .map(Block)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ModDef(SyntaxNode);
+
+impl ModDef {
+    pub fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0
+            .first_token()
+            .filter(|token| token.kind() == SyntaxKind::Ident)
+    }
+
+    pub fn params(&self) -> impl Iterator<Item = Param> {
+        self.0
+            .children()
+            .find(|node| node.kind() == SyntaxKind::ParamList)
+            .into_iter()
+            .flat_map(params)
+    }
+
+    pub fn body(&self) -> Option<Block> {
+        self.0
+            .children()
+            .find(|node| node.kind() == SyntaxKind::Block)
+            .map(Block)
+    }
+}
+
+#[derive(Debug)]
+pub struct CompDef(SyntaxNode);
+
+impl CompDef {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0
+            .first_token()
+            .filter(|token| token.kind() == SyntaxKind::Ident)
+    }
+
+    pub fn fields(&self) -> impl Iterator<Item = CompField> {
+        self.0
+            .children()
+            .filter(|node| node.kind() == SyntaxKind::CompField)
+            .map(CompField)
+    }
+}
+
+#[derive(Debug)]
+pub struct CompField(SyntaxNode);
+
+impl CompField {
+    pub fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0
+            .first_token()
+            .filter(|token| token.kind() == SyntaxKind::Ident)
+    }
+
+    pub fn value(&self) -> Option<Expr> {
+        self.0.children().find_map(Expr::cast)
+    }
+}
+
+fn params(param_list: SyntaxNode) -> impl Iterator<Item = Param> {
+    param_list
+        .children()
+        .filter(|node| node.kind() == SyntaxKind::Param)
+        .map(Param)
+}
+
+#[derive(Debug, Clone)]
+pub struct Param(SyntaxNode);
+
+impl Param {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0
+            .first_token()
+            .filter(|token| token.kind() == SyntaxKind::Ident)
+    }
+
+    pub fn ty(&self) -> Option<TypeExpr> {
+        self.0.children().find_map(TypeExpr::cast)
+    }
+}
+
+// Either `-> number` or named returns, `-> (c: number)`
+#[derive(Debug, Clone)]
+pub struct RetType(SyntaxNode);
+
+impl RetType {
+    pub fn ty(&self) -> Option<TypeExpr> {
+        self.0.children().find_map(TypeExpr::cast)
+    }
+
+    pub fn named(&self) -> Option<Vec<Param>> {
+        self.0
+            .children()
+            .find(|node| node.kind() == SyntaxKind::ParamList)
+            .map(|param_list| params(param_list).collect())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeExpr(SyntaxNode);
+
+impl TypeExpr {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == SyntaxKind::TypeExpr {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    pub fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+
+    // `None` for vector types, which have an element type instead
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0
+            .first_token()
+            .filter(|token| token.kind() == SyntaxKind::Ident)
+    }
+
+    // The element type of `[number]`, or the argument of `Roll<Pending>`
+    pub fn inner(&self) -> Option<TypeExpr> {
+        self.0.children().find_map(TypeExpr::cast)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Block(SyntaxNode);
+
+impl Block {
+    pub fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+
+    pub fn stmts(&self) -> impl Iterator<Item = Stmt> {
+        self.0.children().filter_map(Stmt::cast)
+    }
+}
+
+#[derive(Debug)]
+pub struct ReturnStmt(SyntaxNode);
+
+impl ReturnStmt {
+    pub fn value(&self) -> Option<Expr> {
+        self.0.children().find_map(Expr::cast)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    InfixExpr(InfixExpr),
+    Literal(Literal),
+    StringLiteral(StringLiteral),
+    DiceExpr(DiceExpr),
+    ParenExpr(ParenExpr),
+    PrefixExpr(PrefixExpr),
+    RollExpr(RollExpr),
+    CritExpr(CritExpr),
+    VariableRef(VariableRef),
+    CallExpr(CallExpr),
+    ModExpr(ModExpr),
+    AnnotatedExpr(AnnotatedExpr),
+    FieldExpr(FieldExpr),
+    IndexExpr(IndexExpr),
+    ArrayExpr(ArrayExpr),
+    GroupExpr(GroupExpr),
+}
+
+impl Expr {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        let result = match node.kind() {
+            SyntaxKind::InfixExpr => Self::InfixExpr(InfixExpr(node)),
+            SyntaxKind::Literal => Self::Literal(Literal(node)),
+            SyntaxKind::StringLiteral => Self::StringLiteral(StringLiteral(node)),
+            SyntaxKind::DiceExpr => Self::DiceExpr(DiceExpr(node)),
+            SyntaxKind::ParenExpr => Self::ParenExpr(ParenExpr(node)),
+            SyntaxKind::PrefixExpr => Self::PrefixExpr(PrefixExpr(node)),
+            SyntaxKind::RollExpr => Self::RollExpr(RollExpr(node)),
+            SyntaxKind::CritExpr => Self::CritExpr(CritExpr(node)),
+            SyntaxKind::VariableRef => Self::VariableRef(VariableRef(node)),
+            SyntaxKind::CallExpr => Self::CallExpr(CallExpr(node)),
+            SyntaxKind::ModExpr => Self::ModExpr(ModExpr(node)),
+            SyntaxKind::AnnotatedExpr => Self::AnnotatedExpr(AnnotatedExpr(node)),
+            SyntaxKind::FieldExpr => Self::FieldExpr(FieldExpr(node)),
+            SyntaxKind::IndexExpr => Self::IndexExpr(IndexExpr(node)),
+            SyntaxKind::ArrayExpr => Self::ArrayExpr(ArrayExpr(node)),
+            SyntaxKind::GroupExpr => Self::GroupExpr(GroupExpr(node)),
+            _ => return None,
+        };
+
+        Some(result)
+    }
+
+    pub fn syntax(&self) -> &SyntaxNode {
+        match self {
+            Self::InfixExpr(node) => &node.0,
+            Self::Literal(node) => &node.0,
+            Self::StringLiteral(node) => &node.0,
+            Self::DiceExpr(node) => &node.0,
+            Self::ParenExpr(node) => &node.0,
+            Self::PrefixExpr(node) => &node.0,
+            Self::RollExpr(node) => &node.0,
+            Self::CritExpr(node) => &node.0,
+            Self::VariableRef(node) => &node.0,
+            Self::CallExpr(node) => &node.0,
+            Self::ModExpr(node) => &node.0,
+            Self::AnnotatedExpr(node) => &node.0,
+            Self::FieldExpr(node) => &node.0,
+            Self::IndexExpr(node) => &node.0,
+            Self::ArrayExpr(node) => &node.0,
+            Self::GroupExpr(node) => &node.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InfixExpr(SyntaxNode);
+
+impl InfixExpr {
+    pub fn lhs(&self) -> Option<Expr> {
+        self.0.children().find_map(Expr::cast)
+    }
+
+    pub fn rhs(&self) -> Option<Expr> {
+        self.0.children().filter_map(Expr::cast).nth(1)
+    }
+
+    pub fn op(&self) -> Option<SyntaxToken> {
+        self.0
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .find(|token| {
+                matches!(
+                    token.kind(),
+                    SyntaxKind::Plus | SyntaxKind::Minus | SyntaxKind::Star | SyntaxKind::Slash,
+                )
+            })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Literal(SyntaxNode);
+
+impl Literal {
+    pub fn parse(&self) -> Option<i64> {
+        self.0.first_token().unwrap().text().parse().ok()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StringLiteral(SyntaxNode);
+
+impl StringLiteral {
+    // Strips the quotes and resolves escapes; unknown escapes are kept as written
+    pub fn value(&self) -> Option<String> {
+        let token = self.0.first_token()?;
+        let text = token.text();
+        let inner = text.get(1..text.len().checked_sub(1)?)?;
+
+        let mut value = String::with_capacity(inner.len());
+        let mut chars = inner.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                value.push(c);
+                continue;
+            }
+
+            match chars.next() {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some(c @ ('\\' | '"' | '\'')) => value.push(c),
+                Some(c) => {
+                    value.push('\\');
+                    value.push(c);
+                }
+                None => value.push('\\'),
+            }
+        }
+
+        Some(value)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DiceExpr(SyntaxNode);
+
+impl DiceExpr {
+    // The count is optional in the source: `d20` is the same as `1d20`
+    pub fn count(&self) -> Option<u32> {
+        let token = self.0.first_token()?;
+        let (count, _) = token.text().split_once('d')?;
+
+        if count.is_empty() {
+            Some(1)
+        } else {
+            count.parse().ok()
+        }
+    }
+
+    pub fn sides(&self) -> Option<u32> {
+        let token = self.0.first_token()?;
+        let (_, rest) = token.text().split_once('d')?;
+        let end = rest.find(['k', 'd']).unwrap_or(rest.len());
+        rest[..end].parse().ok()
+    }
+
+    // The `dl1` in `4d6dl1`. The count is `None` when it's too large.
+    pub fn select(&self) -> Option<(Select, Option<u32>)> {
+        let token = self.0.first_token()?;
+        let (_, rest) = token.text().split_once('d')?;
+        let start = rest.find(['k', 'd'])?;
+        let (kind, count) = rest[start..].split_at(2);
+
+        let select = match kind {
+            "kh" => Select::KeepHighest,
+            "kl" => Select::KeepLowest,
+            "dh" => Select::DropHighest,
+            "dl" => Select::DropLowest,
+            _ => return None,
+        };
+
+        Some((select, count.parse().ok()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Select {
+    KeepHighest,
+    KeepLowest,
+    DropHighest,
+    DropLowest,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParenExpr(SyntaxNode);
+
+impl ParenExpr {
+    pub fn expr(&self) -> Option<Expr> {
+        self.0.children().find_map(Expr::cast)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PrefixExpr(SyntaxNode);
+
+impl PrefixExpr {
+    pub fn expr(&self) -> Option<Expr> {
+        self.0.children().find_map(Expr::cast)
+    }
+
+    pub fn op(&self) -> Option<SyntaxToken> {
+        self.0
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .find(|token| token.kind() == SyntaxKind::Minus)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RollExpr(SyntaxNode);
+
+impl RollExpr {
+    pub fn expr(&self) -> Option<Expr> {
+        self.0.children().find_map(Expr::cast)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CritExpr(SyntaxNode);
+
+impl CritExpr {
+    pub fn expr(&self) -> Option<Expr> {
+        self.0.children().find_map(Expr::cast)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VariableRef(SyntaxNode);
+
+impl VariableRef {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0
+            .first_token()
+            .filter(|token| token.kind() == SyntaxKind::Ident)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CallExpr(SyntaxNode);
+
+impl CallExpr {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0
+            .first_token()
+            .filter(|token| token.kind() == SyntaxKind::Ident)
+    }
+
+    pub fn args(&self) -> impl Iterator<Item = Expr> {
+        self.arg_list()
+            .into_iter()
+            .flat_map(|arg_list| arg_list.children().filter_map(Expr::cast))
+    }
+
+    // `repr 1d20` as opposed to `repr(1d20)`
+    pub fn is_juxtaposed(&self) -> bool {
+        self.arg_list().is_some_and(|arg_list| {
+            arg_list
+                .first_token()
+                .is_some_and(|token| token.kind() != SyntaxKind::LParen)
+        })
+    }
+
+    fn arg_list(&self) -> Option<SyntaxNode> {
+        self.0
+            .children()
+            .find(|node| node.kind() == SyntaxKind::ArgList)
+    }
+}
+
+// `d20:Poison(d4)`
+#[derive(Debug, Clone)]
+pub struct ModExpr(SyntaxNode);
+
+impl ModExpr {
+    pub fn expr(&self) -> Option<Expr> {
+        self.0.children().find_map(Expr::cast)
+    }
+
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .find(|token| token.kind() == SyntaxKind::Ident)
+    }
+
+    pub fn args(&self) -> impl Iterator<Item = Expr> {
+        self.0
+            .children()
+            .find(|node| node.kind() == SyntaxKind::ArgList)
+            .into_iter()
+            .flat_map(|arg_list| arg_list.children().filter_map(Expr::cast))
+    }
+}
+
+// `d20:"Stealth"`
+#[derive(Debug, Clone)]
+pub struct AnnotatedExpr(SyntaxNode);
+
+impl AnnotatedExpr {
+    pub fn expr(&self) -> Option<Expr> {
+        self.0.first_child().and_then(Expr::cast)
+    }
+
+    // The string after the colon, without its quotes
+    pub fn annotation(&self) -> Option<String> {
+        match Expr::cast(self.0.children().nth(1)?)? {
+            Expr::StringLiteral(literal) => literal.value(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldExpr(SyntaxNode);
+
+impl FieldExpr {
+    pub fn expr(&self) -> Option<Expr> {
+        self.0.children().find_map(Expr::cast)
+    }
+
+    pub fn field(&self) -> Option<SyntaxToken> {
+        self.0
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .find(|token| token.kind() == SyntaxKind::Ident)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexExpr(SyntaxNode);
+
+impl IndexExpr {
+    pub fn expr(&self) -> Option<Expr> {
+        self.0.children().find_map(Expr::cast)
+    }
+
+    pub fn index(&self) -> Option<Expr> {
+        self.0.children().filter_map(Expr::cast).nth(1)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ArrayExpr(SyntaxNode);
+
+impl ArrayExpr {
+    pub fn items(&self) -> impl Iterator<Item = Expr> {
+        self.0.children().filter_map(Expr::cast)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GroupExpr(SyntaxNode);
+
+impl GroupExpr {
+    pub fn members(&self) -> impl Iterator<Item = GroupMember> {
+        self.0
+            .children()
+            .filter(|node| node.kind() == SyntaxKind::GroupMember)
+            .map(GroupMember)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GroupMember(SyntaxNode);
+
+impl GroupMember {
+    pub fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0
+            .first_token()
+            .filter(|token| token.kind() == SyntaxKind::Ident)
+    }
+
+    pub fn value(&self) -> Option<Expr> {
+        self.0.children().find_map(Expr::cast)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_expr(input: &str) -> Expr {
+        let root = Root::cast(parser::parse(input).syntax()).unwrap();
+        match root.stmts().next() {
+            Some(Stmt::Expr(expr)) => expr,
+            stmt => panic!("expected an expression, got {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn dice_without_count_has_one_die() {
+        match parse_expr("d20") {
+            Expr::DiceExpr(dice) => {
+                assert_eq!(dice.count(), Some(1));
+                assert_eq!(dice.sides(), Some(20));
+            }
+            expr => panic!("expected dice, got {:?}", expr),
+        }
+    }
+
+    #[test]
+    fn dice_with_count() {
+        match parse_expr("12d6") {
+            Expr::DiceExpr(dice) => {
+                assert_eq!(dice.count(), Some(12));
+                assert_eq!(dice.sides(), Some(6));
+            }
+            expr => panic!("expected dice, got {:?}", expr),
+        }
+    }
+
+    #[test]
+    fn dice_dropping_lowest() {
+        match parse_expr("4d6dl1") {
+            Expr::DiceExpr(dice) => {
+                assert_eq!(dice.count(), Some(4));
+                assert_eq!(dice.sides(), Some(6));
+                assert_eq!(dice.select(), Some((Select::DropLowest, Some(1))));
+            }
+            expr => panic!("expected dice, got {:?}", expr),
+        }
+    }
+
+    #[test]
+    fn roll_expr_wraps_operand() {
+        match parse_expr("roll 3d8+4") {
+            Expr::RollExpr(roll) => assert!(matches!(roll.expr(), Some(Expr::InfixExpr(_)))),
+            expr => panic!("expected roll, got {:?}", expr),
+        }
+    }
+
+    #[test]
+    fn variable_def_name_and_value() {
+        let root = Root::cast(parser::parse("let dmg = 2d6").syntax()).unwrap();
+        match root.stmts().next() {
+            Some(Stmt::VariableDef(def)) => {
+                assert_eq!(def.name().unwrap().text(), "dmg");
+                assert!(matches!(def.value(), Some(Expr::DiceExpr(_))));
+            }
+            stmt => panic!("expected a variable definition, got {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn string_literal_resolves_escapes() {
+        match parse_expr(r#""say \"hi\"\n""#) {
+            Expr::StringLiteral(string) => assert_eq!(string.value().unwrap(), "say \"hi\"\n"),
+            expr => panic!("expected a string, got {:?}", expr),
+        }
+    }
+
+    #[test]
+    fn call_name_and_args() {
+        match parse_expr("max(a, 2d6)") {
+            Expr::CallExpr(call) => {
+                assert_eq!(call.name().unwrap().text(), "max");
+                assert_eq!(call.args().count(), 2);
+                assert!(!call.is_juxtaposed());
+            }
+            expr => panic!("expected a call, got {:?}", expr),
+        }
+    }
+
+    #[test]
+    fn juxtaposed_call_has_one_arg() {
+        match parse_expr("repr 1d20") {
+            Expr::CallExpr(call) => {
+                assert!(matches!(
+                    call.args().collect::<Vec<_>>()[..],
+                    [Expr::DiceExpr(_)]
+                ));
+                assert!(call.is_juxtaposed());
+            }
+            expr => panic!("expected a call, got {:?}", expr),
+        }
+    }
+
+    #[test]
+    fn fn_def_parts() {
+        let root = Root::cast(
+            parser::parse("addnums :: fn(a: number, b: [dice]) -> (c: number) { c = a; return c }")
+                .syntax(),
+        )
+        .unwrap();
+
+        let Some(Stmt::FnDef(def)) = root.stmts().next() else {
+            panic!("expected a function definition");
+        };
+
+        assert_eq!(def.name().unwrap().text(), "addnums");
+
+        let params: Vec<_> = def.params().collect();
+        assert_eq!(params.len(), 2);
+        assert_eq!(params[1].name().unwrap().text(), "b");
+
+        let vec_ty = params[1].ty().unwrap();
+        assert!(vec_ty.name().is_none());
+        assert_eq!(vec_ty.inner().unwrap().name().unwrap().text(), "dice");
+
+        let named = def.ret_type().unwrap().named().unwrap();
+        assert_eq!(named[0].name().unwrap().text(), "c");
+
+        let stmts: Vec<_> = def.body().unwrap().stmts().collect();
+        assert!(matches!(
+            stmts[..],
+            [Stmt::Assignment(_), Stmt::ReturnStmt(_)]
+        ));
+    }
+
+    #[test]
+    fn mod_expr_parts() {
+        match parse_expr("d20:Poison(d4)") {
+            Expr::ModExpr(modded) => {
+                assert!(matches!(modded.expr(), Some(Expr::DiceExpr(_))));
+                assert_eq!(modded.name().unwrap().text(), "Poison");
+                assert_eq!(modded.args().count(), 1);
+            }
+            expr => panic!("expected a modifier, got {:?}", expr),
+        }
+    }
+
+    #[test]
+    fn annotated_expr_parts() {
+        match parse_expr("2d4:\"Fire attack\"") {
+            Expr::AnnotatedExpr(annotated) => {
+                assert!(matches!(annotated.expr(), Some(Expr::DiceExpr(_))));
+                assert_eq!(annotated.annotation().unwrap(), "Fire attack");
+            }
+            expr => panic!("expected an annotation, got {:?}", expr),
+        }
+    }
+
+    #[test]
+    fn field_expr_parts() {
+        match parse_expr("Dragon.stomp") {
+            Expr::FieldExpr(field) => {
+                assert!(matches!(field.expr(), Some(Expr::VariableRef(_))));
+                assert_eq!(field.field().unwrap().text(), "stomp");
+            }
+            expr => panic!("expected a field access, got {:?}", expr),
+        }
+    }
+
+    #[test]
+    fn index_expr_parts() {
+        match parse_expr("dnd['Ability Scores']") {
+            Expr::IndexExpr(index) => {
+                assert!(matches!(index.expr(), Some(Expr::VariableRef(_))));
+                assert!(matches!(index.index(), Some(Expr::StringLiteral(_))));
+            }
+            expr => panic!("expected an index, got {:?}", expr),
+        }
+    }
+
+    #[test]
+    fn array_items() {
+        match parse_expr("[1, 'two', d20]") {
+            Expr::ArrayExpr(array) => assert_eq!(array.items().count(), 3),
+            expr => panic!("expected an array, got {:?}", expr),
+        }
+    }
+
+    #[test]
+    fn group_members() {
+        match parse_expr("(uthal := d20+2, mycin := d20+4)") {
+            Expr::GroupExpr(group) => {
+                let names: Vec<_> = group
+                    .members()
+                    .map(|member| member.name().unwrap().text().to_string())
+                    .collect();
+                assert_eq!(names, ["uthal", "mycin"]);
+            }
+            expr => panic!("expected a group, got {:?}", expr),
+        }
+    }
+
+    #[test]
+    fn comp_def_fields() {
+        let root = Root::cast(parser::parse("Dragon :: Comp(a: 1d6, b: 2)").syntax()).unwrap();
+        let Some(Stmt::CompDef(def)) = root.stmts().next() else {
+            panic!("expected a composite definition");
+        };
+
+        assert_eq!(def.name().unwrap().text(), "Dragon");
+        let fields: Vec<_> = def
+            .fields()
+            .map(|field| field.name().unwrap().text().to_string())
+            .collect();
+        assert_eq!(fields, ["a", "b"]);
+    }
+}
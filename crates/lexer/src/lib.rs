@@ -1,4 +1,6 @@
 use logos::Logos;
+use std::ops::Range as StdRange;
+use text_size::{TextRange, TextSize};
 
 mod token_kind;
 pub use token_kind::TokenKind;
@@ -19,6 +21,7 @@ impl<'a> Lexer<'a> {
 pub struct Token<'a> {
     pub kind: Result<TokenKind, ()>,
     pub text: &'a str,
+    pub range: TextRange,
 }
 
 impl<'a> Iterator for Lexer<'a> {
@@ -28,6 +31,14 @@ impl<'a> Iterator for Lexer<'a> {
         let kind = self.inner.next()?;
         let text = self.inner.slice();
 
-        Some(Self::Item { kind, text })
+        let range = {
+            let StdRange { start, end } = self.inner.span();
+            let start = TextSize::try_from(start).unwrap();
+            let end = TextSize::try_from(end).unwrap();
+
+            TextRange::new(start, end)
+        };
+
+        Some(Self::Item { kind, text, range })
     }
 }
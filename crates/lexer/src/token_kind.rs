@@ -2,21 +2,42 @@ use logos::Logos;
 
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Logos, Hash)]
 pub enum TokenKind {
-    #[regex("[ \n]+")]
+    #[regex("[ \t]+")]
     Whitespace,
 
+    // Kept apart from other whitespace because a line break can end a statement
+    #[regex("\r?\n")]
+    Newline,
+
     #[token("let")]
     Let,
 
-    #[regex("([1-9][0-9]*)?d[1-9][0-9]*")]
+    #[token("roll")]
+    Roll,
+
+    #[token("crit")]
+    Crit,
+
+    #[token("fn")]
+    Fn,
+
+    #[token("return")]
+    Return,
+
+    // Optionally keeping or dropping the highest or lowest few, e.g. `4d6dl1`
+    #[regex("([1-9][0-9]*)?d[1-9][0-9]*([kd][hl][1-9][0-9]*)?")]
     Dice,
 
-    #[regex("[A-Za-z][A-Za-z0-9]*")]
+    #[regex("[A-Za-z][A-Za-z0-9_]*")]
     Ident,
 
-    #[regex("[1-9][0-9]*")]
+    #[regex("[0-9]+")]
     Number,
 
+    #[regex(r#""([^"\\\n]|\\.)*""#)]
+    #[regex(r#"'([^'\\\n]|\\.)*'"#)]
+    String,
+
     #[token("+")]
     Plus,
 
@@ -38,16 +59,72 @@ pub enum TokenKind {
     #[token(")")]
     RParen,
 
+    #[token(",")]
+    Comma,
+
+    #[token("::")]
+    ColonColon,
+
+    #[token(":=")]
+    ColonEquals,
+
+    #[token(":")]
+    Colon,
+
+    #[token(".")]
+    Dot,
+
+    #[token("->")]
+    Arrow,
+
+    #[token("{")]
+    LBrace,
+
+    #[token("}")]
+    RBrace,
+
+    #[token("[")]
+    LBracket,
+
+    #[token("]")]
+    RBracket,
+
+    #[token(";")]
+    Semicolon,
+
+    #[token("<")]
+    Lt,
+
+    #[token(">")]
+    Gt,
+
     #[regex("#.*")]
+    #[regex("//.*")]
+    #[token("/*", block_comment)]
     Comment,
 
     Error,
 }
 
+// Block comments don't nest, so they end at the first `*/`. One that's never closed is an error.
+fn block_comment(lex: &mut logos::Lexer<TokenKind>) -> bool {
+    match lex.remainder().find("*/") {
+        Some(end) => {
+            lex.bump(end + 2);
+            true
+        }
+        None => {
+            lex.bump(lex.remainder().len());
+            false
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{Lexer, Token};
+    use text_size::{TextRange, TextSize};
 
     fn check(input: &str, kind: TokenKind) {
         let mut lexer = Lexer::new(input);
@@ -55,7 +132,8 @@ mod tests {
             lexer.next(),
             Some(Token {
                 kind: Ok(kind),
-                text: input
+                text: input,
+                range: TextRange::up_to(TextSize::of(input)),
             })
         );
     }
@@ -69,6 +147,32 @@ mod tests {
     fn lex_number() {
         check("200", TokenKind::Number);
         check("24518672", TokenKind::Number);
+        check("0", TokenKind::Number);
+    }
+
+    #[test]
+    fn lex_let_keyword() {
+        check("let", TokenKind::Let);
+    }
+
+    #[test]
+    fn lex_roll_keyword() {
+        check("roll", TokenKind::Roll);
+    }
+
+    #[test]
+    fn lex_crit_keyword() {
+        check("crit", TokenKind::Crit);
+    }
+
+    #[test]
+    fn lex_fn_keyword() {
+        check("fn", TokenKind::Fn);
+    }
+
+    #[test]
+    fn lex_return_keyword() {
+        check("return", TokenKind::Return);
     }
 
     #[test]
@@ -78,6 +182,14 @@ mod tests {
         check("100d24", TokenKind::Dice);
     }
 
+    #[test]
+    fn lex_dice_keeping_or_dropping() {
+        check("4d6dl1", TokenKind::Dice);
+        check("2d20kh1", TokenKind::Dice);
+        check("d20kl1", TokenKind::Dice);
+        check("8d6dh12", TokenKind::Dice);
+    }
+
     #[test]
     fn lex_alphabetic_identifier() {
         check("abcd", TokenKind::Ident);
@@ -88,6 +200,11 @@ mod tests {
         check("ab123cde456", TokenKind::Ident);
     }
 
+    #[test]
+    fn lex_snake_case_identifier() {
+        check("second_roll", TokenKind::Ident);
+    }
+
     #[test]
     fn lex_mixed_case_identifier() {
         check("ABCdef", TokenKind::Ident);
@@ -118,13 +235,137 @@ mod tests {
         check("-", TokenKind::Minus);
     }
 
+    #[test]
+    fn lex_comma() {
+        check(",", TokenKind::Comma);
+    }
+
+    #[test]
+    fn lex_double_quoted_string() {
+        check(r#""Fire attack""#, TokenKind::String);
+    }
+
+    #[test]
+    fn lex_single_quoted_string() {
+        check("'Ability Scores'", TokenKind::String);
+    }
+
+    #[test]
+    fn lex_empty_string() {
+        check(r#""""#, TokenKind::String);
+    }
+
+    #[test]
+    fn lex_string_with_escaped_quote() {
+        check(r#""say \"hi\"""#, TokenKind::String);
+    }
+
+    #[test]
+    fn lex_colon_colon() {
+        check("::", TokenKind::ColonColon);
+    }
+
+    #[test]
+    fn lex_colon_equals() {
+        check(":=", TokenKind::ColonEquals);
+    }
+
+    #[test]
+    fn lex_colon() {
+        check(":", TokenKind::Colon);
+    }
+
+    #[test]
+    fn lex_dot() {
+        check(".", TokenKind::Dot);
+    }
+
+    #[test]
+    fn lex_arrow() {
+        check("->", TokenKind::Arrow);
+    }
+
+    #[test]
+    fn lex_left_brace() {
+        check("{", TokenKind::LBrace);
+    }
+
+    #[test]
+    fn lex_right_brace() {
+        check("}", TokenKind::RBrace);
+    }
+
+    #[test]
+    fn lex_left_bracket() {
+        check("[", TokenKind::LBracket);
+    }
+
+    #[test]
+    fn lex_right_bracket() {
+        check("]", TokenKind::RBracket);
+    }
+
+    #[test]
+    fn lex_semicolon() {
+        check(";", TokenKind::Semicolon);
+    }
+
+    #[test]
+    fn lex_less_than() {
+        check("<", TokenKind::Lt);
+    }
+
+    #[test]
+    fn lex_greater_than() {
+        check(">", TokenKind::Gt);
+    }
+
     #[test]
     fn lex_comment() {
         check("# foo", TokenKind::Comment);
     }
 
     #[test]
-    fn lex_newlines() {
-        check(" \n ", TokenKind::Whitespace);
+    fn lex_line_comment() {
+        check("// foo", TokenKind::Comment);
+    }
+
+    #[test]
+    fn lex_block_comment() {
+        check("/* foo\n * bar **/", TokenKind::Comment);
+    }
+
+    #[test]
+    fn lex_unclosed_block_comment() {
+        let mut lexer = Lexer::new("/* foo");
+        assert_eq!(lexer.next().unwrap().kind, Err(()));
+    }
+
+    #[test]
+    fn lex_newline() {
+        check("\n", TokenKind::Newline);
+    }
+
+    #[test]
+    fn lex_windows_newline() {
+        check("\r\n", TokenKind::Newline);
+    }
+
+    #[test]
+    fn lex_tabs() {
+        check("\t \t", TokenKind::Whitespace);
+    }
+
+    #[test]
+    fn lex_spaces_and_newlines_apart() {
+        let kinds: Vec<_> = Lexer::new(" \n ").map(|token| token.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                Ok(TokenKind::Whitespace),
+                Ok(TokenKind::Newline),
+                Ok(TokenKind::Whitespace)
+            ]
+        );
     }
 }
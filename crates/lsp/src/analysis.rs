@@ -0,0 +1,745 @@
+use crate::line_index::LineIndex;
+use ast::{Expr, Root, Stmt};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, Diagnostic, DiagnosticSeverity, DocumentSymbol,
+    Documentation, Hover, HoverContents, MarkupContent, MarkupKind, NumberOrString, Position,
+    Range, SymbolKind,
+};
+use rollang::{Dist, Env, Val};
+use std::collections::HashSet;
+use syntax::{SyntaxKind, SyntaxNode, SyntaxToken};
+use text_size::TextSize;
+
+// Everything here works on the whole text of a document, parsed afresh for every request;
+// `.roll` files are small enough for that to be instant
+
+pub(crate) fn diagnostics(text: &str) -> Vec<Diagnostic> {
+    let index = LineIndex::new(text);
+    let parse = parser::parse(text);
+    let root = Root::cast(parse.syntax()).unwrap();
+
+    let error = |range, message| Diagnostic {
+        range: index.range(range),
+        severity: Some(DiagnosticSeverity::ERROR),
+        source: Some("rollang".to_string()),
+        message,
+        ..Diagnostic::default()
+    };
+
+    let mut diagnostics: Vec<_> = parse
+        .errors()
+        .iter()
+        .map(|parse_error| error(parse_error.range, parse_error.message()))
+        .chain(
+            rollang::check(&root, &Env::with_seed(0))
+                .into_iter()
+                .map(|check_error| error(check_error.range, check_error.message)),
+        )
+        .collect();
+
+    // Lints of code that doesn't parse would mostly be about what's missing
+    if parse.errors().is_empty() {
+        let lints = rollang::lint(&root, &Env::with_seed(0))
+            .into_iter()
+            .map(|lint| Diagnostic {
+                range: index.range(lint.range),
+                severity: Some(DiagnosticSeverity::WARNING),
+                code: Some(NumberOrString::String(lint.name.to_string())),
+                source: Some("rollang".to_string()),
+                message: match lint.help {
+                    Some(help) => format!("{}; {}", lint.message, help),
+                    None => lint.message,
+                },
+                ..Diagnostic::default()
+            });
+        diagnostics.extend(lints);
+    }
+
+    diagnostics
+}
+
+pub(crate) fn hover(text: &str, position: Position) -> Option<Hover> {
+    let index = LineIndex::new(text);
+    let root = Root::cast(parser::parse(text).syntax()).unwrap();
+    let token = token_at(root.syntax(), index.offset(position))?;
+
+    let (range, value) = match token.kind() {
+        SyntaxKind::Ident => (token.text_range(), describe_name(&root, &token)?),
+        SyntaxKind::Dice | SyntaxKind::Number => {
+            let expr = formula_expr(&token)?;
+            (expr.text_range(), describe_formula(&root, &expr)?)
+        }
+        _ => return None,
+    };
+
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }),
+        range: Some(index.range(range)),
+    })
+}
+
+// Where the name at `position` is defined, if it's defined in the document
+pub(crate) fn definition(text: &str, position: Position) -> Option<Range> {
+    let index = LineIndex::new(text);
+    let root = Root::cast(parser::parse(text).syntax()).unwrap();
+    let token = token_at(root.syntax(), index.offset(position))?;
+
+    if token.kind() != SyntaxKind::Ident {
+        return None;
+    }
+    Some(index.range(find_def(root.syntax(), &token)?.name.text_range()))
+}
+
+pub(crate) fn completions(text: &str, position: Position) -> Vec<CompletionItem> {
+    let index = LineIndex::new(text);
+    let root = Root::cast(parser::parse(text).syntax()).unwrap();
+    let mut items: Vec<CompletionItem> = Vec::new();
+    let mut add = |item: CompletionItem| {
+        if !items.iter().any(|existing| existing.label == item.label) {
+            items.push(item);
+        }
+    };
+
+    for def in visible_defs(root.syntax(), index.offset(position)) {
+        add(CompletionItem {
+            label: def.name.text().to_string(),
+            kind: Some(def.kind.completion_kind()),
+            detail: def.detail,
+            ..CompletionItem::default()
+        });
+    }
+
+    for (name, val) in Env::with_seed(0).bindings() {
+        add(CompletionItem {
+            label: name.to_string(),
+            kind: Some(CompletionItemKind::CONSTANT),
+            detail: Some(val.ty().to_string()),
+            ..CompletionItem::default()
+        });
+    }
+
+    for builtin in rollang::builtins() {
+        add(CompletionItem {
+            label: builtin.name.to_string(),
+            kind: Some(CompletionItemKind::FUNCTION),
+            detail: builtin.signatures().first().map(|sig| format!("fn{}", sig)),
+            documentation: Some(Documentation::String(builtin.doc.to_string())),
+            ..CompletionItem::default()
+        });
+    }
+
+    for keyword in SyntaxKind::KEYWORDS
+        .into_iter()
+        .filter_map(SyntaxKind::keyword_text)
+    {
+        add(CompletionItem {
+            label: keyword.to_string(),
+            kind: Some(CompletionItemKind::KEYWORD),
+            ..CompletionItem::default()
+        });
+    }
+
+    items
+}
+
+// The outline of a document: its top-level definitions, with the fields of composites
+pub(crate) fn symbols(text: &str) -> Vec<DocumentSymbol> {
+    let index = LineIndex::new(text);
+    let root = Root::cast(parser::parse(text).syntax()).unwrap();
+
+    root.stmts()
+        .filter_map(|stmt| {
+            let (name, kind, children) = match &stmt {
+                Stmt::VariableDef(def) => (def.name()?, SymbolKind::VARIABLE, None),
+                Stmt::FnDef(def) => (def.name()?, SymbolKind::FUNCTION, None),
+                Stmt::ModDef(def) => (def.name()?, SymbolKind::OPERATOR, None),
+                Stmt::CompDef(def) => {
+                    let fields = def
+                        .fields()
+                        .filter_map(|field| {
+                            let name = field.name()?;
+                            Some(symbol(
+                                &index,
+                                &name,
+                                field.syntax(),
+                                SymbolKind::FIELD,
+                                None,
+                            ))
+                        })
+                        .collect();
+                    (def.name()?, SymbolKind::STRUCT, Some(fields))
+                }
+                _ => return None,
+            };
+
+            Some(symbol(&index, &name, stmt.syntax(), kind, children))
+        })
+        .collect()
+}
+
+fn symbol(
+    index: &LineIndex,
+    name: &SyntaxToken,
+    node: &SyntaxNode,
+    kind: SymbolKind,
+    children: Option<Vec<DocumentSymbol>>,
+) -> DocumentSymbol {
+    // `deprecated` is itself deprecated, but still has to be given
+    #[allow(deprecated)]
+    DocumentSymbol {
+        name: name.text().to_string(),
+        detail: None,
+        kind,
+        tags: None,
+        deprecated: None,
+        range: index.range(trimmed_range(node)),
+        selection_range: index.range(name.text_range()),
+        children,
+    }
+}
+
+// Nodes take the whitespace after them along, which editors shouldn't highlight
+fn trimmed_range(node: &SyntaxNode) -> text_size::TextRange {
+    let end = node
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !token.kind().is_trivia())
+        .last()
+        .map_or(node.text_range().end(), |token| token.text_range().end());
+
+    text_size::TextRange::new(node.text_range().start(), end)
+}
+
+// Names and dice win over whatever they touch, so that hovering right after `x` in `x+1` still
+// finds `x`
+fn token_at(root: &SyntaxNode, offset: TextSize) -> Option<SyntaxToken> {
+    let tokens: Vec<_> = root.token_at_offset(offset).collect();
+
+    tokens
+        .iter()
+        .find(|token| {
+            matches!(
+                token.kind(),
+                SyntaxKind::Ident | SyntaxKind::Dice | SyntaxKind::Number
+            )
+        })
+        .or_else(|| tokens.last())
+        .cloned()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DefKind {
+    Variable,
+    Function,
+    Modifier,
+    Comp,
+    Param,
+}
+
+impl DefKind {
+    fn completion_kind(self) -> CompletionItemKind {
+        match self {
+            Self::Variable | Self::Param => CompletionItemKind::VARIABLE,
+            Self::Function | Self::Modifier => CompletionItemKind::FUNCTION,
+            Self::Comp => CompletionItemKind::STRUCT,
+        }
+    }
+}
+
+struct Def {
+    name: SyntaxToken,
+    kind: DefKind,
+    // The declared type of a parameter
+    detail: Option<String>,
+}
+
+// Every definition that can be used at `offset`, innermost and latest first. Definitions only
+// count once they're complete, since the spec only allows forward declarations.
+fn visible_defs(root: &SyntaxNode, offset: TextSize) -> Vec<Def> {
+    let start = root
+        .token_at_offset(offset)
+        .left_biased()
+        .and_then(|token| token.parent())
+        .unwrap_or_else(|| root.clone());
+
+    let mut defs = Vec::new();
+
+    for node in start.ancestors() {
+        match node.kind() {
+            SyntaxKind::Root | SyntaxKind::Block => {
+                let before = node
+                    .children()
+                    .filter(|child| child.text_range().end() <= offset)
+                    .collect::<Vec<_>>();
+
+                for child in before.into_iter().rev() {
+                    defs.extend(stmt_def(child));
+                }
+            }
+            SyntaxKind::FnDef | SyntaxKind::ModDef => {
+                let params = node
+                    .descendants()
+                    .filter(|param| param.kind() == SyntaxKind::Param)
+                    .filter_map(|param| param_def(&param));
+                defs.extend(params);
+                defs.extend(stmt_def(node));
+            }
+            _ => {}
+        }
+    }
+
+    defs
+}
+
+fn stmt_def(node: SyntaxNode) -> Option<Def> {
+    let (name, kind) = match Stmt::cast(node)? {
+        Stmt::VariableDef(def) => (def.name()?, DefKind::Variable),
+        Stmt::FnDef(def) => (def.name()?, DefKind::Function),
+        Stmt::ModDef(def) => (def.name()?, DefKind::Modifier),
+        Stmt::CompDef(def) => (def.name()?, DefKind::Comp),
+        _ => return None,
+    };
+
+    Some(Def {
+        name,
+        kind,
+        detail: None,
+    })
+}
+
+// Parameters, and the named returns of functions, which are also declared in a `Param`
+fn param_def(param: &SyntaxNode) -> Option<Def> {
+    let name = param
+        .children_with_tokens()
+        .filter_map(|element| element.into_token())
+        .find(|token| token.kind() == SyntaxKind::Ident)?;
+    let ty = param
+        .children()
+        .find(|child| child.kind() == SyntaxKind::TypeExpr)
+        .map(|ty| ty.text().to_string().trim().to_string());
+
+    Some(Def {
+        name,
+        kind: DefKind::Param,
+        detail: ty,
+    })
+}
+
+// The definition a name refers to, or the definition it is itself the name of
+fn find_def(root: &SyntaxNode, token: &SyntaxToken) -> Option<Def> {
+    let parent = token.parent()?;
+
+    if matches!(
+        parent.kind(),
+        SyntaxKind::VariableDef
+            | SyntaxKind::FnDef
+            | SyntaxKind::ModDef
+            | SyntaxKind::CompDef
+            | SyntaxKind::Param
+    ) {
+        let def = if parent.kind() == SyntaxKind::Param {
+            param_def(&parent)
+        } else {
+            stmt_def(parent)
+        };
+        return def.filter(|def| def.name == *token);
+    }
+
+    visible_defs(root, token.text_range().start())
+        .into_iter()
+        .find(|def| def.name.text() == token.text())
+}
+
+fn describe_name(root: &Root, token: &SyntaxToken) -> Option<String> {
+    let name = token.text();
+
+    if let Some(def) = find_def(root.syntax(), token) {
+        if def.kind == DefKind::Param {
+            return Some(code(&format!("{}: {}", name, def.detail?)));
+        }
+    }
+
+    // A definition's own name is described by what it defines
+    let defines = token.parent().is_some_and(|parent| {
+        matches!(
+            parent.kind(),
+            SyntaxKind::VariableDef | SyntaxKind::FnDef | SyntaxKind::ModDef | SyntaxKind::CompDef
+        )
+    });
+    let known = known_at(root, token.text_range().start(), defines);
+    let env = &known.env;
+
+    let val = env.bindings().into_iter().find(|(n, _)| *n == name);
+    if let Some((_, val)) = val.filter(|_| !known.unknown.contains(name)) {
+        let mut description = match val {
+            Val::Comp(_) => code(&format!("{}: {}", name, val.ty())),
+            _ => code(&format!("{}: {} = {}", name, val.ty(), val)),
+        };
+        if let Val::Formula(formula) = val {
+            if let Ok(dist) = Dist::of(formula) {
+                description += &summary(&dist);
+            }
+        }
+        return Some(description);
+    }
+
+    if let Some((_, signature)) = env.functions().into_iter().find(|(n, _)| *n == name) {
+        return Some(code(&format!("{} :: fn{}", name, signature)));
+    }
+
+    if let Some((_, signature)) = env.modifiers().into_iter().find(|(n, _)| *n == name) {
+        return Some(code(&format!(
+            "{} :: mod{}",
+            name,
+            signature.params_to_string()
+        )));
+    }
+
+    // A binding whose value isn't known without rolling or calling something, or a local
+    if let Some(ty) = rollang::name_types(root, &Env::with_seed(0)).get(&token.text_range()) {
+        return Some(code(&format!("{}: {}", name, ty)));
+    }
+
+    let builtin = rollang::builtin(name)?;
+    let signatures: Vec<_> = builtin
+        .signatures()
+        .iter()
+        .map(|signature| format!("{} :: fn{}", name, signature))
+        .collect();
+    Some(code(&signatures.join("\n")) + builtin.doc)
+}
+
+// `1d20+4` as a whole rather than just `1d20` or `4`
+fn formula_expr(token: &SyntaxToken) -> Option<SyntaxNode> {
+    let is_formula = |node: &SyntaxNode| {
+        matches!(
+            node.kind(),
+            SyntaxKind::DiceExpr
+                | SyntaxKind::Literal
+                | SyntaxKind::InfixExpr
+                | SyntaxKind::PrefixExpr
+                | SyntaxKind::ParenExpr
+        )
+    };
+
+    let expr = token.parent()?.ancestors().take_while(is_formula).last()?;
+    // Plain numbers aren't worth a hover of their own
+    expr.descendants()
+        .any(|node| node.kind() == SyntaxKind::DiceExpr)
+        .then_some(expr)
+}
+
+fn describe_formula(root: &Root, expr: &SyntaxNode) -> Option<String> {
+    let mut known = known_at(root, expr.text_range().start(), false);
+    if !known.can_eval(expr) {
+        return None;
+    }
+    let Val::Formula(formula) =
+        rollang::eval_stmt(&Stmt::cast(expr.clone())?, &mut known.env).ok()?
+    else {
+        return None;
+    };
+
+    let dist = Dist::of(&formula).ok()?;
+    Some(code(&formula.to_string()) + &summary(&dist))
+}
+
+// What can be known about the top-level bindings before `offset`, or at it when `inclusive` is
+// set, without rolling anything or running any of the script's functions
+struct Known {
+    env: Env,
+    // Bindings whose value depends on something that wasn't run
+    unknown: HashSet<String>,
+}
+
+impl Known {
+    // Whether evaluating `node` can't roll dice, call anything, or use a value that isn't known
+    fn can_eval(&self, node: &SyntaxNode) -> bool {
+        node.descendants().all(|node| match Expr::cast(node) {
+            Some(Expr::RollExpr(_) | Expr::CallExpr(_)) => false,
+            Some(Expr::VariableRef(var)) => var.name().is_some_and(|name| {
+                let name = name.text();
+                !self.unknown.contains(name)
+                    && self.env.bindings().iter().any(|(bound, _)| *bound == name)
+            }),
+            _ => true,
+        })
+    }
+}
+
+fn known_at(root: &Root, offset: TextSize, inclusive: bool) -> Known {
+    let mut known = Known {
+        env: Env::with_seed(0),
+        unknown: HashSet::new(),
+    };
+
+    // Globals a function or modifier body assigns to could change whenever one is run
+    let assigned_in_bodies: Vec<String> = root
+        .syntax()
+        .descendants()
+        .filter(|node| matches!(node.kind(), SyntaxKind::FnDef | SyntaxKind::ModDef))
+        .flat_map(|def| def.descendants())
+        .filter_map(|node| match Stmt::cast(node)? {
+            Stmt::Assignment(assignment) => Some(assignment.name()?.text().to_string()),
+            _ => None,
+        })
+        .collect();
+
+    for stmt in root.stmts() {
+        let range = stmt.syntax().text_range();
+        let runs = range.end() <= offset || (inclusive && range.contains(offset));
+        if !runs {
+            break;
+        }
+
+        let name = match &stmt {
+            Stmt::VariableDef(def) => def.name(),
+            Stmt::Assignment(assignment) => assignment.name(),
+            Stmt::CompDef(def) => def.name(),
+            // Defining a function doesn't run its body
+            Stmt::FnDef(_) | Stmt::ModDef(_) => {
+                let _ = rollang::eval_stmt(&stmt, &mut known.env);
+                continue;
+            }
+            // Other statements can't change a binding unless they run something
+            _ => {
+                if !known.can_eval(stmt.syntax()) {
+                    known.unknown.extend(assigned_in_bodies.iter().cloned());
+                }
+                continue;
+            }
+        };
+
+        if known.can_eval(stmt.syntax()) && rollang::eval_stmt(&stmt, &mut known.env).is_ok() {
+            if let Some(name) = name {
+                known.unknown.remove(name.text());
+            }
+        } else {
+            known
+                .unknown
+                .extend(name.map(|name| name.text().to_string()));
+            known.unknown.extend(assigned_in_bodies.iter().cloned());
+        }
+    }
+
+    known
+}
+
+fn summary(dist: &Dist) -> String {
+    format!(
+        "min {}, max {}, mean {:.2}",
+        dist.min(),
+        dist.max(),
+        dist.mean()
+    )
+}
+
+fn code(text: &str) -> String {
+    format!("```rollang\n{}\n```\n", text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `|` marks the cursor
+    fn at_cursor(text: &str) -> (String, Position) {
+        let offset = text.find('|').unwrap();
+        let text = text.replacen('|', "", 1);
+        let position = LineIndex::new(&text).position(TextSize::try_from(offset).unwrap());
+        (text, position)
+    }
+
+    fn check_hover(text: &str, expected: &str) {
+        let (text, position) = at_cursor(text);
+        let Some(Hover {
+            contents: HoverContents::Markup(markup),
+            ..
+        }) = hover(&text, position)
+        else {
+            panic!("no hover");
+        };
+        assert_eq!(markup.value, expected);
+    }
+
+    #[test]
+    fn diagnostics_of_parse_and_check_errors() {
+        let diagnostics = diagnostics("f :: fn(x: nmber) { x }\n(1d20");
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+
+        assert_eq!(
+            messages,
+            [
+                "expected ‘:’, ‘.’, ‘[’, ‘+’, ‘-’, ‘*’, ‘/’ or ‘)’",
+                "unknown type `nmber`"
+            ]
+        );
+        assert_eq!(diagnostics[1].range.start, Position::new(0, 11));
+    }
+
+    #[test]
+    fn diagnostics_of_lints() {
+        let diagnostics = diagnostics(
+            "ac := 15
+roll 3d1",
+        );
+        let lints: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.code.clone(), d.severity))
+            .collect();
+
+        assert_eq!(
+            lints,
+            [
+                (
+                    Some(NumberOrString::String("unused-binding".to_string())),
+                    Some(DiagnosticSeverity::WARNING)
+                ),
+                (
+                    Some(NumberOrString::String("impossible-dice".to_string())),
+                    Some(DiagnosticSeverity::WARNING)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn hover_binding() {
+        check_hover(
+            "atk := 1d20+4\nroll at|k",
+            "```rollang\natk: dice = 1d20+4\n```\nmin 5, max 24, mean 14.50",
+        );
+    }
+
+    #[test]
+    fn hover_definition_name() {
+        check_hover("bon|us := 2 * 3", "```rollang\nbonus: number = 6\n```\n");
+    }
+
+    #[test]
+    fn hover_dice() {
+        check_hover(
+            "roll 2d|6 + 3",
+            "```rollang\n2d6+3\n```\nmin 5, max 15, mean 10.00",
+        );
+    }
+
+    #[test]
+    fn hover_param() {
+        check_hover(
+            "f :: fn(a: number) -> number { a| + 1 }",
+            "```rollang\na: number\n```\n",
+        );
+    }
+
+    #[test]
+    fn hover_function() {
+        check_hover(
+            "f :: fn(a: number) -> number { a + 1 }\nf|(2)",
+            "```rollang\nf :: fn(number) -> number\n```\n",
+        );
+    }
+
+    // Hovering mustn't roll dice or run the script's functions
+    #[test]
+    fn hover_rolled_binding() {
+        check_hover("atk := roll d20\nroll at|k", "```rollang\natk: roll\n```\n");
+    }
+
+    #[test]
+    fn hover_result_of_call() {
+        check_hover(
+            "f :: fn() -> number { print(\"called\"); f() }\nx := f()\nroll |x",
+            "```rollang\nx: number\n```\n",
+        );
+    }
+
+    #[test]
+    fn hover_global_a_function_assigns() {
+        let (text, position) = at_cursor("hp := 10\nhit :: fn() { hp = hp - 2 }\nhit()\nh|p");
+        assert_eq!(hover(&text, position), None);
+    }
+
+    #[test]
+    fn hover_builtin() {
+        check_hover(
+            "ab|s -3",
+            "```rollang\nabs :: fn(number) -> number\n```\nabsolute value of a number",
+        );
+    }
+
+    #[test]
+    fn definition_of_variable() {
+        let (text, position) = at_cursor("hp := 2d8\nx := 1\nroll h|p");
+        assert_eq!(
+            definition(&text, position),
+            Some(Range::new(Position::new(0, 0), Position::new(0, 2)))
+        );
+    }
+
+    #[test]
+    fn definition_of_redefined_variable_is_the_earlier_one() {
+        let (text, position) = at_cursor("x := 1\nx := x| + 1");
+        assert_eq!(
+            definition(&text, position),
+            Some(Range::new(Position::new(0, 0), Position::new(0, 1)))
+        );
+    }
+
+    #[test]
+    fn definition_of_param() {
+        let (text, position) = at_cursor("f :: fn(a: number) -> number { a| }");
+        assert_eq!(
+            definition(&text, position),
+            Some(Range::new(Position::new(0, 8), Position::new(0, 9)))
+        );
+    }
+
+    #[test]
+    fn definition_of_undefined() {
+        let (text, position) = at_cursor("roll m|issing");
+        assert_eq!(definition(&text, position), None);
+    }
+
+    #[test]
+    fn complete_bindings_and_builtins() {
+        let (text, position) = at_cursor("hp := 2d8\nf :: fn(a: number) { | }\nlater := 1");
+        let items = completions(&text, position);
+        let labels: Vec<_> = items.iter().map(|item| item.label.as_str()).collect();
+
+        assert!(labels.contains(&"hp"));
+        assert!(labels.contains(&"a"));
+        assert!(labels.contains(&"f"));
+        assert!(labels.contains(&"abs"));
+        assert!(labels.contains(&"pass"));
+        assert!(labels.contains(&"roll"));
+        assert!(labels.contains(&"mod"));
+        assert!(!labels.contains(&"later"));
+    }
+
+    #[test]
+    fn symbols_of_definitions() {
+        let symbols = symbols("hp := 2d8\nOrc :: comp(hit: d20, damage: 1d12)\nroll Orc");
+        let names: Vec<_> = symbols
+            .iter()
+            .map(|symbol| (symbol.name.as_str(), symbol.kind))
+            .collect();
+
+        assert_eq!(
+            names,
+            [("hp", SymbolKind::VARIABLE), ("Orc", SymbolKind::STRUCT)]
+        );
+        assert_eq!(symbols[0].range.end, Position::new(0, 9));
+
+        let fields: Vec<_> = symbols[1]
+            .children
+            .iter()
+            .flatten()
+            .map(|field| field.name.as_str())
+            .collect();
+        assert_eq!(fields, ["hit", "damage"]);
+    }
+}
@@ -0,0 +1,94 @@
+use lsp_types::{Position, Range};
+use text_size::{TextRange, TextSize};
+
+// Converts between the byte offsets of the syntax tree and the line and UTF-16 column positions
+// editors talk in
+pub(crate) struct LineIndex<'a> {
+    text: &'a str,
+    // The offset of the start of every line
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+
+        Self { text, line_starts }
+    }
+
+    pub(crate) fn position(&self, offset: TextSize) -> Position {
+        let offset = usize::from(offset).min(self.text.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let character = self.text[self.line_starts[line]..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum::<usize>();
+
+        Position::new(line as u32, character as u32)
+    }
+
+    pub(crate) fn range(&self, range: TextRange) -> Range {
+        Range::new(self.position(range.start()), self.position(range.end()))
+    }
+
+    // Positions past the end of a line or of the text are clamped to it
+    pub(crate) fn offset(&self, position: Position) -> TextSize {
+        let Some(&line_start) = self.line_starts.get(position.line as usize) else {
+            return TextSize::of(self.text);
+        };
+        let line_end = self
+            .line_starts
+            .get(position.line as usize + 1)
+            .map_or(self.text.len(), |next| next - 1);
+
+        let mut utf16 = 0;
+        let mut offset = line_start;
+        for c in self.text[line_start..line_end].chars() {
+            if utf16 >= position.character as usize {
+                break;
+            }
+            utf16 += c.len_utf16();
+            offset += c.len_utf8();
+        }
+
+        TextSize::try_from(offset).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn position_on_later_line() {
+        let index = LineIndex::new("a := 1\nb := 2d6");
+        assert_eq!(index.position(12.into()), Position::new(1, 5));
+    }
+
+    #[test]
+    fn position_counts_utf16() {
+        // `🐉` is two UTF-16 code units and four bytes
+        let index = LineIndex::new("\"🐉\" x");
+        assert_eq!(index.position(7.into()), Position::new(0, 5));
+    }
+
+    #[test]
+    fn offset_round_trips() {
+        let text = "x := \"é\"\ny := 1d20\n";
+        let index = LineIndex::new(text);
+
+        for offset in text.char_indices().map(|(idx, _)| idx) {
+            let offset = TextSize::try_from(offset).unwrap();
+            assert_eq!(index.offset(index.position(offset)), offset);
+        }
+    }
+
+    #[test]
+    fn offset_past_end_of_line() {
+        let index = LineIndex::new("ab\ncd");
+        assert_eq!(index.offset(Position::new(0, 10)), 2.into());
+        assert_eq!(index.offset(Position::new(5, 0)), 5.into());
+    }
+}
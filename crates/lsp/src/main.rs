@@ -0,0 +1,172 @@
+mod analysis;
+mod line_index;
+mod server;
+
+use lsp_server::Connection;
+use server::Server;
+use std::error::Error;
+use std::process::ExitCode;
+
+// A language server for `.roll` files, talking LSP over stdin and stdout
+fn main() -> ExitCode {
+    let (connection, io_threads) = Connection::stdio();
+
+    let result = run(&connection);
+    // The connection has to be dropped before the threads can finish
+    drop(connection);
+    let joined = io_threads.join();
+
+    match result.and(joined.map_err(Into::into)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("rollang-lsp: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(connection: &Connection) -> Result<(), Box<dyn Error>> {
+    let capabilities = serde_json::to_value(server::capabilities())?;
+    connection.initialize(capabilities)?;
+
+    Server::default().run(connection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_server::{Message, Notification, Request, RequestId, Response};
+    use serde_json::{json, Value};
+    use std::thread;
+
+    // Plays the editor's side of a session over an in-memory connection
+    struct Client {
+        connection: Connection,
+        next_id: i32,
+    }
+
+    impl Client {
+        fn request(&mut self, method: &str, params: Value) -> Response {
+            self.next_id += 1;
+            let id = RequestId::from(self.next_id);
+            let request = Request::new(id.clone(), method.to_string(), params);
+            self.connection
+                .sender
+                .send(Message::Request(request))
+                .unwrap();
+
+            match self.connection.receiver.recv().unwrap() {
+                Message::Response(response) if response.id == id => response,
+                message => panic!("expected a response, got {:?}", message),
+            }
+        }
+
+        fn notify(&self, method: &str, params: Value) {
+            let notification = Notification::new(method.to_string(), params);
+            self.connection
+                .sender
+                .send(Message::Notification(notification))
+                .unwrap();
+        }
+
+        fn published(&self) -> Value {
+            match self.connection.receiver.recv().unwrap() {
+                Message::Notification(notification)
+                    if notification.method == "textDocument/publishDiagnostics" =>
+                {
+                    notification.params
+                }
+                message => panic!("expected diagnostics, got {:?}", message),
+            }
+        }
+    }
+
+    #[test]
+    fn session() {
+        let (server, connection) = Connection::memory();
+        let server = thread::spawn(move || run(&server).map_err(|error| error.to_string()));
+        let mut client = Client {
+            connection,
+            next_id: 0,
+        };
+
+        let initialized = client.request("initialize", json!({ "capabilities": {} }));
+        assert_eq!(
+            initialized.result.unwrap()["capabilities"]["hoverProvider"],
+            true
+        );
+        client.notify("initialized", json!({}));
+
+        let uri = "file:///fighter.roll";
+        client.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": {
+                    "uri": uri,
+                    "languageId": "rollang",
+                    "version": 1,
+                    "text": "atk := 1d20+4\nroll atk +",
+                }
+            }),
+        );
+        let published = client.published();
+        assert_eq!(published["uri"], uri);
+        assert_eq!(published["diagnostics"].as_array().unwrap().len(), 1);
+
+        // Nothing is published for a notification that doesn't make sense, and the session
+        // carries on
+        client.notify("textDocument/didChange", json!({ "textDocument": 3 }));
+        client.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": uri, "version": 2 },
+                "contentChanges": [{ "text": "atk := 1d20+4\nroll atk +" }],
+            }),
+        );
+        let published = client.published();
+        assert_eq!(published["uri"], uri);
+        assert_eq!(published["diagnostics"].as_array().unwrap().len(), 1);
+
+        client.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": uri, "version": 3 },
+                "contentChanges": [{ "text": "atk := 1d20+4\nroll atk" }],
+            }),
+        );
+        assert_eq!(client.published()["diagnostics"], json!([]));
+
+        let hover = client.request(
+            "textDocument/hover",
+            json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": 1, "character": 6 },
+            }),
+        );
+        assert_eq!(
+            hover.result.unwrap()["contents"]["value"],
+            "```rollang\natk: dice = 1d20+4\n```\nmin 5, max 24, mean 14.50"
+        );
+
+        let definition = client.request(
+            "textDocument/definition",
+            json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": 1, "character": 6 },
+            }),
+        );
+        assert_eq!(
+            definition.result.unwrap()["range"],
+            json!({
+                "start": { "line": 0, "character": 0 },
+                "end": { "line": 0, "character": 3 },
+            })
+        );
+
+        let shutdown = client.request("shutdown", Value::Null);
+        assert!(shutdown.error.is_none());
+        client.notify("exit", Value::Null);
+
+        assert_eq!(server.join().unwrap(), Ok(()));
+    }
+}
@@ -0,0 +1,177 @@
+use crate::analysis;
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+    PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as _,
+};
+use lsp_types::{
+    CompletionResponse, DocumentSymbolResponse, GotoDefinitionResponse, Location, OneOf,
+    PublishDiagnosticsParams, ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind,
+    Url,
+};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+
+pub(crate) fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(lsp_types::HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(lsp_types::CompletionOptions::default()),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        ..ServerCapabilities::default()
+    }
+}
+
+// The open documents, whole; editors send every change as the full new text
+#[derive(Default)]
+pub(crate) struct Server {
+    documents: HashMap<Url, String>,
+}
+
+impl Server {
+    // Handles messages until the client asks to shut down
+    pub(crate) fn run(&mut self, connection: &Connection) -> Result<(), Box<dyn Error>> {
+        for message in &connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    let response = self.request(request);
+                    connection.sender.send(Message::Response(response))?;
+                }
+                // A notification has no response to carry an error back, so one that doesn't
+                // make sense is logged and skipped rather than ending the session
+                Message::Notification(notification) => {
+                    let method = notification.method.clone();
+                    match self.notification(notification) {
+                        Ok(Some(published)) => {
+                            connection.sender.send(Message::Notification(published))?
+                        }
+                        Ok(None) => {}
+                        Err(error) => eprintln!("rollang-lsp: ignoring `{}`: {}", method, error),
+                    }
+                }
+                Message::Response(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn request(&self, request: Request) -> Response {
+        let id = request.id.clone();
+
+        match self.answer(request) {
+            Ok(result) => Response::new_ok(id, result),
+            Err(message) => {
+                Response::new_err(id, lsp_server::ErrorCode::InvalidParams as i32, message)
+            }
+        }
+    }
+
+    fn answer(&self, request: Request) -> Result<Value, String> {
+        let text = |uri: &Url| {
+            self.documents
+                .get(uri)
+                .ok_or_else(|| format!("`{}` isn't open", uri))
+        };
+
+        let result = match request.method.as_str() {
+            HoverRequest::METHOD => {
+                let (_, params) = parse::<HoverRequest>(request)?;
+                let position = params.text_document_position_params;
+                let text = text(&position.text_document.uri)?;
+                serde_json::to_value(analysis::hover(text, position.position))
+            }
+            GotoDefinition::METHOD => {
+                let (_, params) = parse::<GotoDefinition>(request)?;
+                let position = params.text_document_position_params;
+                let uri = position.text_document.uri;
+                let range = analysis::definition(text(&uri)?, position.position);
+                serde_json::to_value(
+                    range.map(|range| GotoDefinitionResponse::Scalar(Location { uri, range })),
+                )
+            }
+            Completion::METHOD => {
+                let (_, params) = parse::<Completion>(request)?;
+                let position = params.text_document_position;
+                let text = text(&position.text_document.uri)?;
+                serde_json::to_value(CompletionResponse::Array(analysis::completions(
+                    text,
+                    position.position,
+                )))
+            }
+            DocumentSymbolRequest::METHOD => {
+                let (_, params) = parse::<DocumentSymbolRequest>(request)?;
+                let text = text(&params.text_document.uri)?;
+                serde_json::to_value(DocumentSymbolResponse::Nested(analysis::symbols(text)))
+            }
+            method => return Err(format!("unsupported request `{}`", method)),
+        };
+
+        result.map_err(|error| error.to_string())
+    }
+
+    // Keeps track of documents, and gives back the diagnostics to publish for the one that
+    // changed
+    fn notification(
+        &mut self,
+        notification: Notification,
+    ) -> Result<Option<Notification>, serde_json::Error> {
+        let uri = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: lsp_types::DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let document = params.text_document;
+                self.documents.insert(document.uri.clone(), document.text);
+                document.uri
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: lsp_types::DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.documents.insert(uri.clone(), change.text);
+                }
+                uri
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: lsp_types::DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.documents.remove(&params.text_document.uri);
+                // Clear what was published for it
+                return Ok(Some(publish(params.text_document.uri, Vec::new())));
+            }
+            _ => return Ok(None),
+        };
+
+        let diagnostics = self
+            .documents
+            .get(&uri)
+            .map_or_else(Vec::new, |text| analysis::diagnostics(text));
+        Ok(Some(publish(uri, diagnostics)))
+    }
+}
+
+fn parse<R: lsp_types::request::Request>(
+    request: Request,
+) -> Result<(RequestId, R::Params), String> {
+    request
+        .extract(R::METHOD)
+        .map_err(|error| format!("{:?}", error))
+}
+
+fn publish(uri: Url, diagnostics: Vec<lsp_types::Diagnostic>) -> Notification {
+    let params = PublishDiagnosticsParams {
+        uri,
+        diagnostics,
+        version: None,
+    };
+    Notification::new(PublishDiagnostics::METHOD.to_string(), params)
+}
@@ -1,3 +1,4 @@
+use crate::parser::ParseError;
 use syntax::SyntaxKind;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -7,6 +8,9 @@ pub(crate) enum Event {
         forward_parent: Option<usize>,
     },
     AddToken,
+    // Adds the next token with a different kind, for contextual keywords
+    AddTokenAs(SyntaxKind),
     FinishNode,
+    Error(ParseError),
     Placeholder,
 }
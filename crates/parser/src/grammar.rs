@@ -8,9 +8,7 @@ use syntax::SyntaxKind;
 pub(crate) fn root(p: &mut Parser) -> CompletedMarker {
     let m = p.start();
 
-    while !p.at_end() {
-        stmt::stmt(p);
-    }
+    stmt::stmts(p, false);
 
     m.complete(p, SyntaxKind::Root)
 }
@@ -17,16 +17,28 @@ impl InfixOp {
 
 enum UnaryOp {
     Neg,
+    Roll,
+    Crit,
 }
 
 impl UnaryOp {
     fn binding_power(&self) -> ((), u8) {
         match self {
             Self::Neg => ((), 5),
+            // `roll 3d8+4` and `crit 3d8+4` apply to the whole expression that follows
+            Self::Roll | Self::Crit => ((), 1),
         }
     }
 }
 
+// The `:` binder attaches a modifier to the operand right before it, so `-d20:adv+2` is
+// `(-(d20:adv))+2`
+const MOD_BINDING_POWER: u8 = 7;
+
+// Field access and indexing bind tighter still, so `roll Dragon.stomp` rolls the field and `-x.a`
+// is `-(x.a)`
+const FIELD_BINDING_POWER: u8 = 9;
+
 pub(super) fn expr(p: &mut Parser) -> Option<CompletedMarker> {
     expr_binding_power(p, 0)
 }
@@ -35,12 +47,51 @@ fn expr_binding_power(p: &mut Parser, minimum_binding_power: u8) -> Option<Compl
     let mut lhs = lhs(p)?;
 
     loop {
-        let op = match p.peek() {
-            Some(Ok(SyntaxKind::Plus)) => InfixOp::Add,
-            Some(Ok(SyntaxKind::Minus)) => InfixOp::Sub,
-            Some(Ok(SyntaxKind::Star)) => InfixOp::Mul,
-            Some(Ok(SyntaxKind::Slash)) => InfixOp::Div,
-            _ => break, // We'tl handle errors later
+        // Whatever is on the next line is another statement, unless we're inside brackets
+        if p.at_line_break() {
+            break;
+        }
+
+        if p.at(Ok(SyntaxKind::Colon)) {
+            if MOD_BINDING_POWER < minimum_binding_power {
+                break;
+            }
+
+            lhs = mod_expr(p, lhs);
+            continue;
+        }
+
+        if p.at(Ok(SyntaxKind::Dot)) {
+            if FIELD_BINDING_POWER < minimum_binding_power {
+                break;
+            }
+
+            lhs = field_expr(p, lhs);
+            continue;
+        }
+
+        // On a new line, a `[` starts an array rather than indexing the line before
+        if !p.at_line_start() && p.at(Ok(SyntaxKind::LBracket)) {
+            if FIELD_BINDING_POWER < minimum_binding_power {
+                break;
+            }
+
+            lhs = index_expr(p, lhs);
+            continue;
+        }
+
+        let op = if p.at(Ok(SyntaxKind::Plus)) {
+            InfixOp::Add
+        } else if p.at(Ok(SyntaxKind::Minus)) {
+            InfixOp::Sub
+        } else if p.at(Ok(SyntaxKind::Star)) {
+            InfixOp::Mul
+        } else if p.at(Ok(SyntaxKind::Slash)) {
+            InfixOp::Div
+        } else {
+            // We're not at an operator; we don't know what to do next, so we return and let the
+            // caller decide.
+            break;
         };
 
         let (left_binding_power, right_binding_power) = op.binding_power();
@@ -53,23 +104,39 @@ fn expr_binding_power(p: &mut Parser, minimum_binding_power: u8) -> Option<Compl
         p.bump();
 
         let m = lhs.precede(p);
-        expr_binding_power(p, right_binding_power);
+        let parsed_rhs = expr_binding_power(p, right_binding_power).is_some();
         lhs = m.complete(p, SyntaxKind::InfixExpr);
+
+        if !parsed_rhs {
+            break;
+        }
     }
 
     Some(lhs)
 }
 
 fn lhs(p: &mut Parser) -> Option<CompletedMarker> {
-    let cm = match p.peek() {
-        Some(Ok(SyntaxKind::Number)) => literal(p),
-        Some(Ok(SyntaxKind::Ident)) => variable_ref(p),
-        Some(Ok(SyntaxKind::Minus)) => prefix_expr(p),
-        Some(Ok(SyntaxKind::LParen)) => paren_expr(p),
-        _ => {
-            p.error();
-            return None;
-        } // we'tl handle errors later...
+    let cm = if p.at(Ok(SyntaxKind::Number)) {
+        literal(p)
+    } else if p.at(Ok(SyntaxKind::Dice)) {
+        dice_expr(p)
+    } else if p.at(Ok(SyntaxKind::String)) {
+        string_literal(p)
+    } else if p.at(Ok(SyntaxKind::Ident)) {
+        variable_ref_or_call(p)
+    } else if p.at(Ok(SyntaxKind::RollKw)) {
+        keyword_expr(p, UnaryOp::Roll, SyntaxKind::RollExpr)
+    } else if p.at(Ok(SyntaxKind::CritKw)) {
+        keyword_expr(p, UnaryOp::Crit, SyntaxKind::CritExpr)
+    } else if p.at(Ok(SyntaxKind::Minus)) {
+        prefix_expr(p)
+    } else if p.at(Ok(SyntaxKind::LParen)) {
+        paren_expr(p)
+    } else if p.at(Ok(SyntaxKind::LBracket)) {
+        array_expr(p)
+    } else {
+        p.error();
+        return None;
     };
 
     Some(cm)
@@ -83,12 +150,130 @@ fn literal(p: &mut Parser) -> CompletedMarker {
     m.complete(p, SyntaxKind::Literal)
 }
 
-fn variable_ref(p: &mut Parser) -> CompletedMarker {
+fn dice_expr(p: &mut Parser) -> CompletedMarker {
+    assert!(p.at(Ok(SyntaxKind::Dice)));
+
+    let m = p.start();
+    p.bump();
+    m.complete(p, SyntaxKind::DiceExpr)
+}
+
+fn string_literal(p: &mut Parser) -> CompletedMarker {
+    assert!(p.at(Ok(SyntaxKind::String)));
+
+    let m = p.start();
+    p.bump();
+    m.complete(p, SyntaxKind::StringLiteral)
+}
+
+fn variable_ref_or_call(p: &mut Parser) -> CompletedMarker {
     assert!(p.at(Ok(SyntaxKind::Ident)));
 
     let m = p.start();
     p.bump();
-    m.complete(p, SyntaxKind::VariableRef)
+
+    // A name followed on the same line by something that can start an operand is a call. We peek
+    // rather than use `at` so that the operand kinds don't pile up in the expected list.
+    if p.at_line_start() || !at_arg_start(p) {
+        return m.complete(p, SyntaxKind::VariableRef);
+    }
+
+    if p.peek() == Some(Ok(SyntaxKind::LParen)) {
+        arg_list(p);
+    } else {
+        // `repr 1d20`: juxtaposition passes a single operand and binds tighter than any infix
+        // operator, so `abs x + 1` is `abs(x) + 1`
+        let args = p.start();
+        expr_binding_power(p, MOD_BINDING_POWER);
+        args.complete(p, SyntaxKind::ArgList);
+    }
+
+    m.complete(p, SyntaxKind::CallExpr)
+}
+
+// Minus is left out so that `x -1` stays a subtraction
+fn at_arg_start(p: &mut Parser) -> bool {
+    matches!(
+        p.peek(),
+        Some(Ok(SyntaxKind::Number
+            | SyntaxKind::Dice
+            | SyntaxKind::String
+            | SyntaxKind::Ident
+            | SyntaxKind::RollKw
+            | SyntaxKind::CritKw
+            | SyntaxKind::LParen))
+    )
+}
+
+// `d20:adv` or `d20:Poison(d4)`, or an annotation like `d20:"Stealth"`
+fn mod_expr(p: &mut Parser, lhs: CompletedMarker) -> CompletedMarker {
+    assert!(p.at(Ok(SyntaxKind::Colon)));
+
+    let m = lhs.precede(p);
+    p.bump();
+
+    if p.at(Ok(SyntaxKind::String)) {
+        string_literal(p);
+        return m.complete(p, SyntaxKind::AnnotatedExpr);
+    }
+
+    p.expect(Ok(SyntaxKind::Ident));
+
+    if p.peek() == Some(Ok(SyntaxKind::LParen)) && !p.at_line_start() {
+        arg_list(p);
+    }
+
+    m.complete(p, SyntaxKind::ModExpr)
+}
+
+// `Dragon.fire_breath`
+fn field_expr(p: &mut Parser, lhs: CompletedMarker) -> CompletedMarker {
+    assert!(p.at(Ok(SyntaxKind::Dot)));
+
+    let m = lhs.precede(p);
+    p.bump();
+    p.expect(Ok(SyntaxKind::Ident));
+    m.complete(p, SyntaxKind::FieldExpr)
+}
+
+// `dnd["Ability Scores"]`
+fn index_expr(p: &mut Parser, lhs: CompletedMarker) -> CompletedMarker {
+    assert!(p.at(Ok(SyntaxKind::LBracket)));
+
+    let m = lhs.precede(p);
+    p.bump();
+    p.enter(SyntaxKind::RBracket, false);
+    expr_binding_power(p, 0);
+    p.exit();
+    p.expect(Ok(SyntaxKind::RBracket));
+    m.complete(p, SyntaxKind::IndexExpr)
+}
+
+fn arg_list(p: &mut Parser) {
+    assert!(p.at(Ok(SyntaxKind::LParen)));
+
+    let m = p.start();
+    p.bump();
+    p.enter(SyntaxKind::RParen, false);
+
+    while !p.at(Ok(SyntaxKind::RParen)) {
+        if expr(p).is_none() {
+            break;
+        }
+
+        if p.at(Ok(SyntaxKind::Comma)) {
+            p.bump();
+        } else if !p.at(Ok(SyntaxKind::RParen)) && at_arg_start(p) {
+            // `max(1 2)`: report the missing comma and carry on with the next argument
+            p.report_error();
+        } else {
+            break;
+        }
+    }
+
+    p.exit();
+    p.expect(Ok(SyntaxKind::RParen));
+    m.complete(p, SyntaxKind::ArgList);
 }
 
 fn prefix_expr(p: &mut Parser) -> CompletedMarker {
@@ -106,17 +291,80 @@ fn prefix_expr(p: &mut Parser) -> CompletedMarker {
     m.complete(p, SyntaxKind::PrefixExpr)
 }
 
+fn keyword_expr(p: &mut Parser, op: UnaryOp, kind: SyntaxKind) -> CompletedMarker {
+    let m = p.start();
+
+    let ((), right_binding_power) = op.binding_power();
+
+    // Eat the keyword
+    p.bump();
+
+    expr_binding_power(p, right_binding_power);
+    m.complete(p, kind)
+}
+
 fn paren_expr(p: &mut Parser) -> CompletedMarker {
     assert!(p.at(Ok(SyntaxKind::LParen)));
 
     let m = p.start();
     p.bump();
-    expr_binding_power(p, 0);
+    p.enter(SyntaxKind::RParen, false);
+
+    let kind = if p.peek() == Some(Ok(SyntaxKind::Ident))
+        && p.peek_nth(1) == Some(Ok(SyntaxKind::ColonEquals))
+    {
+        group_members(p);
+        SyntaxKind::GroupExpr
+    } else {
+        expr_binding_power(p, 0);
+        SyntaxKind::ParenExpr
+    };
+
+    p.exit();
+    p.expect(Ok(SyntaxKind::RParen));
+    m.complete(p, kind)
+}
+
+// `['Utahkh', 'Mythelia']`, with an optional trailing comma
+fn array_expr(p: &mut Parser) -> CompletedMarker {
+    assert!(p.at(Ok(SyntaxKind::LBracket)));
 
-    assert_eq!(p.peek(), Some(Ok(SyntaxKind::RParen)));
+    let m = p.start();
     p.bump();
+    p.enter(SyntaxKind::RBracket, false);
+
+    while !p.at(Ok(SyntaxKind::RBracket)) {
+        if expr(p).is_none() {
+            break;
+        }
+
+        if p.at(Ok(SyntaxKind::Comma)) {
+            p.bump();
+        } else {
+            break;
+        }
+    }
+
+    p.exit();
+    p.expect(Ok(SyntaxKind::RBracket));
+    m.complete(p, SyntaxKind::ArrayExpr)
+}
+
+// `(uthal := d20+2, mycin := d20+4)`, whose members are only reachable through the group
+fn group_members(p: &mut Parser) {
+    while p.at(Ok(SyntaxKind::Ident)) {
+        let member = p.start();
+        p.bump();
+        p.expect(Ok(SyntaxKind::ColonEquals));
+        expr(p);
+        member.complete(p, SyntaxKind::GroupMember);
 
-    m.complete(p, SyntaxKind::ParenExpr)
+        if p.at(Ok(SyntaxKind::Comma)) {
+            p.bump();
+        } else {
+            break;
+        }
+    }
 }
 
 #[cfg(test)]
@@ -272,26 +520,28 @@ Root@0..12
     fn parse_infix_expression_interspersed_with_comments() {
         check(
             "
-1
-  + 1 # Add one
-  + 10 # Add ten",
+1 +
+  1 + # Add one
+  10 # Add ten",
             expect![[r##"
 Root@0..35
-  Whitespace@0..1 "\n"
+  Newline@0..1 "\n"
   InfixExpr@1..35
-    InfixExpr@1..21
-      Literal@1..5
+    InfixExpr@1..9
+      Literal@1..3
         Number@1..2 "1"
-        Whitespace@2..5 "\n  "
-      Plus@5..6 "+"
-      Whitespace@6..7 " "
-      Literal@7..21
+        Whitespace@2..3 " "
+      Plus@3..4 "+"
+      Newline@4..5 "\n"
+      Whitespace@5..7 "  "
+      Literal@7..9
         Number@7..8 "1"
         Whitespace@8..9 " "
-        Comment@9..18 "# Add one"
-        Whitespace@18..21 "\n  "
-    Plus@21..22 "+"
-    Whitespace@22..23 " "
+    Plus@9..10 "+"
+    Whitespace@10..11 " "
+    Comment@11..20 "# Add one"
+    Newline@20..21 "\n"
+    Whitespace@21..23 "  "
     Literal@23..35
       Number@23..25 "10"
       Whitespace@25..26 " "
@@ -311,7 +561,7 @@ Root@0..3
       Literal@1..2
         Number@1..2 "1"
       Plus@2..3 "+"
-error at 2..3: expected number, identifier, ‘-’ or ‘(’
+error at 2..3: expected number, dice, string, identifier, ‘roll’, ‘crit’, ‘-’, ‘(’ or ‘[’
 error at 2..3: expected ‘)’"#]],
         );
     }
@@ -407,7 +657,567 @@ Root@0..4
     LParen@0..1 "("
     VariableRef@1..4
       Ident@1..4 "foo"
-error at 1..4: expected ‘+’, ‘-’, ‘*’, ‘/’ or ‘)’"#]],
+error at 1..4: expected ‘:’, ‘.’, ‘[’, ‘+’, ‘-’, ‘*’, ‘/’ or ‘)’"#]],
+        );
+    }
+
+    #[test]
+    fn parse_dice() {
+        check(
+            "3d8",
+            expect![[r#"
+Root@0..3
+  DiceExpr@0..3
+    Dice@0..3 "3d8""#]],
+        );
+    }
+
+    #[test]
+    fn parse_dice_with_modifier() {
+        check(
+            "d20+4",
+            expect![[r#"
+Root@0..5
+  InfixExpr@0..5
+    DiceExpr@0..3
+      Dice@0..3 "d20"
+    Plus@3..4 "+"
+    Literal@4..5
+      Number@4..5 "4""#]],
+        );
+    }
+
+    #[test]
+    fn roll_applies_to_whole_expression() {
+        check(
+            "roll 3d8+4",
+            expect![[r#"
+Root@0..10
+  RollExpr@0..10
+    RollKw@0..4 "roll"
+    Whitespace@4..5 " "
+    InfixExpr@5..10
+      DiceExpr@5..8
+        Dice@5..8 "3d8"
+      Plus@8..9 "+"
+      Literal@9..10
+        Number@9..10 "4""#]],
+        );
+    }
+
+    #[test]
+    fn parse_roll_of_crit() {
+        check(
+            "roll crit 2d6*2",
+            expect![[r#"
+Root@0..15
+  RollExpr@0..15
+    RollKw@0..4 "roll"
+    Whitespace@4..5 " "
+    CritExpr@5..15
+      CritKw@5..9 "crit"
+      Whitespace@9..10 " "
+      InfixExpr@10..15
+        DiceExpr@10..13
+          Dice@10..13 "2d6"
+        Star@13..14 "*"
+        Literal@14..15
+          Number@14..15 "2""#]],
+        );
+    }
+
+    #[test]
+    fn roll_without_operand() {
+        check(
+            "roll",
+            expect![[r#"
+Root@0..4
+  RollExpr@0..4
+    RollKw@0..4 "roll"
+error at 0..4: expected number, dice, string, identifier, ‘roll’, ‘crit’, ‘-’, ‘(’ or ‘[’"#]],
+        );
+    }
+    #[test]
+    fn parse_string() {
+        check(
+            r#"'Fire attack'"#,
+            expect![[r#"
+Root@0..13
+  StringLiteral@0..13
+    String@0..13 "'Fire attack'""#]],
+        );
+    }
+
+    #[test]
+    fn parse_call_with_parenthesised_args() {
+        check(
+            "max(a, 2d6)",
+            expect![[r#"
+Root@0..11
+  CallExpr@0..11
+    Ident@0..3 "max"
+    ArgList@3..11
+      LParen@3..4 "("
+      VariableRef@4..5
+        Ident@4..5 "a"
+      Comma@5..6 ","
+      Whitespace@6..7 " "
+      DiceExpr@7..10
+        Dice@7..10 "2d6"
+      RParen@10..11 ")""#]],
+        );
+    }
+
+    #[test]
+    fn parse_call_without_args() {
+        check(
+            "roll_stats()",
+            expect![[r#"
+Root@0..12
+  CallExpr@0..12
+    Ident@0..10 "roll_stats"
+    ArgList@10..12
+      LParen@10..11 "("
+      RParen@11..12 ")""#]],
+        );
+    }
+
+    #[test]
+    fn parse_juxtaposed_call() {
+        check(
+            "repr 1d20",
+            expect![[r#"
+Root@0..9
+  CallExpr@0..9
+    Ident@0..4 "repr"
+    Whitespace@4..5 " "
+    ArgList@5..9
+      DiceExpr@5..9
+        Dice@5..9 "1d20""#]],
+        );
+    }
+
+    #[test]
+    fn juxtaposed_call_binds_tighter_than_operators() {
+        check(
+            "abs x+1",
+            expect![[r#"
+Root@0..7
+  InfixExpr@0..7
+    CallExpr@0..5
+      Ident@0..3 "abs"
+      Whitespace@3..4 " "
+      ArgList@4..5
+        VariableRef@4..5
+          Ident@4..5 "x"
+    Plus@5..6 "+"
+    Literal@6..7
+      Number@6..7 "1""#]],
+        );
+    }
+
+    #[test]
+    fn juxtaposed_calls_nest_to_the_right() {
+        check(
+            "print repr x",
+            expect![[r#"
+Root@0..12
+  CallExpr@0..12
+    Ident@0..5 "print"
+    Whitespace@5..6 " "
+    ArgList@6..12
+      CallExpr@6..12
+        Ident@6..10 "repr"
+        Whitespace@10..11 " "
+        ArgList@11..12
+          VariableRef@11..12
+            Ident@11..12 "x""#]],
+        );
+    }
+
+    #[test]
+    fn parenthesised_args_may_follow_whitespace() {
+        check(
+            r#"print (1d20, "")"#,
+            expect![[r#"
+Root@0..16
+  CallExpr@0..16
+    Ident@0..5 "print"
+    Whitespace@5..6 " "
+    ArgList@6..16
+      LParen@6..7 "("
+      DiceExpr@7..11
+        Dice@7..11 "1d20"
+      Comma@11..12 ","
+      Whitespace@12..13 " "
+      StringLiteral@13..15
+        String@13..15 "\"\""
+      RParen@15..16 ")""#]],
+        );
+    }
+
+    #[test]
+    fn name_followed_by_minus_is_subtraction() {
+        check(
+            "x -1",
+            expect![[r#"
+Root@0..4
+  InfixExpr@0..4
+    VariableRef@0..2
+      Ident@0..1 "x"
+      Whitespace@1..2 " "
+    Minus@2..3 "-"
+    Literal@3..4
+      Number@3..4 "1""#]],
+        );
+    }
+
+    #[test]
+    fn name_at_end_of_line_is_not_called() {
+        check(
+            "x\n(1)",
+            expect![[r#"
+Root@0..5
+  VariableRef@0..2
+    Ident@0..1 "x"
+    Newline@1..2 "\n"
+  ParenExpr@2..5
+    LParen@2..3 "("
+    Literal@3..4
+      Number@3..4 "1"
+    RParen@4..5 ")""#]],
+        );
+    }
+
+    #[test]
+    fn report_missing_comma_between_args() {
+        check(
+            "max(1 2)",
+            expect![[r#"
+Root@0..8
+  CallExpr@0..8
+    Ident@0..3 "max"
+    ArgList@3..8
+      LParen@3..4 "("
+      Literal@4..6
+        Number@4..5 "1"
+        Whitespace@5..6 " "
+      Literal@6..7
+        Number@6..7 "2"
+      RParen@7..8 ")"
+error at 6..7: expected ‘:’, ‘.’, ‘[’, ‘+’, ‘-’, ‘*’, ‘/’, ‘,’ or ‘)’, but found number"#]],
+        );
+    }
+
+    #[test]
+    fn report_missing_closing_paren_of_call() {
+        check(
+            "max(1, 2",
+            expect![[r#"
+Root@0..8
+  CallExpr@0..8
+    Ident@0..3 "max"
+    ArgList@3..8
+      LParen@3..4 "("
+      Literal@4..5
+        Number@4..5 "1"
+      Comma@5..6 ","
+      Whitespace@6..7 " "
+      Literal@7..8
+        Number@7..8 "2"
+error at 7..8: expected ‘:’, ‘.’, ‘[’, ‘+’, ‘-’, ‘*’, ‘/’, ‘,’ or ‘)’"#]],
+        );
+    }
+
+    #[test]
+    fn parse_modifier() {
+        check(
+            "roll d20:Poison(d4)",
+            expect![[r#"
+Root@0..19
+  RollExpr@0..19
+    RollKw@0..4 "roll"
+    Whitespace@4..5 " "
+    ModExpr@5..19
+      DiceExpr@5..8
+        Dice@5..8 "d20"
+      Colon@8..9 ":"
+      Ident@9..15 "Poison"
+      ArgList@15..19
+        LParen@15..16 "("
+        DiceExpr@16..18
+          Dice@16..18 "d4"
+        RParen@18..19 ")""#]],
+        );
+    }
+
+    #[test]
+    fn modifier_binds_tighter_than_operators() {
+        check(
+            "-d20:adv+2",
+            expect![[r#"
+Root@0..10
+  InfixExpr@0..10
+    PrefixExpr@0..8
+      Minus@0..1 "-"
+      ModExpr@1..8
+        DiceExpr@1..4
+          Dice@1..4 "d20"
+        Colon@4..5 ":"
+        Ident@5..8 "adv"
+    Plus@8..9 "+"
+    Literal@9..10
+      Number@9..10 "2""#]],
+        );
+    }
+
+    #[test]
+    fn modifier_on_juxtaposed_argument() {
+        check(
+            "print d20:adv",
+            expect![[r#"
+Root@0..13
+  CallExpr@0..13
+    Ident@0..5 "print"
+    Whitespace@5..6 " "
+    ArgList@6..13
+      ModExpr@6..13
+        DiceExpr@6..9
+          Dice@6..9 "d20"
+        Colon@9..10 ":"
+        Ident@10..13 "adv""#]],
+        );
+    }
+
+    #[test]
+    fn parse_annotation() {
+        check(
+            "roll 1d20:\"Fire\" -4:\"Debuff\"",
+            expect![[r#"
+Root@0..28
+  RollExpr@0..28
+    RollKw@0..4 "roll"
+    Whitespace@4..5 " "
+    InfixExpr@5..28
+      AnnotatedExpr@5..17
+        DiceExpr@5..9
+          Dice@5..9 "1d20"
+        Colon@9..10 ":"
+        StringLiteral@10..17
+          String@10..16 "\"Fire\""
+          Whitespace@16..17 " "
+      Minus@17..18 "-"
+      AnnotatedExpr@18..28
+        Literal@18..19
+          Number@18..19 "4"
+        Colon@19..20 ":"
+        StringLiteral@20..28
+          String@20..28 "\"Debuff\"""#]],
+        );
+    }
+
+    #[test]
+    fn parse_field_access() {
+        check(
+            "roll Dragon.fire_breath",
+            expect![[r#"
+Root@0..23
+  RollExpr@0..23
+    RollKw@0..4 "roll"
+    Whitespace@4..5 " "
+    FieldExpr@5..23
+      VariableRef@5..11
+        Ident@5..11 "Dragon"
+      Dot@11..12 "."
+      Ident@12..23 "fire_breath""#]],
+        );
+    }
+
+    #[test]
+    fn field_access_binds_tighter_than_prefix() {
+        check(
+            "-x.a",
+            expect![[r#"
+Root@0..4
+  PrefixExpr@0..4
+    Minus@0..1 "-"
+    FieldExpr@1..4
+      VariableRef@1..2
+        Ident@1..2 "x"
+      Dot@2..3 "."
+      Ident@3..4 "a""#]],
+        );
+    }
+
+    #[test]
+    fn parse_field_access_without_name() {
+        check(
+            "Dragon.",
+            expect![[r#"
+Root@0..7
+  FieldExpr@0..7
+    VariableRef@0..6
+      Ident@0..6 "Dragon"
+    Dot@6..7 "."
+error at 6..7: expected identifier"#]],
+        );
+    }
+
+    #[test]
+    fn parse_index_with_string() {
+        check(
+            "roll dnd['Ability Scores']",
+            expect![[r#"
+Root@0..26
+  RollExpr@0..26
+    RollKw@0..4 "roll"
+    Whitespace@4..5 " "
+    IndexExpr@5..26
+      VariableRef@5..8
+        Ident@5..8 "dnd"
+      LBracket@8..9 "["
+      StringLiteral@9..25
+        String@9..25 "'Ability Scores'"
+      RBracket@25..26 "]""#]],
+        );
+    }
+
+    #[test]
+    fn parse_index_without_closing_bracket() {
+        check(
+            "dnd[1",
+            expect![[r#"
+Root@0..5
+  IndexExpr@0..5
+    VariableRef@0..3
+      Ident@0..3 "dnd"
+    LBracket@3..4 "["
+    Literal@4..5
+      Number@4..5 "1"
+error at 4..5: expected ‘:’, ‘.’, ‘[’, ‘+’, ‘-’, ‘*’, ‘/’ or ‘]’"#]],
+        );
+    }
+
+    #[test]
+    fn parse_array() {
+        check(
+            "init(['Utahkh', 'Mythelia',])",
+            expect![[r#"
+Root@0..29
+  CallExpr@0..29
+    Ident@0..4 "init"
+    ArgList@4..29
+      LParen@4..5 "("
+      ArrayExpr@5..28
+        LBracket@5..6 "["
+        StringLiteral@6..14
+          String@6..14 "'Utahkh'"
+        Comma@14..15 ","
+        Whitespace@15..16 " "
+        StringLiteral@16..26
+          String@16..26 "'Mythelia'"
+        Comma@26..27 ","
+        RBracket@27..28 "]"
+      RParen@28..29 ")""#]],
+        );
+    }
+
+    #[test]
+    fn parse_empty_array() {
+        check(
+            "[]",
+            expect![[r#"
+Root@0..2
+  ArrayExpr@0..2
+    LBracket@0..1 "["
+    RBracket@1..2 "]""#]],
+        );
+    }
+
+    #[test]
+    fn array_on_new_line_is_not_an_index() {
+        check(
+            "x\n[1]",
+            expect![[r#"
+Root@0..5
+  VariableRef@0..2
+    Ident@0..1 "x"
+    Newline@1..2 "\n"
+  ArrayExpr@2..5
+    LBracket@2..3 "["
+    Literal@3..4
+      Number@3..4 "1"
+    RBracket@4..5 "]""#]],
+        );
+    }
+
+    #[test]
+    fn parse_group() {
+        check(
+            "(\n    uthal := d20+2,\n    gold := d20+3:adv,\n)",
+            expect![[r#"
+Root@0..46
+  GroupExpr@0..46
+    LParen@0..1 "("
+    Newline@1..2 "\n"
+    Whitespace@2..6 "    "
+    GroupMember@6..20
+      Ident@6..11 "uthal"
+      Whitespace@11..12 " "
+      ColonEquals@12..14 ":="
+      Whitespace@14..15 " "
+      InfixExpr@15..20
+        DiceExpr@15..18
+          Dice@15..18 "d20"
+        Plus@18..19 "+"
+        Literal@19..20
+          Number@19..20 "2"
+    Comma@20..21 ","
+    Newline@21..22 "\n"
+    Whitespace@22..26 "    "
+    GroupMember@26..43
+      Ident@26..30 "gold"
+      Whitespace@30..31 " "
+      ColonEquals@31..33 ":="
+      Whitespace@33..34 " "
+      InfixExpr@34..43
+        DiceExpr@34..37
+          Dice@34..37 "d20"
+        Plus@37..38 "+"
+        ModExpr@38..43
+          Literal@38..39
+            Number@38..39 "3"
+          Colon@39..40 ":"
+          Ident@40..43 "adv"
+    Comma@43..44 ","
+    Newline@44..45 "\n"
+    RParen@45..46 ")""#]],
+        );
+    }
+
+    #[test]
+    fn parse_group_member_without_value() {
+        check(
+            "(a := d20, b :=)",
+            expect![[r#"
+Root@0..16
+  GroupExpr@0..16
+    LParen@0..1 "("
+    GroupMember@1..9
+      Ident@1..2 "a"
+      Whitespace@2..3 " "
+      ColonEquals@3..5 ":="
+      Whitespace@5..6 " "
+      DiceExpr@6..9
+        Dice@6..9 "d20"
+    Comma@9..10 ","
+    Whitespace@10..11 " "
+    GroupMember@11..15
+      Ident@11..12 "b"
+      Whitespace@12..13 " "
+      ColonEquals@13..15 ":="
+    RParen@15..16 ")"
+error at 15..16: expected number, dice, string, identifier, ‘roll’, ‘crit’, ‘-’, ‘(’ or ‘[’, but found ‘)’"#]],
         );
     }
 }
@@ -1,8 +1,14 @@
 use super::*;
 
 pub(super) fn stmt(p: &mut Parser) -> Option<CompletedMarker> {
-    match p.peek() {
-        Some(Ok(SyntaxKind::LetKw)) => variable_def(p),
+    match (p.peek(), p.peek_nth(1)) {
+        (Some(Ok(SyntaxKind::LetKw)), _) => variable_def(p),
+        (Some(Ok(SyntaxKind::ReturnKw)), _) => Some(return_stmt(p)),
+        (Some(Ok(SyntaxKind::Ident)), Some(Ok(SyntaxKind::ColonColon))) => Some(def(p)),
+        (Some(Ok(SyntaxKind::Ident)), Some(Ok(SyntaxKind::ColonEquals))) => {
+            Some(short_variable_def(p))
+        }
+        (Some(Ok(SyntaxKind::Ident)), Some(Ok(SyntaxKind::Equals))) => Some(assignment(p)),
         _ => expr::expr(p),
     }
 }
@@ -13,13 +19,270 @@ fn variable_def(p: &mut Parser) -> Option<CompletedMarker> {
     p.bump();
 
     p.expect(Ok(SyntaxKind::Ident));
-    p.expect(Ok(SyntaxKind::Equals));
+
+    if p.at(Ok(SyntaxKind::Equals)) {
+        p.bump();
+    } else {
+        p.error();
+
+        // Without the `=`, a value on the next line is a statement of its own
+        if p.at_line_break() {
+            return Some(m.complete(p, SyntaxKind::VariableDef));
+        }
+    }
 
     expr::expr(p);
 
     Some(m.complete(p, SyntaxKind::VariableDef))
 }
 
+// `name := value`, which is the same as `let name = value`
+fn short_variable_def(p: &mut Parser) -> CompletedMarker {
+    let m = p.start();
+    p.bump();
+    p.bump();
+
+    expr::expr(p);
+
+    m.complete(p, SyntaxKind::VariableDef)
+}
+
+fn assignment(p: &mut Parser) -> CompletedMarker {
+    let m = p.start();
+    p.bump();
+    p.bump();
+
+    expr::expr(p);
+
+    m.complete(p, SyntaxKind::Assignment)
+}
+
+fn return_stmt(p: &mut Parser) -> CompletedMarker {
+    assert!(p.at(Ok(SyntaxKind::ReturnKw)));
+    let m = p.start();
+    p.bump();
+
+    // A bare `return` hands back the function's named returns
+    if !p.at_end()
+        && !p.at_line_break()
+        && p.peek() != Some(Ok(SyntaxKind::RBrace))
+        && p.peek() != Some(Ok(SyntaxKind::Semicolon))
+    {
+        expr::expr(p);
+    }
+
+    m.complete(p, SyntaxKind::ReturnStmt)
+}
+
+// name :: fn(a: number, b: number) -> (c: number) { ... }
+// name :: mod(d: Roll<Pending>) { ... }
+fn def(p: &mut Parser) -> CompletedMarker {
+    let m = p.start();
+    p.bump();
+    p.bump();
+
+    let kind = if p.at(Ok(SyntaxKind::FnKw)) {
+        p.bump();
+        SyntaxKind::FnDef
+    } else if p.at_contextual_kw(SyntaxKind::ModKw, "mod") {
+        p.bump_as(SyntaxKind::ModKw);
+        SyntaxKind::ModDef
+    } else if p.at_contextual_kw(SyntaxKind::CompKw, "comp")
+        || p.at_contextual_kw(SyntaxKind::CompKw, "Comp")
+    {
+        p.bump_as(SyntaxKind::CompKw);
+        comp_fields(p);
+        return m.complete(p, SyntaxKind::CompDef);
+    } else {
+        p.error();
+        SyntaxKind::FnDef
+    };
+
+    if p.at(Ok(SyntaxKind::LParen)) {
+        param_list(p);
+    } else {
+        p.error();
+    }
+
+    // Modifiers always hand back `result`, so they have no return type
+    if kind == SyntaxKind::FnDef && p.at(Ok(SyntaxKind::Arrow)) {
+        ret_type(p);
+    }
+
+    if p.at(Ok(SyntaxKind::LBrace)) {
+        block(p);
+    } else {
+        p.error();
+    }
+
+    m.complete(p, kind)
+}
+
+// `Comp(a: 1d6, b: 2d8)` or `comp { a: 1d6, b: 2d8 }`, with an optional trailing comma
+fn comp_fields(p: &mut Parser) {
+    let close = if p.at(Ok(SyntaxKind::LParen)) {
+        SyntaxKind::RParen
+    } else if p.at(Ok(SyntaxKind::LBrace)) {
+        SyntaxKind::RBrace
+    } else {
+        p.error();
+        return;
+    };
+    p.bump();
+    p.enter(close, false);
+
+    while p.at(Ok(SyntaxKind::Ident)) {
+        comp_field(p);
+
+        if p.at(Ok(SyntaxKind::Comma)) {
+            p.bump();
+        } else {
+            break;
+        }
+    }
+
+    p.exit();
+    p.expect(Ok(close));
+}
+
+fn comp_field(p: &mut Parser) {
+    assert!(p.at(Ok(SyntaxKind::Ident)));
+
+    let m = p.start();
+    p.bump();
+
+    // `Comp(stomp 3d6)`: report the missing colon, but still read the value
+    if p.at(Ok(SyntaxKind::Colon)) {
+        p.bump();
+    } else {
+        p.report_error();
+    }
+
+    expr::expr(p);
+    m.complete(p, SyntaxKind::CompField);
+}
+
+fn param_list(p: &mut Parser) {
+    assert!(p.at(Ok(SyntaxKind::LParen)));
+
+    let m = p.start();
+    p.bump();
+
+    while p.at(Ok(SyntaxKind::Ident)) {
+        param(p);
+
+        if p.at(Ok(SyntaxKind::Comma)) {
+            p.bump();
+        } else {
+            break;
+        }
+    }
+
+    p.expect(Ok(SyntaxKind::RParen));
+    m.complete(p, SyntaxKind::ParamList);
+}
+
+fn param(p: &mut Parser) {
+    assert!(p.at(Ok(SyntaxKind::Ident)));
+
+    let m = p.start();
+    p.bump();
+    p.expect(Ok(SyntaxKind::Colon));
+    type_expr(p);
+    m.complete(p, SyntaxKind::Param);
+}
+
+// Either a single type (`-> number`) or named returns (`-> (c: number)`)
+fn ret_type(p: &mut Parser) {
+    assert!(p.at(Ok(SyntaxKind::Arrow)));
+
+    let m = p.start();
+    p.bump();
+
+    if p.at(Ok(SyntaxKind::LParen)) {
+        param_list(p);
+    } else {
+        type_expr(p);
+    }
+
+    m.complete(p, SyntaxKind::RetType);
+}
+
+// `number`, `[number]` or `Roll<Pending>`
+fn type_expr(p: &mut Parser) {
+    if p.at(Ok(SyntaxKind::Ident)) {
+        let m = p.start();
+        p.bump();
+
+        if p.at(Ok(SyntaxKind::Lt)) {
+            p.bump();
+            type_expr(p);
+            p.expect(Ok(SyntaxKind::Gt));
+        }
+
+        m.complete(p, SyntaxKind::TypeExpr);
+    } else if p.at(Ok(SyntaxKind::LBracket)) {
+        let m = p.start();
+        p.bump();
+        type_expr(p);
+        p.expect(Ok(SyntaxKind::RBracket));
+        m.complete(p, SyntaxKind::TypeExpr);
+    } else {
+        p.error();
+    }
+}
+
+fn block(p: &mut Parser) {
+    assert!(p.at(Ok(SyntaxKind::LBrace)));
+
+    let m = p.start();
+    p.bump();
+    p.enter(SyntaxKind::RBrace, true);
+
+    stmts(p, true);
+
+    p.exit();
+    p.expect(Ok(SyntaxKind::RBrace));
+    m.complete(p, SyntaxKind::Block);
+}
+
+// A statement ends at a line break or a `;`, or at the end of its block or the script. Extra `;`s
+// are fine.
+pub(super) fn stmts(p: &mut Parser, in_block: bool) {
+    loop {
+        if p.at_end() || in_block && p.at(Ok(SyntaxKind::RBrace)) {
+            break;
+        }
+        if p.peek() == Some(Ok(SyntaxKind::Semicolon)) {
+            p.bump();
+            continue;
+        }
+
+        let errors = p.errors();
+        let start = p.position();
+        stmt(p);
+
+        // Recovery can stop at the very token the statement should have started with, like a
+        // stray `)` at the start of a line; skip it so that we get past it
+        if p.position() == start {
+            p.bump_error();
+            continue;
+        }
+
+        // A statement that went wrong has been reported already
+        let ended = p.errors() > errors
+            || p.at_end()
+            || p.at_line_start()
+            || p.at(Ok(SyntaxKind::Semicolon))
+            || in_block && p.at(Ok(SyntaxKind::RBrace));
+
+        if !ended {
+            p.expected(SyntaxKind::Newline);
+            p.report_error();
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::check;
@@ -55,7 +318,7 @@ Root@0..17
     Ident@4..5 "a"
     Whitespace@5..6 " "
     Equals@6..7 "="
-    Whitespace@7..8 "\n"
+    Newline@7..8 "\n"
   VariableDef@8..17
     LetKw@8..11 "let"
     Whitespace@11..12 " "
@@ -64,7 +327,8 @@ Root@0..17
     Equals@14..15 "="
     Whitespace@15..16 " "
     VariableRef@16..17
-      Ident@16..17 "a""#]],
+      Ident@16..17 "a"
+error at 8..11: expected number, dice, string, identifier, ‘roll’, ‘crit’, ‘-’, ‘(’ or ‘[’, but found ‘let’"#]],
         );
     }
 
@@ -83,9 +347,519 @@ Root@0..11
     Whitespace@7..8 " "
     Literal@8..10
       Number@8..9 "1"
-      Whitespace@9..10 "\n"
+      Newline@9..10 "\n"
   VariableRef@10..11
     Ident@10..11 "a""#]],
         );
     }
+
+    #[test]
+    fn parse_short_variable_definition() {
+        check(
+            "dmg := 2d6",
+            expect![[r#"
+Root@0..10
+  VariableDef@0..10
+    Ident@0..3 "dmg"
+    Whitespace@3..4 " "
+    ColonEquals@4..6 ":="
+    Whitespace@6..7 " "
+    DiceExpr@7..10
+      Dice@7..10 "2d6""#]],
+        );
+    }
+
+    #[test]
+    fn parse_assignment() {
+        check(
+            "dmg = dmg + 1",
+            expect![[r#"
+Root@0..13
+  Assignment@0..13
+    Ident@0..3 "dmg"
+    Whitespace@3..4 " "
+    Equals@4..5 "="
+    Whitespace@5..6 " "
+    InfixExpr@6..13
+      VariableRef@6..10
+        Ident@6..9 "dmg"
+        Whitespace@9..10 " "
+      Plus@10..11 "+"
+      Whitespace@11..12 " "
+      Literal@12..13
+        Number@12..13 "1""#]],
+        );
+    }
+
+    #[test]
+    fn parse_function_definition() {
+        check(
+            "addnums :: fn(a: number, b: number) -> (c: number) { c = a + b; return c }",
+            expect![[r#"
+Root@0..74
+  FnDef@0..74
+    Ident@0..7 "addnums"
+    Whitespace@7..8 " "
+    ColonColon@8..10 "::"
+    Whitespace@10..11 " "
+    FnKw@11..13 "fn"
+    ParamList@13..36
+      LParen@13..14 "("
+      Param@14..23
+        Ident@14..15 "a"
+        Colon@15..16 ":"
+        Whitespace@16..17 " "
+        TypeExpr@17..23
+          Ident@17..23 "number"
+      Comma@23..24 ","
+      Whitespace@24..25 " "
+      Param@25..34
+        Ident@25..26 "b"
+        Colon@26..27 ":"
+        Whitespace@27..28 " "
+        TypeExpr@28..34
+          Ident@28..34 "number"
+      RParen@34..35 ")"
+      Whitespace@35..36 " "
+    RetType@36..51
+      Arrow@36..38 "->"
+      Whitespace@38..39 " "
+      ParamList@39..51
+        LParen@39..40 "("
+        Param@40..49
+          Ident@40..41 "c"
+          Colon@41..42 ":"
+          Whitespace@42..43 " "
+          TypeExpr@43..49
+            Ident@43..49 "number"
+        RParen@49..50 ")"
+        Whitespace@50..51 " "
+    Block@51..74
+      LBrace@51..52 "{"
+      Whitespace@52..53 " "
+      Assignment@53..62
+        Ident@53..54 "c"
+        Whitespace@54..55 " "
+        Equals@55..56 "="
+        Whitespace@56..57 " "
+        InfixExpr@57..62
+          VariableRef@57..59
+            Ident@57..58 "a"
+            Whitespace@58..59 " "
+          Plus@59..60 "+"
+          Whitespace@60..61 " "
+          VariableRef@61..62
+            Ident@61..62 "b"
+      Semicolon@62..63 ";"
+      Whitespace@63..64 " "
+      ReturnStmt@64..73
+        ReturnKw@64..70 "return"
+        Whitespace@70..71 " "
+        VariableRef@71..73
+          Ident@71..72 "c"
+          Whitespace@72..73 " "
+      RBrace@73..74 "}""#]],
+        );
+    }
+
+    #[test]
+    fn parse_function_with_return_type() {
+        check(
+            "total :: fn(xs: [number]) -> number { sum(xs) }",
+            expect![[r#"
+Root@0..47
+  FnDef@0..47
+    Ident@0..5 "total"
+    Whitespace@5..6 " "
+    ColonColon@6..8 "::"
+    Whitespace@8..9 " "
+    FnKw@9..11 "fn"
+    ParamList@11..26
+      LParen@11..12 "("
+      Param@12..24
+        Ident@12..14 "xs"
+        Colon@14..15 ":"
+        Whitespace@15..16 " "
+        TypeExpr@16..24
+          LBracket@16..17 "["
+          TypeExpr@17..23
+            Ident@17..23 "number"
+          RBracket@23..24 "]"
+      RParen@24..25 ")"
+      Whitespace@25..26 " "
+    RetType@26..36
+      Arrow@26..28 "->"
+      Whitespace@28..29 " "
+      TypeExpr@29..36
+        Ident@29..35 "number"
+        Whitespace@35..36 " "
+    Block@36..47
+      LBrace@36..37 "{"
+      Whitespace@37..38 " "
+      CallExpr@38..46
+        Ident@38..41 "sum"
+        ArgList@41..46
+          LParen@41..42 "("
+          VariableRef@42..44
+            Ident@42..44 "xs"
+          RParen@44..45 ")"
+          Whitespace@45..46 " "
+      RBrace@46..47 "}""#]],
+        );
+    }
+
+    #[test]
+    fn parse_function_without_params_or_return_type() {
+        check(
+            "greet :: fn() {\n  print 'hello'\n}",
+            expect![[r#"
+Root@0..33
+  FnDef@0..33
+    Ident@0..5 "greet"
+    Whitespace@5..6 " "
+    ColonColon@6..8 "::"
+    Whitespace@8..9 " "
+    FnKw@9..11 "fn"
+    ParamList@11..14
+      LParen@11..12 "("
+      RParen@12..13 ")"
+      Whitespace@13..14 " "
+    Block@14..33
+      LBrace@14..15 "{"
+      Newline@15..16 "\n"
+      Whitespace@16..18 "  "
+      CallExpr@18..32
+        Ident@18..23 "print"
+        Whitespace@23..24 " "
+        ArgList@24..32
+          StringLiteral@24..32
+            String@24..31 "'hello'"
+            Newline@31..32 "\n"
+      RBrace@32..33 "}""#]],
+        );
+    }
+
+    #[test]
+    fn parse_bare_return() {
+        check(
+            "f :: fn() -> (c: number) { c = 1; return }",
+            expect![[r#"
+Root@0..42
+  FnDef@0..42
+    Ident@0..1 "f"
+    Whitespace@1..2 " "
+    ColonColon@2..4 "::"
+    Whitespace@4..5 " "
+    FnKw@5..7 "fn"
+    ParamList@7..10
+      LParen@7..8 "("
+      RParen@8..9 ")"
+      Whitespace@9..10 " "
+    RetType@10..25
+      Arrow@10..12 "->"
+      Whitespace@12..13 " "
+      ParamList@13..25
+        LParen@13..14 "("
+        Param@14..23
+          Ident@14..15 "c"
+          Colon@15..16 ":"
+          Whitespace@16..17 " "
+          TypeExpr@17..23
+            Ident@17..23 "number"
+        RParen@23..24 ")"
+        Whitespace@24..25 " "
+    Block@25..42
+      LBrace@25..26 "{"
+      Whitespace@26..27 " "
+      Assignment@27..32
+        Ident@27..28 "c"
+        Whitespace@28..29 " "
+        Equals@29..30 "="
+        Whitespace@30..31 " "
+        Literal@31..32
+          Number@31..32 "1"
+      Semicolon@32..33 ";"
+      Whitespace@33..34 " "
+      ReturnStmt@34..41
+        ReturnKw@34..40 "return"
+        Whitespace@40..41 " "
+      RBrace@41..42 "}""#]],
+        );
+    }
+
+    #[test]
+    fn recover_inside_function_body() {
+        check(
+            "f :: fn(a: number) { a + }\nf(1)",
+            expect![[r#"
+Root@0..31
+  FnDef@0..27
+    Ident@0..1 "f"
+    Whitespace@1..2 " "
+    ColonColon@2..4 "::"
+    Whitespace@4..5 " "
+    FnKw@5..7 "fn"
+    ParamList@7..19
+      LParen@7..8 "("
+      Param@8..17
+        Ident@8..9 "a"
+        Colon@9..10 ":"
+        Whitespace@10..11 " "
+        TypeExpr@11..17
+          Ident@11..17 "number"
+      RParen@17..18 ")"
+      Whitespace@18..19 " "
+    Block@19..27
+      LBrace@19..20 "{"
+      Whitespace@20..21 " "
+      InfixExpr@21..25
+        VariableRef@21..23
+          Ident@21..22 "a"
+          Whitespace@22..23 " "
+        Plus@23..24 "+"
+        Whitespace@24..25 " "
+      RBrace@25..26 "}"
+      Newline@26..27 "\n"
+  CallExpr@27..31
+    Ident@27..28 "f"
+    ArgList@28..31
+      LParen@28..29 "("
+      Literal@29..30
+        Number@29..30 "1"
+      RParen@30..31 ")"
+error at 25..26: expected number, dice, string, identifier, ‘roll’, ‘crit’, ‘-’, ‘(’ or ‘[’, but found ‘}’"#]],
+        );
+    }
+
+    #[test]
+    fn report_stray_closing_brace() {
+        check(
+            "1 }",
+            expect![[r#"
+Root@0..3
+  Literal@0..2
+    Number@0..1 "1"
+    Whitespace@1..2 " "
+  Error@2..3
+    RBrace@2..3 "}"
+error at 2..3: expected ‘:’, ‘.’, ‘[’, ‘+’, ‘-’, ‘*’, ‘/’, ‘;’ or newline, but found ‘}’"#]],
+        );
+    }
+
+    #[test]
+    fn report_missing_function_body() {
+        check(
+            "f :: fn(a: number)",
+            expect![[r#"
+Root@0..18
+  FnDef@0..18
+    Ident@0..1 "f"
+    Whitespace@1..2 " "
+    ColonColon@2..4 "::"
+    Whitespace@4..5 " "
+    FnKw@5..7 "fn"
+    ParamList@7..18
+      LParen@7..8 "("
+      Param@8..17
+        Ident@8..9 "a"
+        Colon@9..10 ":"
+        Whitespace@10..11 " "
+        TypeExpr@11..17
+          Ident@11..17 "number"
+      RParen@17..18 ")"
+error at 17..18: expected ‘->’ or ‘{’"#]],
+        );
+    }
+
+    #[test]
+    fn parse_modifier_definition() {
+        check(
+            "Poison :: mod(d: Roll<Pending>) { result = result - (roll d) }",
+            expect![[r#"
+Root@0..62
+  ModDef@0..62
+    Ident@0..6 "Poison"
+    Whitespace@6..7 " "
+    ColonColon@7..9 "::"
+    Whitespace@9..10 " "
+    ModKw@10..13 "mod"
+    ParamList@13..32
+      LParen@13..14 "("
+      Param@14..30
+        Ident@14..15 "d"
+        Colon@15..16 ":"
+        Whitespace@16..17 " "
+        TypeExpr@17..30
+          Ident@17..21 "Roll"
+          Lt@21..22 "<"
+          TypeExpr@22..29
+            Ident@22..29 "Pending"
+          Gt@29..30 ">"
+      RParen@30..31 ")"
+      Whitespace@31..32 " "
+    Block@32..62
+      LBrace@32..33 "{"
+      Whitespace@33..34 " "
+      Assignment@34..61
+        Ident@34..40 "result"
+        Whitespace@40..41 " "
+        Equals@41..42 "="
+        Whitespace@42..43 " "
+        InfixExpr@43..61
+          VariableRef@43..50
+            Ident@43..49 "result"
+            Whitespace@49..50 " "
+          Minus@50..51 "-"
+          Whitespace@51..52 " "
+          ParenExpr@52..61
+            LParen@52..53 "("
+            RollExpr@53..59
+              RollKw@53..57 "roll"
+              Whitespace@57..58 " "
+              VariableRef@58..59
+                Ident@58..59 "d"
+            RParen@59..60 ")"
+            Whitespace@60..61 " "
+      RBrace@61..62 "}""#]],
+        );
+    }
+
+    #[test]
+    fn mod_is_only_a_keyword_after_colons() {
+        check(
+            "mod(15)",
+            expect![[r#"
+Root@0..7
+  CallExpr@0..7
+    Ident@0..3 "mod"
+    ArgList@3..7
+      LParen@3..4 "("
+      Literal@4..6
+        Number@4..6 "15"
+      RParen@6..7 ")""#]],
+        );
+    }
+
+    #[test]
+    fn report_unknown_definition_kind() {
+        check(
+            "f :: func() {}",
+            expect![[r#"
+Root@0..14
+  FnDef@0..14
+    Ident@0..1 "f"
+    Whitespace@1..2 " "
+    ColonColon@2..4 "::"
+    Whitespace@4..5 " "
+    Error@5..9
+      Ident@5..9 "func"
+    ParamList@9..12
+      LParen@9..10 "("
+      RParen@10..11 ")"
+      Whitespace@11..12 " "
+    Block@12..14
+      LBrace@12..13 "{"
+      RBrace@13..14 "}"
+error at 5..9: expected ‘fn’, ‘mod’ or ‘comp’, but found identifier"#]],
+        );
+    }
+
+    #[test]
+    fn parse_comp_definition() {
+        check(
+            "Dragon :: Comp(fire_breath: 4d8+4, stomp: 3d6+2)",
+            expect![[r#"
+Root@0..48
+  CompDef@0..48
+    Ident@0..6 "Dragon"
+    Whitespace@6..7 " "
+    ColonColon@7..9 "::"
+    Whitespace@9..10 " "
+    CompKw@10..14 "Comp"
+    LParen@14..15 "("
+    CompField@15..33
+      Ident@15..26 "fire_breath"
+      Colon@26..27 ":"
+      Whitespace@27..28 " "
+      InfixExpr@28..33
+        DiceExpr@28..31
+          Dice@28..31 "4d8"
+        Plus@31..32 "+"
+        Literal@32..33
+          Number@32..33 "4"
+    Comma@33..34 ","
+    Whitespace@34..35 " "
+    CompField@35..47
+      Ident@35..40 "stomp"
+      Colon@40..41 ":"
+      Whitespace@41..42 " "
+      InfixExpr@42..47
+        DiceExpr@42..45
+          Dice@42..45 "3d6"
+        Plus@45..46 "+"
+        Literal@46..47
+          Number@46..47 "2"
+    RParen@47..48 ")""#]],
+        );
+    }
+
+    #[test]
+    fn parse_comp_definition_with_braces() {
+        check(
+            "Abilities :: comp {\n    STR: 4d6,\n    DEX: 4d6,\n}",
+            expect![[r#"
+Root@0..49
+  CompDef@0..49
+    Ident@0..9 "Abilities"
+    Whitespace@9..10 " "
+    ColonColon@10..12 "::"
+    Whitespace@12..13 " "
+    CompKw@13..17 "comp"
+    Whitespace@17..18 " "
+    LBrace@18..19 "{"
+    Newline@19..20 "\n"
+    Whitespace@20..24 "    "
+    CompField@24..32
+      Ident@24..27 "STR"
+      Colon@27..28 ":"
+      Whitespace@28..29 " "
+      DiceExpr@29..32
+        Dice@29..32 "4d6"
+    Comma@32..33 ","
+    Newline@33..34 "\n"
+    Whitespace@34..38 "    "
+    CompField@38..46
+      Ident@38..41 "DEX"
+      Colon@41..42 ":"
+      Whitespace@42..43 " "
+      DiceExpr@43..46
+        Dice@43..46 "4d6"
+    Comma@46..47 ","
+    Newline@47..48 "\n"
+    RBrace@48..49 "}""#]],
+        );
+    }
+
+    #[test]
+    fn parse_comp_field_without_colon() {
+        check(
+            "Dragon :: Comp(stomp 3d6)",
+            expect![[r#"
+Root@0..25
+  CompDef@0..25
+    Ident@0..6 "Dragon"
+    Whitespace@6..7 " "
+    ColonColon@7..9 "::"
+    Whitespace@9..10 " "
+    CompKw@10..14 "Comp"
+    LParen@14..15 "("
+    CompField@15..24
+      Ident@15..20 "stomp"
+      Whitespace@20..21 " "
+      DiceExpr@21..24
+        Dice@21..24 "3d6"
+    RParen@24..25 ")"
+error at 21..24: expected ‘:’, but found dice"#]],
+        );
+    }
 }
@@ -1,15 +1,19 @@
 mod event;
 mod grammar;
 mod parser;
+mod reparse;
 mod sink;
 mod source;
 
 use lexer::Lexer;
+pub use parser::ParseError;
 use parser::Parser;
-use rowan::GreenNode;
+pub use reparse::TextEdit;
+use rowan::{GreenNode, NodeOrToken};
+use serde_json::{json, Value};
 use sink::Sink;
 use source::Source;
-use syntax::SyntaxNode;
+use syntax::{SyntaxElement, SyntaxNode};
 
 pub fn parse(input: &str) -> Parse {
     let tokens: Vec<_> = Lexer::new(input).collect();
@@ -18,23 +22,87 @@ pub fn parse(input: &str) -> Parse {
     let events = parser.parse();
     let sink = Sink::new(&tokens, events);
 
-    Parse {
-        green_node: sink.finish(),
-    }
+    sink.finish()
 }
 
 pub struct Parse {
     green_node: GreenNode,
+    errors: Vec<ParseError>,
 }
 
 impl Parse {
+    pub fn syntax(&self) -> SyntaxNode {
+        SyntaxNode::new_root(self.green_node.clone())
+    }
+
+    pub fn green(&self) -> GreenNode {
+        self.green_node.clone()
+    }
+
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    // The tree and errors as JSON, for tools that aren't written in Rust. Every node and token has
+    // its `kind` and `start` and `end` offsets; nodes also have their `children` and tokens their
+    // `text`.
+    pub fn to_json(&self) -> Value {
+        let errors: Vec<_> = self
+            .errors
+            .iter()
+            .map(|error| {
+                json!({
+                    "message": error.message(),
+                    "start": u32::from(error.range.start()),
+                    "end": u32::from(error.range.end()),
+                    "expected": error
+                        .expected
+                        .iter()
+                        .map(|kind| format!("{:?}", kind))
+                        .collect::<Vec<_>>(),
+                    "found": error.found.map(|kind| format!("{:?}", kind)),
+                })
+            })
+            .collect();
+
+        json!({
+            "tree": element_json(self.syntax().into()),
+            "errors": errors,
+        })
+    }
+
     pub fn debug_tree(&self) -> String {
-        let syntax_node = SyntaxNode::new_root(self.green_node.clone());
-        let formatted = format!("{:#?}", syntax_node);
+        let mut s = String::new();
+
+        let tree = format!("{:#?}", self.syntax());
 
         // We cut off the last byte because formatting the SyntaxNode adds a newline at the end.
-        formatted[0..formatted.len() - 1].to_string()
+        s.push_str(&tree[0..tree.len() - 1]);
+
+        for error in &self.errors {
+            s.push_str(&format!("\n{}", error));
+        }
+
+        s
+    }
+}
+
+fn element_json(element: SyntaxElement) -> Value {
+    let range = element.text_range();
+    let mut json = json!({
+        "kind": format!("{:?}", element.kind()),
+        "start": u32::from(range.start()),
+        "end": u32::from(range.end()),
+    });
+
+    match element {
+        NodeOrToken::Node(node) => {
+            json["children"] = node.children_with_tokens().map(element_json).collect();
+        }
+        NodeOrToken::Token(token) => json["text"] = json!(token.text()),
     }
+
+    json
 }
 
 #[cfg(test)]
@@ -42,3 +110,42 @@ fn check(input: &str, expected_tree: expect_test::Expect) {
     let parse = parse(input);
     expected_tree.assert_eq(&parse.debug_tree());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tree_as_json() {
+        assert_eq!(
+            parse("x :=").to_json(),
+            json!({
+                "tree": {
+                    "kind": "Root",
+                    "start": 0,
+                    "end": 4,
+                    "children": [{
+                        "kind": "VariableDef",
+                        "start": 0,
+                        "end": 4,
+                        "children": [
+                            { "kind": "Ident", "start": 0, "end": 1, "text": "x" },
+                            { "kind": "Whitespace", "start": 1, "end": 2, "text": " " },
+                            { "kind": "ColonEquals", "start": 2, "end": 4, "text": ":=" },
+                        ],
+                    }],
+                },
+                "errors": [{
+                    "message": "expected number, dice, string, identifier, ‘roll’, ‘crit’, ‘-’, ‘(’ or ‘[’",
+                    "start": 2,
+                    "end": 4,
+                    "expected": [
+                        "Number", "Dice", "String", "Ident", "RollKw", "CritKw", "Minus", "LParen",
+                        "LBracket",
+                    ],
+                    "found": null,
+                }],
+            })
+        );
+    }
+}
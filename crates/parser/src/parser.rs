@@ -1,16 +1,30 @@
 pub(crate) mod marker;
 
+mod parse_error;
+pub use parse_error::ParseError;
+
 use crate::event::Event;
 use crate::grammar;
 use crate::source::Source;
+use lexer::Token;
 use marker::Marker;
+use std::mem;
 use syntax::SyntaxKind;
+use text_size::TextRange;
 
-const RECOVERY_SET: [SyntaxKind; 1] = [SyntaxKind::LetKw];
+// Tokens that start a statement, which errors never swallow
+const RECOVERY_SET: [SyntaxKind; 2] = [SyntaxKind::LetKw, SyntaxKind::RollKw];
 
 pub(crate) struct Parser<'t, 'input> {
     source: Source<'t, 'input>,
     events: Vec<Event>,
+    expected_kinds: Vec<SyntaxKind>,
+    // The brackets we're inside, innermost last: the token that closes each, and whether a line
+    // break ends a statement in it. Line breaks do at the top level and in blocks, but not inside
+    // other brackets, so that a long call or array can go over several lines.
+    open: Vec<(SyntaxKind, bool)>,
+    errors: usize,
+    last_error: Option<TextRange>,
 }
 
 impl<'t, 'input> Parser<'t, 'input> {
@@ -18,6 +32,10 @@ impl<'t, 'input> Parser<'t, 'input> {
         Self {
             source,
             events: Vec::new(),
+            expected_kinds: Vec::new(),
+            open: Vec::new(),
+            errors: 0,
+            last_error: None,
         }
     }
 
@@ -33,18 +51,76 @@ impl<'t, 'input> Parser<'t, 'input> {
     }
 
     pub(crate) fn bump(&mut self) {
+        self.expected_kinds.clear();
         self.source.next_token().unwrap();
         self.events.push(Event::AddToken);
     }
 
     pub(crate) fn at(&mut self, kind: Result<SyntaxKind, ()>) -> bool {
+        if let Ok(kind) = kind {
+            if !self.expected_kinds.contains(&kind) {
+                self.expected_kinds.push(kind);
+            }
+        }
         self.peek() == Some(kind)
     }
 
+    // An identifier that acts as a keyword here, like `mod` in `Poison :: mod(...)`
+    pub(crate) fn at_contextual_kw(&mut self, kind: SyntaxKind, text: &str) -> bool {
+        self.expected(kind);
+        self.peek() == Some(Ok(SyntaxKind::Ident)) && self.source.peek_text() == Some(text)
+    }
+
+    // Adds to what the next error says was expected, for tokens `at` can't look for
+    pub(crate) fn expected(&mut self, kind: SyntaxKind) {
+        if !self.expected_kinds.contains(&kind) {
+            self.expected_kinds.push(kind);
+        }
+    }
+
+    pub(crate) fn bump_as(&mut self, kind: SyntaxKind) {
+        self.expected_kinds.clear();
+        self.source.next_token().unwrap();
+        self.events.push(Event::AddTokenAs(kind));
+    }
+
     pub(crate) fn peek(&mut self) -> Option<Result<SyntaxKind, ()>> {
         self.source.peek_kind()
     }
 
+    pub(crate) fn peek_nth(&mut self, n: usize) -> Option<Result<SyntaxKind, ()>> {
+        self.source.peek_nth_kind(n)
+    }
+
+    pub(crate) fn at_line_start(&mut self) -> bool {
+        self.source.at_line_start()
+    }
+
+    // Called after an opening bracket, with the token that closes it
+    pub(crate) fn enter(&mut self, close: SyntaxKind, line_breaks_end_stmt: bool) {
+        self.open.push((close, line_breaks_end_stmt));
+    }
+
+    // Called before the closing bracket
+    pub(crate) fn exit(&mut self) {
+        self.open.pop();
+    }
+
+    // A line break before the next token that ends the statement it follows
+    pub(crate) fn at_line_break(&mut self) -> bool {
+        self.open.last().is_none_or(|&(_, ends_stmt)| ends_stmt) && self.at_line_start()
+    }
+
+    // How far into the input we are, to tell whether anything was consumed
+    pub(crate) fn position(&mut self) -> usize {
+        self.source.position()
+    }
+
+    // How many errors there have been so far, including ones not reported twice
+    pub(crate) fn errors(&self) -> usize {
+        self.errors
+    }
+
     pub(crate) fn expect(&mut self, kind: Result<SyntaxKind, ()>) {
         if self.at(kind) {
             self.bump();
@@ -53,15 +129,60 @@ impl<'t, 'input> Parser<'t, 'input> {
         }
     }
 
+    // Reports an error and skips the current token, unless it's one that somewhere further out
+    // can make sense of: the start of a statement, the start of a new line when that ends the
+    // statement, or a bracket that closes one that's open
     pub(crate) fn error(&mut self) {
-        if !self.at_set(&RECOVERY_SET) && !self.at_end() {
-            self.bump();
+        self.report_error();
+
+        let closes_open = self.peek().is_some_and(|kind| {
+            kind.is_ok_and(|kind| self.open.iter().any(|&(close, _)| close == kind))
+        });
+
+        if !self.at_set(&RECOVERY_SET) && !closes_open && !self.at_line_break() && !self.at_end() {
+            self.bump_error();
         }
     }
 
+    // Wraps the current token in an error node
+    pub(crate) fn bump_error(&mut self) {
+        let m = self.start();
+        self.bump();
+        m.complete(self, SyntaxKind::Error);
+    }
+
+    // Records an error at the current token without consuming it, for when the parser can carry
+    // on as if the expected token had been there
+    pub(crate) fn report_error(&mut self) {
+        let current_token = self.source.peek_token();
+
+        let (found, range) = if let Some(Token { kind, range, .. }) = current_token {
+            (Some(kind.unwrap_or(lexer::TokenKind::Error).into()), *range)
+        } else {
+            // If we're at the end of the input we use the range of the very last token
+            (None, self.source.last_token_range().unwrap_or_default())
+        };
+
+        let expected = mem::take(&mut self.expected_kinds);
+        self.errors += 1;
+
+        // One error per token is enough: a token that ends one statement wrongly often can't
+        // start the next either
+        if found.is_some() && self.last_error == Some(range) {
+            return;
+        }
+
+        self.last_error = Some(range);
+        self.events.push(Event::Error(ParseError {
+            expected,
+            found,
+            range,
+        }));
+    }
+
     fn at_set(&mut self, set: &[SyntaxKind]) -> bool {
-        self.peek().map_or(false, |k| match k {
-            Ok(k) => RECOVERY_SET.contains(&k),
+        self.peek().is_some_and(|k| match k {
+            Ok(k) => set.contains(&k),
             Err(_) => false,
         })
     }
@@ -105,27 +226,228 @@ Root@0..8
     fn parse_binary_expression_interspersed_with_comments() {
         check(
             "
-1
+(1
   + 1 # Add one
-  + 10 # Add ten",
+  + 10 # Add ten
+)",
             expect![[r##"
-Root@0..35
-  Whitespace@0..1 "\n"
-  InfixExpr@1..35
-    InfixExpr@1..21
-      Number@1..2 "1"
-      Whitespace@2..5 "\n  "
-      Plus@5..6 "+"
-      Whitespace@6..7 " "
-      Number@7..8 "1"
-      Whitespace@8..9 " "
-      Comment@9..18 "# Add one"
-      Whitespace@18..21 "\n  "
-    Plus@21..22 "+"
-    Whitespace@22..23 " "
-    Number@23..25 "10"
-    Whitespace@25..26 " "
-    Comment@26..35 "# Add ten""##]],
+Root@0..38
+  Newline@0..1 "\n"
+  ParenExpr@1..38
+    LParen@1..2 "("
+    InfixExpr@2..37
+      InfixExpr@2..22
+        Literal@2..6
+          Number@2..3 "1"
+          Newline@3..4 "\n"
+          Whitespace@4..6 "  "
+        Plus@6..7 "+"
+        Whitespace@7..8 " "
+        Literal@8..22
+          Number@8..9 "1"
+          Whitespace@9..10 " "
+          Comment@10..19 "# Add one"
+          Newline@19..20 "\n"
+          Whitespace@20..22 "  "
+      Plus@22..23 "+"
+      Whitespace@23..24 " "
+      Literal@24..37
+        Number@24..26 "10"
+        Whitespace@26..27 " "
+        Comment@27..36 "# Add ten"
+        Newline@36..37 "\n"
+    RParen@37..38 ")""##]],
+        );
+    }
+
+    #[test]
+    fn line_break_ends_statement() {
+        check(
+            "d20\n-1",
+            expect![[r#"
+Root@0..6
+  DiceExpr@0..4
+    Dice@0..3 "d20"
+    Newline@3..4 "\n"
+  PrefixExpr@4..6
+    Minus@4..5 "-"
+    Literal@5..6
+      Number@5..6 "1""#]],
+        );
+    }
+
+    #[test]
+    fn operator_at_end_of_line_continues_statement() {
+        check(
+            "d20 -\n1",
+            expect![[r#"
+Root@0..7
+  InfixExpr@0..7
+    DiceExpr@0..4
+      Dice@0..3 "d20"
+      Whitespace@3..4 " "
+    Minus@4..5 "-"
+    Newline@5..6 "\n"
+    Literal@6..7
+      Number@6..7 "1""#]],
+        );
+    }
+
+    #[test]
+    fn parse_statements_separated_by_semicolons() {
+        check(
+            "a := d20; roll a;",
+            expect![[r#"
+Root@0..17
+  VariableDef@0..8
+    Ident@0..1 "a"
+    Whitespace@1..2 " "
+    ColonEquals@2..4 ":="
+    Whitespace@4..5 " "
+    DiceExpr@5..8
+      Dice@5..8 "d20"
+  Semicolon@8..9 ";"
+  Whitespace@9..10 " "
+  RollExpr@10..16
+    RollKw@10..14 "roll"
+    Whitespace@14..15 " "
+    VariableRef@15..16
+      Ident@15..16 "a"
+  Semicolon@16..17 ";""#]],
+        );
+    }
+
+    #[test]
+    fn report_missing_statement_separator() {
+        check(
+            "d20 d6",
+            expect![[r#"
+Root@0..6
+  DiceExpr@0..4
+    Dice@0..3 "d20"
+    Whitespace@3..4 " "
+  DiceExpr@4..6
+    Dice@4..6 "d6"
+error at 4..6: expected ‘:’, ‘.’, ‘[’, ‘+’, ‘-’, ‘*’, ‘/’, ‘;’ or newline, but found dice"#]],
+        );
+    }
+
+    #[test]
+    fn line_breaks_inside_brackets_are_ignored() {
+        check(
+            "max(\n  d20,\n  [d8\n    -1]\n)",
+            expect![[r#"
+Root@0..27
+  CallExpr@0..27
+    Ident@0..3 "max"
+    ArgList@3..27
+      LParen@3..4 "("
+      Newline@4..5 "\n"
+      Whitespace@5..7 "  "
+      DiceExpr@7..10
+        Dice@7..10 "d20"
+      Comma@10..11 ","
+      Newline@11..12 "\n"
+      Whitespace@12..14 "  "
+      ArrayExpr@14..26
+        LBracket@14..15 "["
+        InfixExpr@15..24
+          DiceExpr@15..22
+            Dice@15..17 "d8"
+            Newline@17..18 "\n"
+            Whitespace@18..22 "    "
+          Minus@22..23 "-"
+          Literal@23..24
+            Number@23..24 "1"
+        RBracket@24..25 "]"
+        Newline@25..26 "\n"
+      RParen@26..27 ")""#]],
+        );
+    }
+
+    #[test]
+    fn parse_unrecognised_tokens() {
+        check(
+            "d20\t$ 0",
+            expect![[r#"
+Root@0..7
+  DiceExpr@0..4
+    Dice@0..3 "d20"
+    Whitespace@3..4 "\t"
+  Error@4..6
+    Error@4..5 "$"
+    Whitespace@5..6 " "
+  Literal@6..7
+    Number@6..7 "0"
+error at 4..5: expected ‘:’, ‘.’, ‘[’, ‘+’, ‘-’, ‘*’, ‘/’, ‘;’ or newline, but found an unrecognized token"#]],
+        );
+    }
+
+    #[test]
+    fn recover_at_closing_bracket_of_outer_list() {
+        check(
+            "max(x[1)",
+            expect![[r#"
+Root@0..8
+  CallExpr@0..8
+    Ident@0..3 "max"
+    ArgList@3..8
+      LParen@3..4 "("
+      IndexExpr@4..7
+        VariableRef@4..5
+          Ident@4..5 "x"
+        LBracket@5..6 "["
+        Literal@6..7
+          Number@6..7 "1"
+      RParen@7..8 ")"
+error at 7..8: expected ‘:’, ‘.’, ‘[’, ‘+’, ‘-’, ‘*’, ‘/’ or ‘]’, but found ‘)’"#]],
         );
     }
+
+    #[test]
+    fn recover_at_line_break() {
+        check(
+            "let x\nroll x",
+            expect![[r#"
+Root@0..12
+  VariableDef@0..6
+    LetKw@0..3 "let"
+    Whitespace@3..4 " "
+    Ident@4..5 "x"
+    Newline@5..6 "\n"
+  RollExpr@6..12
+    RollKw@6..10 "roll"
+    Whitespace@10..11 " "
+    VariableRef@11..12
+      Ident@11..12 "x"
+error at 6..10: expected ‘=’, but found ‘roll’"#]],
+        );
+    }
+
+    // Every input made of up to three of these pieces, alone or inside a function or comp, parses
+    // without panicking into a tree that holds all of it
+    #[test]
+    fn parse_anything() {
+        let pieces = [
+            "(", ")", "[", "]", "{", "}", "0", "d6", "x", "+", "-", ":", "::", ":=", "=", ",", ";",
+            "\n", " ", "let", "roll", "fn", "return", "$", "\"", "/*", ".",
+        ];
+        let mut inputs = vec![String::new()];
+        let mut last = inputs.clone();
+        for _ in 0..3 {
+            last = last
+                .iter()
+                .flat_map(|input| pieces.iter().map(move |piece| format!("{input}{piece}")))
+                .collect();
+            inputs.extend(last.iter().cloned());
+        }
+
+        for prefix in ["", "f :: fn(a: number) {\n", "c :: comp("] {
+            for input in &inputs {
+                let input = format!("{prefix}{input}");
+                let parse = crate::parse(&input);
+                assert_eq!(parse.syntax().text().to_string(), input);
+            }
+        }
+    }
 }
@@ -0,0 +1,121 @@
+use std::fmt;
+use syntax::SyntaxKind;
+use text_size::TextRange;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub expected: Vec<SyntaxKind>,
+    pub found: Option<SyntaxKind>,
+    pub range: TextRange,
+}
+
+impl ParseError {
+    // The error without its location, for callers that report the location themselves
+    pub fn message(&self) -> String {
+        let mut message = self.expected_message();
+
+        if let Some(found) = self.found {
+            message += &format!(", but found {}", found);
+        }
+
+        message
+    }
+
+    // Just what was expected, e.g. `expected ‘+’ or ‘-’`
+    pub fn expected_message(&self) -> String {
+        let mut message = String::from("expected ");
+        let num_expected = self.expected.len();
+        let is_first = |idx| idx == 0;
+        let is_last = |idx| idx == num_expected - 1;
+
+        for (idx, expected_kind) in self.expected.iter().enumerate() {
+            if is_first(idx) {
+                message += &expected_kind.to_string();
+            } else if is_last(idx) {
+                message += &format!(" or {}", expected_kind);
+            } else {
+                message += &format!(", {}", expected_kind);
+            }
+        }
+
+        message
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "error at {}..{}: {}",
+            u32::from(self.range.start()),
+            u32::from(self.range.end()),
+            self.message(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ops::Range as StdRange;
+
+    fn check(
+        expected: Vec<SyntaxKind>,
+        found: Option<SyntaxKind>,
+        range: StdRange<u32>,
+        output: &str,
+    ) {
+        let error = ParseError {
+            expected,
+            found,
+            range: TextRange::new(range.start.into(), range.end.into()),
+        };
+
+        assert_eq!(format!("{}", error), output);
+    }
+
+    #[test]
+    fn one_expected_did_find() {
+        check(
+            vec![SyntaxKind::Equals],
+            Some(SyntaxKind::Ident),
+            10..20,
+            "error at 10..20: expected ‘=’, but found identifier",
+        );
+    }
+
+    #[test]
+    fn one_expected_did_not_find() {
+        check(
+            vec![SyntaxKind::RParen],
+            None,
+            5..6,
+            "error at 5..6: expected ‘)’",
+        );
+    }
+
+    #[test]
+    fn multiple_expected_did_find() {
+        check(
+            vec![
+                SyntaxKind::Number,
+                SyntaxKind::Ident,
+                SyntaxKind::Minus,
+                SyntaxKind::LParen,
+            ],
+            Some(SyntaxKind::LetKw),
+            100..105,
+            "error at 100..105: expected number, identifier, ‘-’ or ‘(’, but found ‘let’",
+        );
+    }
+
+    #[test]
+    fn two_expected_did_find() {
+        check(
+            vec![SyntaxKind::Plus, SyntaxKind::Minus],
+            Some(SyntaxKind::Equals),
+            0..1,
+            "error at 0..1: expected ‘+’ or ‘-’, but found ‘=’",
+        );
+    }
+}
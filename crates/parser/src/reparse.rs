@@ -0,0 +1,220 @@
+use crate::{parse, Parse};
+use rowan::NodeOrToken;
+use std::ops::Range as StdRange;
+use syntax::SyntaxNode;
+use text_size::{TextRange, TextSize};
+
+// A change to a script: the text in `range` is replaced with `insert`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: TextRange,
+    pub insert: String,
+}
+
+impl TextEdit {
+    pub fn apply(&self, text: &mut String) {
+        text.replace_range(StdRange::<usize>::from(self.range), &self.insert);
+    }
+}
+
+impl Parse {
+    // The same as parsing the edited text from scratch, but when the edit stays inside one
+    // statement only that statement is parsed again, and the rest of the tree is shared with this
+    // one
+    pub fn reparse(&self, edit: TextEdit) -> Parse {
+        reparse_stmt(self, &edit).unwrap_or_else(|| {
+            let mut text = self.syntax().text().to_string();
+            edit.apply(&mut text);
+            parse(&text)
+        })
+    }
+}
+
+// Statements only depend on each other through their first tokens, on the line break or `;`
+// that ends them, and on errors, so we only take the shortcut when none of those change
+fn reparse_stmt(old: &Parse, edit: &TextEdit) -> Option<Parse> {
+    let root = old.syntax();
+    let (index, stmt) = root
+        .children_with_tokens()
+        .enumerate()
+        .find(|(_, child)| child.text_range().contains_range(edit.range))?;
+    let stmt = stmt.into_node()?;
+    let range = stmt.text_range();
+    let first = stmt.first_token()?;
+
+    // The statement's first token decides how the one before it ends, and its last character has
+    // to be a line break that nothing can carry on past, unless it's the end of the script
+    if edit.range.start() <= first.text_range().end() || edit.range.end() >= range.end() {
+        return None;
+    }
+    let last = stmt.next_sibling_or_token().is_none();
+    if !last && !stmt.text().to_string().ends_with('\n') {
+        return None;
+    }
+    if old
+        .errors
+        .iter()
+        .any(|error| error.range.intersect(range).is_some())
+    {
+        return None;
+    }
+
+    let mut text = stmt.text().to_string();
+    TextEdit {
+        range: edit.range - range.start(),
+        insert: edit.insert.clone(),
+    }
+    .apply(&mut text);
+
+    let new = parse(&text);
+    if !new.errors.is_empty() {
+        return None;
+    }
+
+    let new_root = new.syntax();
+    let mut children = new_root.children_with_tokens();
+    let new_stmt = match (children.next(), children.next()) {
+        (Some(NodeOrToken::Node(new_stmt)), None) => new_stmt,
+        _ => return None,
+    };
+
+    let new_first = new_stmt.first_token()?;
+    if new_first.kind() != first.kind()
+        || new_first.text_range() != first.text_range() - range.start()
+    {
+        return None;
+    }
+
+    // A statement of one token is only known to be one once the next token has been looked at,
+    // since e.g. `x` followed by `:=` on the next line is a definition
+    if significant_tokens(&new_stmt) < 2 {
+        return None;
+    }
+
+    let green = root
+        .green()
+        .replace_child(index, NodeOrToken::Node(new_stmt.green().into_owned()));
+
+    let inserted = TextSize::of(&edit.insert);
+    let errors = old
+        .errors
+        .iter()
+        .map(|error| {
+            let mut error = error.clone();
+            if error.range.start() >= range.end() {
+                error.range = error.range - edit.range.len() + inserted;
+            }
+            error
+        })
+        .collect();
+
+    Some(Parse {
+        green_node: green,
+        errors,
+    })
+}
+
+fn significant_tokens(node: &SyntaxNode) -> usize {
+    node.descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !token.kind().is_trivia())
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syntax::{SyntaxKind, SyntaxNodePtr};
+
+    const SCRIPTS: [&str; 4] = [
+        "atk := 1d20+4\nlet dmg = 2d6 + 3 # sword\nroll atk; roll dmg\n",
+        "f :: fn(a: number) -> number {\n    a * 2\n}\nx := f(3)\n[1, 2]\nx\n",
+        "Orc :: comp(\n    hit: d20+5,\n    hp: 2d8\n)\nlet x\nOrc.hit\n",
+        "x := (1 +\n  2) # sum\ny := x\n  := 3\n\"a\"\n",
+    ];
+
+    const INSERTS: [&str; 16] = [
+        "", "1", "+", " ", "\n", "(", ")", "x", ":=", ";", "#", "\"", "/*", "d", "{", "}",
+    ];
+
+    fn check(script: &str, old: &Parse, edit: TextEdit) {
+        let mut text = script.to_string();
+        edit.apply(&mut text);
+
+        let reparsed = old.reparse(edit.clone());
+        let parsed = parse(&text);
+
+        if reparsed.green_node != parsed.green_node || reparsed.errors != parsed.errors {
+            assert_eq!(
+                reparsed.debug_tree(),
+                parsed.debug_tree(),
+                "{:?} in {:?}",
+                edit,
+                script
+            );
+        }
+    }
+
+    #[test]
+    fn reparse_is_the_same_as_parse() {
+        for script in SCRIPTS {
+            let old = parse(script);
+            let len = script.len() as u32;
+
+            for start in 0..=len {
+                for end in start..=len.min(start + 2) {
+                    for insert in INSERTS {
+                        let edit = TextEdit {
+                            range: TextRange::new(start.into(), end.into()),
+                            insert: insert.to_string(),
+                        };
+                        check(script, &old, edit);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reparse_keeps_other_statements() {
+        let old = parse(SCRIPTS[0]);
+        let new = old.reparse(TextEdit {
+            range: TextRange::new(18.into(), 21.into()),
+            insert: "y".to_string(),
+        });
+
+        assert_eq!(
+            new.syntax().to_string(),
+            "atk := 1d20+4\nlet y = 2d6 + 3 # sword\nroll atk; roll dmg\n"
+        );
+
+        let old_stmts: Vec<_> = old.syntax().children().collect();
+        let new_stmts: Vec<_> = new.syntax().children().collect();
+        let shared = |a: &SyntaxNode, b: &SyntaxNode| std::ptr::eq(&*a.green(), &*b.green());
+
+        assert!(shared(&old_stmts[0], &new_stmts[0]));
+        assert!(!shared(&old_stmts[1], &new_stmts[1]));
+        assert!(shared(&old_stmts[2], &new_stmts[2]));
+    }
+
+    #[test]
+    fn pointers_resolve_in_reparsed_tree() {
+        let old = parse(SCRIPTS[0]);
+        let stmts: Vec<_> = old
+            .syntax()
+            .children()
+            .map(|stmt| SyntaxNodePtr::new(&stmt))
+            .collect();
+        let new = old.reparse(TextEdit {
+            range: TextRange::new(18.into(), 21.into()),
+            insert: "y".to_string(),
+        });
+
+        let first = stmts[0].to_node(&new.syntax()).unwrap();
+        assert_eq!(first.text(), "atk := 1d20+4\n");
+        assert_eq!(first.kind(), SyntaxKind::VariableDef);
+
+        // Everything after the edit has moved
+        assert_eq!(stmts[2].to_node(&new.syntax()), None);
+    }
+}
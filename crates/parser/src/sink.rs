@@ -1,6 +1,8 @@
 use super::event::Event;
+use crate::parser::ParseError;
+use crate::Parse;
 use lexer::Token;
-use rowan::{GreenNode, GreenNodeBuilder, Language};
+use rowan::{GreenNodeBuilder, Language};
 use std::mem;
 use syntax::{RollangLanguage, SyntaxKind};
 
@@ -9,6 +11,7 @@ pub(crate) struct Sink<'t, 'input> {
     tokens: &'t [Token<'input>],
     cursor: usize,
     events: Vec<Event>,
+    errors: Vec<ParseError>,
 }
 
 impl<'t, 'input> Sink<'t, 'input> {
@@ -18,10 +21,11 @@ impl<'t, 'input> Sink<'t, 'input> {
             tokens,
             cursor: 0,
             events,
+            errors: Vec::new(),
         }
     }
 
-    pub fn finish(mut self) -> GreenNode {
+    pub fn finish(mut self) -> Parse {
         for idx in 0..self.events.len() {
             match mem::replace(&mut self.events[idx], Event::Placeholder) {
                 Event::StartNode {
@@ -56,31 +60,43 @@ impl<'t, 'input> Sink<'t, 'input> {
                     }
                 }
                 Event::AddToken => self.token(),
+                Event::AddTokenAs(kind) => self.token_as(kind),
                 Event::FinishNode => self.builder.finish_node(),
+                Event::Error(error) => self.errors.push(error),
                 Event::Placeholder => {}
             }
 
             self.eat_trivia();
         }
 
-        self.builder.finish()
+        Parse {
+            green_node: self.builder.finish(),
+            errors: self.errors,
+        }
     }
 
     fn token(&mut self) {
-        let Token { kind, text } = self.tokens[self.cursor];
+        let kind = self.tokens[self.cursor]
+            .kind
+            .unwrap_or(lexer::TokenKind::Error);
+        self.token_as(kind.into());
+    }
 
-        let kind = kind.unwrap_or(lexer::TokenKind::Error);
-        self.builder
-            .token(RollangLanguage::kind_to_raw(kind.into()), text.into());
+    fn token_as(&mut self, kind: SyntaxKind) {
+        let Token { text, .. } = self.tokens[self.cursor];
+
+        self.builder.token(RollangLanguage::kind_to_raw(kind), text);
         self.cursor += 1;
     }
 
     fn eat_trivia(&mut self) {
         while let Some(token) = self.tokens.get(self.cursor) {
-            if let Ok(kind) = token.kind {
-                if !SyntaxKind::from(kind).is_trivia() {
-                    break;
-                }
+            // Tokens the lexer didn't recognise aren't trivia; the parser puts them in error nodes
+            if !token
+                .kind
+                .is_ok_and(|kind| SyntaxKind::from(kind).is_trivia())
+            {
+                break;
             }
 
             self.token();
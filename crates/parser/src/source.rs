@@ -1,5 +1,6 @@
 use lexer::Token;
 use syntax::SyntaxKind;
+use text_size::TextRange;
 
 pub(crate) struct Source<'t, 'input> {
     tokens: &'t [Token<'input>],
@@ -25,6 +26,49 @@ impl<'t, 'input> Source<'t, 'input> {
         self.peek_kind_raw()
     }
 
+    // Looks past the next token, e.g. `peek_nth_kind(1)` is the kind of the token after it
+    pub(crate) fn peek_nth_kind(&mut self, n: usize) -> Option<Result<SyntaxKind, ()>> {
+        self.eat_trivia();
+        self.tokens[self.cursor..]
+            .iter()
+            .filter(|token| !is_trivia(token))
+            .nth(n)
+            .map(|Token { kind, .. }| match *kind {
+                Ok(kind) => Ok(kind.into()),
+                Err(kind) => Err(kind),
+            })
+    }
+
+    pub(crate) fn peek_text(&mut self) -> Option<&'input str> {
+        self.eat_trivia();
+        self.peek_token_raw().map(|Token { text, .. }| *text)
+    }
+
+    pub(crate) fn peek_token(&mut self) -> Option<&Token<'input>> {
+        self.eat_trivia();
+        self.peek_token_raw()
+    }
+
+    // Whether the trivia between the previous token and the next one contains a line break
+    pub(crate) fn at_line_start(&mut self) -> bool {
+        self.eat_trivia();
+        self.tokens[..self.cursor]
+            .iter()
+            .rev()
+            .take_while(|token| is_trivia(token))
+            .any(|token| token.text.contains('\n'))
+    }
+
+    // The index of the next token that isn't trivia
+    pub(crate) fn position(&mut self) -> usize {
+        self.eat_trivia();
+        self.cursor
+    }
+
+    pub(crate) fn last_token_range(&self) -> Option<TextRange> {
+        self.tokens.last().map(|Token { range, .. }| *range)
+    }
+
     fn eat_trivia(&mut self) {
         while self.at_trivia() {
             self.cursor += 1;
@@ -33,15 +77,23 @@ impl<'t, 'input> Source<'t, 'input> {
 
     fn at_trivia(&self) -> bool {
         self.peek_kind_raw()
-            .map_or(false, |kind| kind.is_ok_and(|x| SyntaxKind::is_trivia(x)))
+            .is_some_and(|kind| kind.is_ok_and(SyntaxKind::is_trivia))
     }
 
     fn peek_kind_raw(&self) -> Option<Result<SyntaxKind, ()>> {
-        self.tokens
-            .get(self.cursor)
-            .map(|Token { kind, .. }| match *kind {
-                Ok(kind) => Ok(kind.into()),
-                Err(kind) => Err(kind.into()),
-            })
+        self.peek_token_raw().map(|Token { kind, .. }| match *kind {
+            Ok(kind) => Ok(kind.into()),
+            Err(kind) => Err(kind),
+        })
+    }
+
+    fn peek_token_raw(&self) -> Option<&Token<'input>> {
+        self.tokens.get(self.cursor)
     }
 }
+
+fn is_trivia(token: &Token) -> bool {
+    token
+        .kind
+        .is_ok_and(|kind| SyntaxKind::from(kind).is_trivia())
+}
@@ -0,0 +1,810 @@
+use crate::env::Env;
+use crate::eval::EvalError;
+use crate::func::{resolve_ty, Function, RESULT};
+use crate::stdlib::{self, Signature};
+use crate::ty::Ty;
+use ast::{Expr, Root, Stmt};
+use std::collections::{HashMap, HashSet};
+use syntax::{SyntaxKind, SyntaxNode, SyntaxToken};
+use text_size::TextRange;
+
+// Finds the mistakes that don't need anything to be run, so that a script can report all of them
+// before it rolls a single die: names that aren't defined yet, and values of the wrong type for
+// what's done with them. `env` is what the script is about to run in.
+pub fn check(root: &Root, env: &Env) -> Vec<EvalError> {
+    let mut checker = Checker::new(root, env);
+    checker.root(root);
+
+    let mut errors = checker.errors;
+    errors.sort_by_key(|error| error.range.start());
+    errors
+}
+
+// The type of every name in a script the check can work out, by the range of the name, both
+// where it's defined and where it's used
+pub fn name_types(root: &Root, env: &Env) -> HashMap<TextRange, Ty> {
+    let mut checker = Checker::new(root, env);
+    checker.root(root);
+    checker.names
+}
+
+// The names a script defines at the top level. A function body runs when it's called, and by then
+// any of them may exist.
+#[derive(Default)]
+struct Defined {
+    bindings: HashSet<String>,
+    functions: HashMap<String, Signature>,
+    modifiers: HashMap<String, Signature>,
+    // Names assigned to inside a function, whose type can change whenever it's called
+    assigned: HashSet<String>,
+}
+
+struct Checker {
+    // Types are `None` when they can't be known without running the script
+    globals: HashMap<String, Option<Ty>>,
+    functions: HashMap<String, Signature>,
+    modifiers: HashMap<String, Signature>,
+    defined: Defined,
+    // The locals of the function whose body is being checked
+    locals: Option<HashMap<String, Option<Ty>>>,
+    errors: Vec<EvalError>,
+    names: HashMap<TextRange, Ty>,
+}
+
+impl Checker {
+    fn new(root: &Root, env: &Env) -> Self {
+        let mut defined = Defined::default();
+        for stmt in root.stmts() {
+            match &stmt {
+                Stmt::VariableDef(def) => defined.bindings.extend(def.name().map(name)),
+                Stmt::CompDef(def) => defined.bindings.extend(def.name().map(name)),
+                Stmt::FnDef(def) => {
+                    if let Ok(func) = Function::from_def(def) {
+                        defined
+                            .functions
+                            .insert(func.name.clone(), func.signature());
+                    }
+                }
+                Stmt::ModDef(def) => {
+                    if let Ok(modifier) = Function::from_mod_def(def) {
+                        defined
+                            .modifiers
+                            .insert(modifier.name.clone(), modifier.signature());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let bodies = root
+            .syntax()
+            .descendants()
+            .filter(|node| node.kind() == SyntaxKind::Block);
+        for body in bodies {
+            let assigned = body
+                .descendants()
+                .filter_map(|node| match Stmt::cast(node) {
+                    Some(Stmt::Assignment(assignment)) => assignment.name().map(name),
+                    _ => None,
+                });
+            defined.assigned.extend(assigned);
+        }
+
+        let owned = |(name, signature): (&str, Signature)| (name.to_string(), signature);
+
+        Self {
+            globals: env
+                .bindings()
+                .into_iter()
+                .map(|(name, val)| (name.to_string(), known(val.ty())))
+                .collect(),
+            functions: env.functions().into_iter().map(owned).collect(),
+            modifiers: env.modifiers().into_iter().map(owned).collect(),
+            defined,
+            locals: None,
+            errors: Vec::new(),
+            names: HashMap::new(),
+        }
+    }
+
+    fn root(&mut self, root: &Root) {
+        for stmt in root.stmts() {
+            self.stmt(&stmt);
+        }
+
+        for node in root.syntax().descendants() {
+            let nested = node
+                .parent()
+                .is_some_and(|parent| parent.kind() != SyntaxKind::Root);
+
+            match node.kind() {
+                SyntaxKind::FnDef if nested => {
+                    self.error("functions can only be defined at the top level", &node)
+                }
+                SyntaxKind::ModDef if nested => {
+                    self.error("modifiers can only be defined at the top level", &node)
+                }
+                SyntaxKind::ReturnStmt
+                    if !node.ancestors().any(|ancestor| {
+                        matches!(ancestor.kind(), SyntaxKind::FnDef | SyntaxKind::ModDef)
+                    }) =>
+                {
+                    self.error("`return` outside of a function", &node)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn error(&mut self, message: impl Into<String>, node: &SyntaxNode) {
+        self.errors.push(EvalError::new(message, node));
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::VariableDef(def) => {
+                let ty = self.expr(def.value());
+                if let Some(name) = def.name() {
+                    self.define(&name, ty);
+                }
+            }
+            Stmt::Assignment(assignment) => {
+                let ty = self.expr(assignment.value());
+                let Some(token) = assignment.name() else {
+                    return;
+                };
+
+                let name = token.text();
+                let declared = match &mut self.locals {
+                    Some(locals) if locals.contains_key(name) => {
+                        locals.insert(name.to_string(), ty);
+                        true
+                    }
+                    // What a function does to a global is up to when it's called
+                    Some(_) => {
+                        self.globals.contains_key(name) || self.defined.bindings.contains(name)
+                    }
+                    None => self
+                        .globals
+                        .get_mut(name)
+                        .map(|binding| *binding = ty)
+                        .is_some(),
+                };
+
+                if !declared {
+                    self.error(
+                        format!(
+                            "cannot assign to `{0}` before it is declared; use `{0} := ...` to declare it",
+                            name
+                        ),
+                        stmt.syntax(),
+                    );
+                }
+            }
+            // Definitions inside a body are reported on their own, and never run
+            Stmt::FnDef(_) | Stmt::ModDef(_) if self.locals.is_some() => {}
+            Stmt::FnDef(def) => {
+                match Function::from_def(def) {
+                    Ok(func) if stdlib::builtin(&func.name).is_some() => self.error(
+                        format!(
+                            "`{}` is a builtin function and cannot be redefined",
+                            func.name
+                        ),
+                        stmt.syntax(),
+                    ),
+                    Ok(func) => {
+                        self.functions.insert(func.name.clone(), func.signature());
+                    }
+                    Err(error) => self.errors.push(error),
+                }
+
+                let named = def
+                    .ret_type()
+                    .and_then(|ret| ret.named())
+                    .unwrap_or_default();
+                // Named returns start out unassigned
+                let named = named
+                    .iter()
+                    .filter_map(|param| Some((name(param.name()?), None)));
+                let locals = params(def.params()).chain(named).collect();
+                self.body(locals, def.body());
+            }
+            Stmt::ModDef(def) => {
+                match Function::from_mod_def(def) {
+                    Ok(modifier) => {
+                        self.modifiers
+                            .insert(modifier.name.clone(), modifier.signature());
+                    }
+                    Err(error) => self.errors.push(error),
+                }
+
+                let result = (RESULT.to_string(), Some(Ty::Number));
+                let locals = params(def.params()).chain([result]).collect();
+                self.body(locals, def.body());
+            }
+            Stmt::CompDef(def) => {
+                let mut fields = Vec::new();
+                for field in def.fields() {
+                    if let Some(field_name) = field.name() {
+                        if fields.contains(&field_name.text().to_string()) {
+                            self.error(
+                                format!(
+                                    "`{}` already has a field `{}`",
+                                    def.name().map_or_else(String::new, name),
+                                    field_name.text()
+                                ),
+                                field.syntax(),
+                            );
+                        }
+                        fields.push(field_name.text().to_string());
+                    }
+                    self.expr(field.value());
+                }
+
+                if let Some(name) = def.name() {
+                    self.define(&name, Some(Ty::Comp));
+                }
+            }
+            Stmt::ReturnStmt(ret) => {
+                self.expr(ret.value());
+            }
+            Stmt::Expr(expr) => {
+                self.expr(Some(expr.clone()));
+            }
+        }
+    }
+
+    fn body(&mut self, locals: HashMap<String, Option<Ty>>, body: Option<ast::Block>) {
+        let Some(body) = body else {
+            return;
+        };
+
+        self.locals = Some(locals);
+        for stmt in body.stmts() {
+            self.stmt(&stmt);
+        }
+        self.locals = None;
+    }
+
+    fn define(&mut self, token: &SyntaxToken, ty: Option<Ty>) {
+        let scope = self.locals.as_mut().unwrap_or(&mut self.globals);
+        scope.insert(name(token.clone()), ty.clone());
+        self.use_name(token, ty);
+    }
+
+    fn use_name(&mut self, token: &SyntaxToken, ty: Option<Ty>) {
+        if let Some(ty) = ty {
+            self.names.insert(token.text_range(), ty);
+        }
+    }
+
+    // The type of a binding, which is `Some(None)` when it exists but its type isn't known
+    fn binding(&self, name: &str) -> Option<Option<Ty>> {
+        match &self.locals {
+            Some(locals) => locals.get(name).cloned().or_else(|| {
+                // Globals may have changed by the time the function is called
+                (self.globals.contains_key(name) || self.defined.bindings.contains(name))
+                    .then_some(None)
+            }),
+            None => {
+                let ty = self.globals.get(name)?;
+                Some(ty.clone().filter(|_| !self.defined.assigned.contains(name)))
+            }
+        }
+    }
+
+    fn function(&self, name: &str) -> Option<Signature> {
+        let defined = self.locals.as_ref().and(self.defined.functions.get(name));
+        defined.or_else(|| self.functions.get(name)).cloned()
+    }
+
+    fn modifier(&self, name: &str) -> Option<Signature> {
+        let defined = self.locals.as_ref().and(self.defined.modifiers.get(name));
+        defined.or_else(|| self.modifiers.get(name)).cloned()
+    }
+
+    // Checks the arguments of a call against the signatures of what's called, giving back the
+    // type of its result
+    fn call(
+        &mut self,
+        name: &str,
+        signatures: &[Signature],
+        args: Vec<Option<Ty>>,
+        node: &SyntaxNode,
+    ) -> Option<Ty> {
+        if let Some(error) = stdlib::arity_error(name, signatures, args.len()) {
+            self.error(error, node);
+            return None;
+        }
+
+        let fitting: Vec<_> = signatures
+            .iter()
+            .filter(|sig| sig.params.len() == args.len())
+            .filter(|sig| {
+                args.iter()
+                    .zip(&sig.params)
+                    .all(|(arg, param)| arg.as_ref().is_none_or(|arg| arg.fits(param)))
+            })
+            .collect();
+
+        match fitting.as_slice() {
+            [] => {
+                let given: Vec<_> = args.into_iter().map(|arg| arg.unwrap_or(Ty::Any)).collect();
+                self.error(stdlib::mismatch_error(name, signatures, &given), node);
+                None
+            }
+            [sig, rest @ ..] if rest.iter().all(|other| other.ret == sig.ret) => {
+                known(sig.ret.clone())
+            }
+            _ => None,
+        }
+    }
+
+    fn exprs(&mut self, exprs: impl Iterator<Item = Expr>) -> Vec<Option<Ty>> {
+        exprs.map(|expr| self.expr(Some(expr))).collect()
+    }
+
+    // The type of an expression, following what `eval` does with it. An expression with a
+    // mistake in it is of no known type, so that the mistake is only reported once.
+    fn expr(&mut self, expr: Option<Expr>) -> Option<Ty> {
+        let expr = expr?;
+        let node = expr.syntax().clone();
+
+        match expr {
+            Expr::Literal(literal) => {
+                if literal.parse().is_none() {
+                    self.error("number is too large", &node);
+                    return None;
+                }
+                Some(Ty::Number)
+            }
+            Expr::StringLiteral(_) => Some(Ty::Str),
+            Expr::DiceExpr(dice) => {
+                let Some((count, _)) = dice.count().zip(dice.sides()) else {
+                    self.error("dice are too large", &node);
+                    return None;
+                };
+
+                if let Some((select, n)) = dice.select() {
+                    let Some(n) = n else {
+                        self.error("dice are too large", &node);
+                        return None;
+                    };
+                    if n > count {
+                        let verb = match select {
+                            ast::Select::KeepHighest | ast::Select::KeepLowest => "keep",
+                            ast::Select::DropHighest | ast::Select::DropLowest => "drop",
+                        };
+                        self.error(format!("cannot {} {} of {} dice", verb, n, count), &node);
+                        return None;
+                    }
+                }
+
+                Some(Ty::Dice)
+            }
+            Expr::VariableRef(var) => {
+                let token = var.name()?;
+                let name = token.text();
+
+                let ty = match self.binding(name) {
+                    Some(ty) => ty,
+                    // A builtin that can take no arguments, like `next`, can also be called by
+                    // its name
+                    None => match stdlib::builtin(name).and_then(|builtin| {
+                        builtin
+                            .signatures()
+                            .into_iter()
+                            .find(|sig| sig.params.is_empty())
+                    }) {
+                        Some(sig) => known(sig.ret),
+                        None => {
+                            self.error(format!("unbound variable `{}`", name), &node);
+                            return None;
+                        }
+                    },
+                };
+
+                self.use_name(&token, ty.clone());
+                ty
+            }
+            Expr::CallExpr(call) => {
+                let name = call.name()?;
+                let name = name.text();
+                let args = self.exprs(call.args());
+
+                if let Some(sig) = self.function(name) {
+                    return self.call(name, &[sig], args, &node);
+                }
+                if let Some(builtin) = stdlib::builtin(name) {
+                    return self.call(name, &builtin.signatures(), args, &node);
+                }
+
+                let message = match self.binding(name) {
+                    // `max(a b)` parses as `max(a(b))`, so point out the likely mistake
+                    Some(Some(ty)) if call.is_juxtaposed() => format!(
+                        "`{}` is a {}, not a function; is there a missing comma or operator after it?",
+                        name, ty
+                    ),
+                    Some(Some(ty)) => format!("`{}` is a {}, not a function", name, ty),
+                    Some(None) => format!("`{}` is not a function", name),
+                    None => format!("unknown function `{}`", name),
+                };
+                self.error(message, &node);
+                None
+            }
+            Expr::ModExpr(modded) => {
+                let inner = self.expr(modded.expr());
+                let args = self.exprs(modded.args());
+                let name = modded.name()?;
+                let name = name.text();
+
+                match inner {
+                    Some(Ty::Roll) => {
+                        self.error(
+                            "cannot apply a modifier to a value that has already been rolled; apply it before rolling",
+                            &node,
+                        );
+                        return None;
+                    }
+                    Some(ty) if ty != Ty::Number && ty != Ty::Dice => {
+                        self.error(format!("cannot apply a modifier to {}", ty), &node);
+                        return None;
+                    }
+                    _ => {}
+                }
+
+                let Some(sig) = self.modifier(name) else {
+                    let message =
+                        if self.function(name).is_some() || stdlib::builtin(name).is_some() {
+                            format!("`{}` is a function, not a modifier", name)
+                        } else {
+                            format!("unknown modifier `{}`", name)
+                        };
+                    self.error(message, &node);
+                    return None;
+                };
+
+                self.call(name, &[sig], args, &node)?;
+                Some(Ty::Dice)
+            }
+            Expr::ParenExpr(paren) => self.expr(paren.expr()),
+            Expr::PrefixExpr(prefix) => {
+                let ty = self.expr(prefix.expr())?;
+                self.operand(ty, &node)
+            }
+            Expr::InfixExpr(infix) => {
+                let lhs = self.expr(infix.lhs());
+                let rhs = self.expr(infix.rhs());
+                infix.op()?;
+
+                let lhs = self.operand(lhs?, &node)?;
+                let rhs = self.operand(rhs?, &node)?;
+                if lhs == Ty::Dice || rhs == Ty::Dice {
+                    Some(Ty::Dice)
+                } else {
+                    Some(Ty::Number)
+                }
+            }
+            Expr::RollExpr(roll) => match self.expr(roll.expr())? {
+                Ty::Number | Ty::Dice => Some(Ty::Roll),
+                Ty::Comp => Some(Ty::Comp),
+                Ty::Roll => {
+                    self.error("this value has already been rolled", &node);
+                    None
+                }
+                ty => {
+                    self.error(format!("cannot roll {}", ty), &node);
+                    None
+                }
+            },
+            Expr::ArrayExpr(array) => {
+                let items = self.exprs(array.items());
+
+                // Arrays only hold one type of value. Only types known all the way down can be
+                // compared, since e.g. `[any]` is the type of an empty vector.
+                let mut precise = items.iter().flatten().filter(|ty| !ty.has_any());
+                if let Some(Some(first)) = items.first() {
+                    let other = precise.find(|item| *item != first);
+                    if let Some(other) = other.filter(|_| !first.has_any()) {
+                        self.error(
+                            format!(
+                                "arrays can only hold one type, but this one has {} and {}",
+                                first, other
+                            ),
+                            &node,
+                        );
+                        return None;
+                    }
+                }
+
+                let first = items.into_iter().next().flatten();
+                Some(Ty::vec(first.unwrap_or(Ty::Any)))
+            }
+            Expr::GroupExpr(group) => {
+                let mut names = Vec::new();
+                for member in group.members() {
+                    if let Some(name) = member.name() {
+                        if names.contains(&name.text().to_string()) {
+                            self.error(
+                                format!("the group already has a member named `{}`", name.text()),
+                                member.syntax(),
+                            );
+                        }
+                        names.push(name.text().to_string());
+                    }
+                    self.expr(member.value());
+                }
+
+                Some(Ty::Comp)
+            }
+            Expr::IndexExpr(index) => {
+                let base = self.expr(index.expr());
+                let key = self.expr(index.index());
+
+                match (base?, key?) {
+                    (Ty::Comp, Ty::Str) => None,
+                    (Ty::Vec(elem), Ty::Number) => known(*elem),
+                    (base, key) => {
+                        self.error(format!("cannot index {} with {}", base, key), &node);
+                        None
+                    }
+                }
+            }
+            Expr::FieldExpr(access) => {
+                let base = self.expr(access.expr());
+                let field = access.field()?;
+
+                match base? {
+                    Ty::Comp => None,
+                    ty => {
+                        self.error(
+                            format!("cannot access field `{}` on {}", field.text(), ty),
+                            &node,
+                        );
+                        None
+                    }
+                }
+            }
+            Expr::AnnotatedExpr(annotated) => match self.expr(annotated.expr())? {
+                Ty::Number | Ty::Dice => Some(Ty::Dice),
+                Ty::Roll => {
+                    self.error(
+                        "cannot annotate a value that has already been rolled; annotate it before rolling",
+                        &node,
+                    );
+                    None
+                }
+                ty => {
+                    self.error(format!("cannot annotate {}", ty), &node);
+                    None
+                }
+            },
+            Expr::CritExpr(crit) => match self.expr(crit.expr())? {
+                ty @ (Ty::Number | Ty::Dice) => Some(ty),
+                Ty::Roll => {
+                    self.error(
+                        "cannot crit a value that has already been rolled; crit the dice before rolling them",
+                        &node,
+                    );
+                    None
+                }
+                ty => {
+                    self.error(format!("cannot crit {}", ty), &node);
+                    None
+                }
+            },
+        }
+    }
+
+    // A rolled value takes part in arithmetic through its total
+    fn operand(&mut self, ty: Ty, node: &SyntaxNode) -> Option<Ty> {
+        match ty {
+            Ty::Number | Ty::Roll => Some(Ty::Number),
+            Ty::Dice => Some(Ty::Dice),
+            ty => {
+                self.error(format!("cannot do arithmetic on {}", ty), node);
+                None
+            }
+        }
+    }
+}
+
+// `any` is what functions without a return type give back, which could be anything
+fn known(ty: Ty) -> Option<Ty> {
+    (ty != Ty::Any).then_some(ty)
+}
+
+fn name(token: SyntaxToken) -> String {
+    token.text().to_string()
+}
+
+fn params(params: impl Iterator<Item = ast::Param>) -> impl Iterator<Item = (String, Option<Ty>)> {
+    params.filter_map(|param| {
+        let ty = param.ty().and_then(|ty| resolve_ty(&ty).ok());
+        Some((name(param.name()?), ty.and_then(known)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_errors(input: &str, expected: &[&str]) {
+        let parse = parser::parse(input);
+        assert_eq!(parse.errors(), &[]);
+        let root = Root::cast(parse.syntax()).unwrap();
+
+        let errors: Vec<_> = check(&root, &Env::with_seed(0))
+            .into_iter()
+            .map(|error| error.message)
+            .collect();
+        assert_eq!(errors, expected);
+    }
+
+    #[test]
+    fn check_valid_script() {
+        check_errors(
+            "bonus :: fn(x: number) -> number { return x + 2 }\nbonus(roll 1d20)",
+            &[],
+        );
+    }
+
+    #[test]
+    fn check_reports_every_error_in_order() {
+        check_errors(
+            "a :: fn(x: nmber) { x }\nreturn 1\nb :: fn() -> strng { 1 }",
+            &[
+                "unknown type `nmber`",
+                "`return` outside of a function",
+                "unknown type `strng`",
+            ],
+        );
+    }
+
+    #[test]
+    fn check_nested_function() {
+        check_errors(
+            "outer :: fn() { inner :: fn() { 1 }\n 2 }",
+            &["functions can only be defined at the top level"],
+        );
+    }
+
+    #[test]
+    fn check_unbound_variable() {
+        check_errors("roll dmg\ndmg := d6", &["unbound variable `dmg`"]);
+    }
+
+    // By the time a function is called, names defined after it may exist
+    #[test]
+    fn check_function_uses_later_binding() {
+        check_errors("f :: fn() -> number { bonus }\nbonus := 2\nf()", &[]);
+    }
+
+    #[test]
+    fn check_unknown_function_and_modifier() {
+        check_errors(
+            "rol(d20)\nd20:adv\nd20:max",
+            &[
+                "unknown function `rol`",
+                "unknown modifier `adv`",
+                "`max` is a function, not a modifier",
+            ],
+        );
+    }
+
+    #[test]
+    fn check_argument_types() {
+        check_errors(
+            "max(1d20, 3)",
+            &["`max` expects (number, number) or ([number]), but was given (dice, number)"],
+        );
+        check_errors("atk := roll d20\nmax(atk, 3)\nsum(roll 4d6)", &[]);
+        check_errors(
+            "f :: fn(a: str) { a }\nf(1, 2)",
+            &["`f` takes 1 argument but 2 were given"],
+        );
+    }
+
+    #[test]
+    fn check_operand_types() {
+        check_errors(
+            "x := \"a\" + 1\nroll [1, 2]",
+            &["cannot do arithmetic on str", "cannot roll [number]"],
+        );
+        check_errors(
+            "f :: fn(s: str) -> number { s * 2 }",
+            &["cannot do arithmetic on str"],
+        );
+    }
+
+    #[test]
+    fn check_rolled_values() {
+        check_errors(
+            "adv :: mod() { result = result + 1 }\natk := roll d20\natk:adv\nroll atk\ncrit atk",
+            &[
+                "cannot apply a modifier to a value that has already been rolled; apply it before rolling",
+                "this value has already been rolled",
+                "cannot crit a value that has already been rolled; crit the dice before rolling them",
+            ],
+        );
+        check_errors("x := crit 1d8\nroll x", &[]);
+        check_errors(
+            "x := roll d20:\"Stealth\"\nx:\"late\"",
+            &["cannot annotate a value that has already been rolled; annotate it before rolling"],
+        );
+    }
+
+    #[test]
+    fn check_arrays_fields_and_indexes() {
+        check_errors(
+            "[1, \"a\"]\nx := 3\nx.hp\nx[0]\ndnd[\"Standard Array\"]",
+            &[
+                "arrays can only hold one type, but this one has number and str",
+                "cannot access field `hp` on number",
+                "cannot index number with number",
+            ],
+        );
+    }
+
+    #[test]
+    fn check_assignment_before_declaration() {
+        check_errors(
+            "x = 1\ny := 1\ny = 2",
+            &["cannot assign to `x` before it is declared; use `x := ...` to declare it"],
+        );
+    }
+
+    // One mistake makes the types that depend on it unknown, rather than causing more errors
+    #[test]
+    fn check_reports_each_mistake_once() {
+        check_errors(
+            "x := max(1d20, 3)\nroll x + 1",
+            &["`max` expects (number, number) or ([number]), but was given (dice, number)"],
+        );
+    }
+
+    #[test]
+    fn check_examples() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../../examples");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let input = std::fs::read_to_string(&path).unwrap();
+            let parse = parser::parse(&input);
+            assert_eq!(parse.errors(), &[], "{}", path.display());
+
+            let root = Root::cast(parse.syntax()).unwrap();
+            let errors: Vec<_> = check(&root, &Env::with_seed(0))
+                .into_iter()
+                .map(|error| error.message)
+                .collect();
+            assert_eq!(errors, Vec::<String>::new(), "{}", path.display());
+        }
+    }
+
+    // A function can change the type of a global whenever it's called
+    #[test]
+    fn check_global_assigned_in_function() {
+        check_errors("x := \"a\"\nf :: fn() { x = 1 }\nf()\nx + 1", &[]);
+    }
+
+    #[test]
+    fn types_of_names() {
+        let input = "atk := roll d20\nf :: fn(a: dice) { a }\nlater := atk";
+        let root = Root::cast(parser::parse(input).syntax()).unwrap();
+        let mut types: Vec<_> = name_types(&root, &Env::with_seed(0))
+            .into_iter()
+            .map(|(range, ty)| (&input[range], ty.to_string()))
+            .collect();
+        types.sort();
+
+        assert_eq!(
+            types,
+            [
+                ("a", "dice".to_string()),
+                ("atk", "roll".to_string()),
+                ("atk", "roll".to_string()),
+                ("later", "roll".to_string()),
+            ]
+        );
+    }
+}
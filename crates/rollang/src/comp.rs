@@ -0,0 +1,147 @@
+use crate::formula::Outcome;
+use crate::val::Val;
+use std::fmt;
+
+// A composite groups named values, e.g. `Dragon :: Comp(fire_breath: 4d8+4, stomp: 3d6+2)`.
+// Rolling it rolls every member, and it prints as a table with one field per line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comp {
+    pub name: String,
+    pub fields: Vec<(String, Val)>,
+}
+
+impl Comp {
+    pub fn field(&self, name: &str) -> Option<&Val> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, val)| val)
+    }
+
+    fn write_fields(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let indent = "    ".repeat(depth);
+        let cells: Vec<_> = self
+            .fields
+            .iter()
+            .map(|(name, val)| (label(name), cells(val)))
+            .collect();
+
+        let name_width = cells.iter().map(|(name, _)| name.chars().count()).max();
+        let name_width = name_width.unwrap_or(0);
+        let body_width = cells
+            .iter()
+            .filter(|(_, (_, total))| total.is_some())
+            .map(|(_, (body, _))| body.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        for ((name, (body, total)), (_, val)) in cells.iter().zip(&self.fields) {
+            write!(f, "\n{}{:<name_width$}  ", indent, name)?;
+
+            match total {
+                Some(total) => write!(f, "{:<body_width$}  {}", body, total)?,
+                None => f.write_str(body)?,
+            }
+
+            if let Val::Comp(comp) = val {
+                comp.write_fields(f, depth + 1)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Fields whose names aren't identifiers, like the rollset entry "Ability Scores", can only be
+// reached by indexing, so that's how they're shown
+fn label(name: &str) -> String {
+    let is_ident = name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if is_ident {
+        format!(".{}", name)
+    } else {
+        format!("[{:?}]", name)
+    }
+}
+
+// Splits a member into what was rolled and, in its own column, the total it came to
+fn cells(val: &Val) -> (String, Option<String>) {
+    match val {
+        Val::Roll(roll) => match roll.outcome {
+            Outcome::Number(_) => (roll.outcome.to_string(), None),
+            _ => (roll.outcome.to_string(), Some(format!("->{}", roll.total))),
+        },
+        Val::Comp(comp) => (comp.name.clone(), None),
+        val => (val.to_string(), None),
+    }
+}
+
+impl fmt::Display for Comp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // A group that was never bound to a name
+        if self.name.is_empty() {
+            f.write_str("(group)")?;
+        } else {
+            f.write_str(&self.name)?;
+        }
+
+        self.write_fields(f, 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formula::{Dice, Formula, InfixOp};
+    use crate::rng::Rng;
+
+    #[test]
+    fn format_pending_fields() {
+        let comp = Comp {
+            name: "Dragon".to_string(),
+            fields: vec![
+                (
+                    "fire_breath".to_string(),
+                    Val::Formula(Formula::Dice(Dice::new(4, 8))),
+                ),
+                ("speed".to_string(), Val::Number(40)),
+            ],
+        };
+
+        assert_eq!(
+            comp.to_string(),
+            "Dragon\n    .fire_breath  4d8\n    .speed        40"
+        );
+    }
+
+    #[test]
+    fn format_rolled_fields_in_columns() {
+        let mut rng = Rng::new(7);
+        let fire_breath = Formula::Infix {
+            op: InfixOp::Add,
+            lhs: Box::new(Formula::Dice(Dice::new(4, 8))),
+            rhs: Box::new(Formula::Number(4)),
+        };
+        let stomp = Formula::Dice(Dice::new(3, 6));
+
+        let comp = Comp {
+            name: "Dragon".to_string(),
+            fields: vec![
+                (
+                    "fire_breath".to_string(),
+                    Val::Roll(fire_breath.roll(&mut rng).unwrap()),
+                ),
+                (
+                    "stomp".to_string(),
+                    Val::Roll(stomp.roll(&mut rng).unwrap()),
+                ),
+            ],
+        };
+
+        assert_eq!(
+            comp.to_string(),
+            "Dragon\n    .fire_breath  4d8=[8, 5, 3, 4] +4  ->24\n    .stomp        3d6=[5, 4, 5]        ->14"
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use crate::formula::{Dice, Formula, InfixOp, Select};
+use std::fmt;
+use std::str::FromStr;
+
+// How a critical hit changes a damage roll. Tables disagree on this, so the rule is picked per
+// session.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum CritRule {
+    // 5e rules as written: roll twice as many dice, add modifiers once (`3d8+4` -> `6d8+4`)
+    #[default]
+    DoubleDice,
+    // Take the maximum of the dice and roll them once more (`3d8+4` -> `3d8+24+4`)
+    MaxPlusRoll,
+    // Roll normally and double everything, modifiers included (`3d8+4` -> `(3d8+4)*2`)
+    DoubleTotal,
+}
+
+impl CritRule {
+    pub const ALL: [CritRule; 3] = [Self::DoubleDice, Self::MaxPlusRoll, Self::DoubleTotal];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::DoubleDice => "double-dice",
+            Self::MaxPlusRoll => "max-plus-roll",
+            Self::DoubleTotal => "double-total",
+        }
+    }
+}
+
+impl fmt::Display for CritRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for CritRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|rule| rule.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::ALL.iter().map(|rule| rule.name()).collect();
+                format!(
+                    "unknown crit rule `{}` (expected one of {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+impl Formula {
+    pub fn crit(&self, rule: CritRule) -> Formula {
+        match rule {
+            // Keep/drop dice keep or drop twice as many too, so `2d20kh1` becomes `4d20kh2`
+            CritRule::DoubleDice => self.map_dice(&|dice| {
+                Formula::Dice(Dice {
+                    count: dice.count.saturating_mul(2),
+                    select: dice.select.map(double_select),
+                    ..dice
+                })
+            }),
+            CritRule::MaxPlusRoll => self.map_dice(&|dice| Formula::Infix {
+                op: InfixOp::Add,
+                lhs: Box::new(Formula::Dice(dice)),
+                rhs: Box::new(Formula::Number(dice.max())),
+            }),
+            CritRule::DoubleTotal => Formula::Infix {
+                op: InfixOp::Mul,
+                lhs: Box::new(self.clone()),
+                rhs: Box::new(Formula::Number(2)),
+            },
+        }
+    }
+
+    // Rebuilds the formula with every dice term replaced, leaving numbers untouched
+    fn map_dice(&self, f: &impl Fn(Dice) -> Formula) -> Formula {
+        match self {
+            Self::Number(n) => Self::Number(*n),
+            Self::Dice(dice) => f(*dice),
+            Self::Infix { op, lhs, rhs } => Self::Infix {
+                op: *op,
+                lhs: Box::new(lhs.map_dice(f)),
+                rhs: Box::new(rhs.map_dice(f)),
+            },
+            Self::Neg(inner) => Self::Neg(Box::new(inner.map_dice(f))),
+            Self::Mod { inner, modifier } => Self::Mod {
+                inner: Box::new(inner.map_dice(f)),
+                modifier: modifier.clone(),
+            },
+            Self::Annotated { inner, annotation } => Self::Annotated {
+                inner: Box::new(inner.map_dice(f)),
+                annotation: annotation.clone(),
+            },
+        }
+    }
+}
+
+fn double_select(select: Select) -> Select {
+    match select {
+        Select::KeepHighest(n) => Select::KeepHighest(n.saturating_mul(2)),
+        Select::KeepLowest(n) => Select::KeepLowest(n.saturating_mul(2)),
+        Select::DropHighest(n) => Select::DropHighest(n.saturating_mul(2)),
+        Select::DropLowest(n) => Select::DropLowest(n.saturating_mul(2)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn damage() -> Formula {
+        Formula::Infix {
+            op: InfixOp::Add,
+            lhs: Box::new(Formula::Dice(Dice::new(3, 8))),
+            rhs: Box::new(Formula::Number(4)),
+        }
+    }
+
+    fn check(rule: CritRule, formula: Formula, expected: &str) {
+        assert_eq!(formula.crit(rule).to_string(), expected);
+    }
+
+    #[test]
+    fn double_dice_leaves_modifiers_alone() {
+        check(CritRule::DoubleDice, damage(), "6d8+4");
+    }
+
+    #[test]
+    fn double_dice_doubles_every_dice_term() {
+        let formula = Formula::Infix {
+            op: InfixOp::Add,
+            lhs: Box::new(damage()),
+            rhs: Box::new(Formula::Dice(Dice::new(1, 6))),
+        };
+        check(CritRule::DoubleDice, formula, "6d8+4+2d6");
+    }
+
+    #[test]
+    fn double_dice_doubles_dropped_dice() {
+        let dice = Dice::new(4, 6).with_select(Select::DropLowest(1));
+        check(CritRule::DoubleDice, Formula::Dice(dice), "8d6dl2");
+    }
+
+    #[test]
+    fn double_dice_doubles_kept_dice() {
+        let dice = Dice::new(2, 20).with_select(Select::KeepHighest(1));
+        check(CritRule::DoubleDice, Formula::Dice(dice), "4d20kh2");
+    }
+
+    #[test]
+    fn max_plus_roll_adds_maximum_of_dice() {
+        check(CritRule::MaxPlusRoll, damage(), "3d8+24+4");
+    }
+
+    #[test]
+    fn double_total_doubles_modifiers_too() {
+        check(CritRule::DoubleTotal, damage(), "(3d8+4)*2");
+    }
+
+    #[test]
+    fn parse_rule_names() {
+        for rule in CritRule::ALL {
+            assert_eq!(rule.name().parse(), Ok(rule));
+        }
+    }
+
+    #[test]
+    fn reject_unknown_rule() {
+        assert_eq!(
+            "triple".parse::<CritRule>(),
+            Err(
+                "unknown crit rule `triple` (expected one of double-dice, max-plus-roll, double-total)"
+                    .to_string()
+            ),
+        );
+    }
+}
@@ -0,0 +1,542 @@
+use lexer::{Lexer, TokenKind};
+use parser::ParseError;
+use serde_json::{json, Value};
+use std::io::IsTerminal;
+use syntax::SyntaxKind;
+use text_size::{TextRange, TextSize};
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[1;36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+// An error as it's shown to players: the message, the line it's on with the offending part
+// underlined, and whatever else might help them fix it
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Diagnostic {
+    severity: Severity,
+    // What kind of mistake this is, for tools; see the JSON output in the user guide
+    code: &'static str,
+    message: String,
+    labels: Vec<Label>,
+    notes: Vec<String>,
+    help: Option<String>,
+}
+
+// Errors stop a script from running; warnings are lints, which don't
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+        }
+    }
+
+    fn colour(self) -> &'static str {
+        match self {
+            Self::Error => RED,
+            Self::Warning => YELLOW,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Label {
+    range: TextRange,
+    message: String,
+    // The primary label is underlined with `^` and gives the location in the header; the others
+    // point at related code and are underlined with `-`
+    primary: bool,
+}
+
+impl Diagnostic {
+    pub(crate) fn error(
+        message: impl Into<String>,
+        range: TextRange,
+        label: impl Into<String>,
+    ) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "error",
+            message: message.into(),
+            labels: vec![Label {
+                range,
+                message: label.into(),
+                primary: true,
+            }],
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub(crate) fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub(crate) fn with_code(mut self, code: &'static str) -> Self {
+        self.code = code;
+        self
+    }
+
+    pub(crate) fn with_label(mut self, range: TextRange, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            range,
+            message: message.into(),
+            primary: false,
+        });
+        self
+    }
+
+    pub(crate) fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub(crate) fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub(crate) fn from_parse_error(error: &ParseError, source: &str) -> Self {
+        let mut label = error.expected_message();
+        if error.found.is_none() {
+            label += " after this";
+        }
+
+        if let Some((opener, opener_range)) = unclosed(error, source) {
+            let closer = closer(opener);
+            let name = match opener {
+                SyntaxKind::LParen => "paren",
+                SyntaxKind::LBracket => "bracket",
+                _ => "brace",
+            };
+
+            return Self::error(format!("unclosed {}", opener), error.range, label)
+                .with_code("unclosed-delimiter")
+                .with_label(opener_range, format!("opening {} here", name))
+                .with_help(format!("add a {} to close it", closer));
+        }
+
+        match error.found {
+            Some(SyntaxKind::Error) => Self::error(
+                format!("unrecognized token `{}`", &source[error.range]),
+                error.range,
+                label,
+            )
+            .with_code("unrecognized-token"),
+            Some(found) => Self::error(format!("unexpected {}", found), error.range, label)
+                .with_code("unexpected-token"),
+            None => Self::error("unexpected end of input", error.range, label)
+                .with_code("unexpected-end"),
+        }
+    }
+
+    // Evaluation errors often end in a suggestion after a `;`, which reads better as help. An
+    // error inside a function also points at the calls that led to it.
+    pub(crate) fn from_eval_error(error: &rollang::EvalError) -> Self {
+        let mut diagnostic = match error.message.split_once("; ") {
+            Some((message, help)) => Self::error(message, error.range, "").with_help(help),
+            None => Self::error(error.message.clone(), error.range, ""),
+        };
+        for (name, range) in &error.calls {
+            diagnostic = diagnostic.with_label(*range, format!("in this call to `{}`", name));
+        }
+        diagnostic.with_code("runtime")
+    }
+
+    // The lint's name is its code, and what `# allow(...)` needs to turn it off
+    pub(crate) fn from_lint(lint: &rollang::Lint) -> Self {
+        let mut diagnostic = Self::error(lint.message.clone(), lint.range, "")
+            .with_code(lint.name)
+            .with_note(format!("`# allow({})` turns this warning off", lint.name));
+        diagnostic.severity = Severity::Warning;
+        diagnostic.help = lint.help.clone();
+        diagnostic
+    }
+
+    // One line of `--format json`
+    pub(crate) fn to_json(&self, path: Option<&str>, source: &str) -> Value {
+        let labels: Vec<_> = self
+            .labels
+            .iter()
+            .map(|label| {
+                json!({
+                    "span": span_json(label.range, source),
+                    "message": label.message,
+                    "primary": label.primary,
+                })
+            })
+            .collect();
+        let primary = self.labels.iter().find(|label| label.primary).unwrap();
+
+        json!({
+            "version": crate::output::SCHEMA_VERSION,
+            "kind": "diagnostic",
+            "severity": self.severity.name(),
+            "code": self.code,
+            "message": self.message,
+            "file": path,
+            "span": span_json(primary.range, source),
+            "labels": labels,
+            "notes": self.notes,
+            "help": self.help,
+        })
+    }
+
+    // `path` is left out for input typed at the REPL, which is right above the error anyway
+    pub(crate) fn render(&self, path: Option<&str>, source: &str, colour: bool) -> String {
+        let paint = |style: &str, text: &str| {
+            if colour {
+                format!("{}{}{}", style, text, RESET)
+            } else {
+                text.to_string()
+            }
+        };
+
+        let mut labels: Vec<_> = self.labels.iter().collect();
+        labels.sort_by_key(|label| (label.range.start(), !label.primary));
+        let primary = self.labels.iter().find(|label| label.primary).unwrap();
+
+        let last_line = labels
+            .iter()
+            .map(|label| line_col(source, label.range.start()).0)
+            .max()
+            .unwrap();
+        let width = last_line.to_string().len();
+        let gutter = paint(BLUE, &format!("{:width$} |", "", width = width));
+
+        let mut out = format!(
+            "{}{}\n",
+            paint(self.severity.colour(), self.severity.name()),
+            paint(BOLD, &format!(": {}", self.message))
+        );
+
+        if let Some(path) = path {
+            let (line, col) = line_col(source, primary.range.start());
+            out += &format!(
+                "{}{} {}:{}:{}\n",
+                " ".repeat(width),
+                paint(BLUE, "-->"),
+                path,
+                line,
+                col
+            );
+        }
+        out += &format!("{}\n", gutter);
+
+        let mut shown_line = None;
+        for label in labels {
+            let (line, col) = line_col(source, label.range.start());
+            let text = source.lines().nth(line - 1).unwrap_or("");
+
+            if shown_line != Some(line) {
+                out += &format!(
+                    "{} {}\n",
+                    paint(BLUE, &format!("{:width$} |", line, width = width)),
+                    text
+                );
+                shown_line = Some(line);
+            }
+
+            // The underline stops at the end of the line, but is always at least one wide so that
+            // there's something to see at the end of the input
+            let line_len = text.chars().count() + 1;
+            let len = source[label.range]
+                .chars()
+                .count()
+                .min(line_len.saturating_sub(col))
+                .max(1);
+
+            let (mark, style) = if label.primary {
+                ("^", self.severity.colour())
+            } else {
+                ("-", BLUE)
+            };
+            let underline = paint(style, &mark.repeat(len));
+            let message = paint(style, &label.message);
+
+            out += &format!(
+                "{} {}{} {}\n",
+                gutter,
+                " ".repeat(col - 1),
+                underline,
+                message
+            );
+        }
+
+        for note in &self.notes {
+            out += &format!(
+                "{} {} {}\n",
+                " ".repeat(width),
+                paint(BOLD, "= note:"),
+                note
+            );
+        }
+        if let Some(help) = &self.help {
+            out += &format!(
+                "{} {} {}\n",
+                " ".repeat(width),
+                paint(CYAN, "= help:"),
+                help
+            );
+        }
+
+        // Labels without a message leave trailing spaces behind
+        out.lines()
+            .map(|line| format!("{}\n", line.trim_end()))
+            .collect()
+    }
+}
+
+// Byte offsets for tools that slice the source, and the line and column for people
+pub(crate) fn span_json(range: TextRange, source: &str) -> Value {
+    let (line, column) = line_col(source, range.start());
+    json!({
+        "start": u32::from(range.start()),
+        "end": u32::from(range.end()),
+        "line": line,
+        "column": column,
+    })
+}
+
+// Errors go to stderr, so that's what needs to be a terminal for colour to show up
+pub(crate) fn use_colour() -> bool {
+    std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+// `(line, col)`, both counted from 1 the way editors do
+pub(crate) fn line_col(source: &str, offset: TextSize) -> (usize, usize) {
+    let before = &source[..usize::from(offset).min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    let col = before[line_start..].chars().count() + 1;
+
+    (line, col)
+}
+
+// The innermost bracket before the error that is still open, if the parser was waiting for it
+// to be closed
+fn unclosed(error: &ParseError, source: &str) -> Option<(SyntaxKind, TextRange)> {
+    let mut open = Vec::new();
+
+    for token in Lexer::new(source).take_while(|token| token.range.start() < error.range.start()) {
+        match token.kind {
+            Ok(TokenKind::LParen | TokenKind::LBracket | TokenKind::LBrace) => {
+                open.push((SyntaxKind::from(token.kind.unwrap()), token.range))
+            }
+            Ok(TokenKind::RParen | TokenKind::RBracket | TokenKind::RBrace) => {
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+
+    open.pop()
+        .filter(|(opener, _)| error.expected.contains(&closer(*opener)))
+}
+
+fn closer(opener: SyntaxKind) -> SyntaxKind {
+    match opener {
+        SyntaxKind::LParen => SyntaxKind::RParen,
+        SyntaxKind::LBracket => SyntaxKind::RBracket,
+        _ => SyntaxKind::RBrace,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_parse(input: &str, expected: &str) {
+        let parse = parser::parse(input);
+        let diagnostic = Diagnostic::from_parse_error(&parse.errors()[0], input);
+        assert_eq!(diagnostic.render(Some("test.roll"), input, false), expected);
+    }
+
+    #[test]
+    fn unclosed_paren() {
+        check_parse(
+            "(foo",
+            "\
+error: unclosed ‘(’
+ --> test.roll:1:2
+  |
+1 | (foo
+  | - opening paren here
+  |  ^^^ expected ‘:’, ‘.’, ‘[’, ‘+’, ‘-’, ‘*’, ‘/’ or ‘)’ after this
+  = help: add a ‘)’ to close it
+",
+        );
+    }
+
+    #[test]
+    fn unclosed_brace_on_earlier_line() {
+        check_parse(
+            "f :: fn() {\n  1d20\n",
+            "\
+error: unclosed ‘{’
+ --> test.roll:2:7
+  |
+1 | f :: fn() {
+  |           - opening brace here
+2 |   1d20
+  |       ^ expected ‘}’ after this
+  = help: add a ‘}’ to close it
+",
+        );
+    }
+
+    #[test]
+    fn unexpected_token() {
+        check_parse(
+            "x := )",
+            "\
+error: unexpected ‘)’
+ --> test.roll:1:6
+  |
+1 | x := )
+  |      ^ expected number, dice, string, identifier, ‘roll’, ‘crit’, ‘-’, ‘(’ or ‘[’
+",
+        );
+    }
+
+    #[test]
+    fn unrecognized_token() {
+        check_parse(
+            "x := $",
+            "\
+error: unrecognized token `$`
+ --> test.roll:1:6
+  |
+1 | x := $
+  |      ^ expected number, dice, string, identifier, ‘roll’, ‘crit’, ‘-’, ‘(’ or ‘[’
+",
+        );
+    }
+
+    #[test]
+    fn eval_error_with_help() {
+        let error = rollang::EvalError {
+            message: "cannot assign to `hp` before it is declared; use `hp := ...` to declare it"
+                .to_string(),
+            range: TextRange::new(0.into(), 7.into()),
+            calls: Vec::new(),
+        };
+
+        assert_eq!(
+            Diagnostic::from_eval_error(&error).render(None, "hp = 10", false),
+            "\
+error: cannot assign to `hp` before it is declared
+  |
+1 | hp = 10
+  | ^^^^^^^
+  = help: use `hp := ...` to declare it
+"
+        );
+    }
+
+    #[test]
+    fn eval_error_in_function() {
+        let source = "f :: fn() -> number { 1 / 0 }\nx := f()";
+        let error = rollang::EvalError {
+            message: "division by zero".to_string(),
+            range: TextRange::new(22.into(), 27.into()),
+            calls: vec![("f".to_string(), TextRange::new(35.into(), 38.into()))],
+        };
+
+        assert_eq!(
+            Diagnostic::from_eval_error(&error).render(Some("test.roll"), source, false),
+            "\
+error: division by zero
+ --> test.roll:1:23
+  |
+1 | f :: fn() -> number { 1 / 0 }
+  |                       ^^^^^
+2 | x := f()
+  |      --- in this call to `f`
+"
+        );
+    }
+
+    #[test]
+    fn lint_warning() {
+        let source = "roll 1d20 +4";
+        let root = ast::Root::cast(parser::parse(source).syntax()).unwrap();
+        let lint = &rollang::lint(&root, &rollang::Env::with_seed(0))[0];
+        let diagnostic = Diagnostic::from_lint(lint);
+
+        assert_eq!(diagnostic.severity(), Severity::Warning);
+        assert_eq!(
+            diagnostic.render(Some("test.roll"), source, false),
+            "\
+warning: spaces between dice and their modifier need parentheses
+ --> test.roll:1:6
+  |
+1 | roll 1d20 +4
+  |      ^^^^^^^
+  = note: `# allow(dice-spacing)` turns this warning off
+  = help: write `1d20+4`
+"
+        );
+        assert_eq!(diagnostic.to_json(None, source)["severity"], "warning");
+    }
+
+    #[test]
+    fn note() {
+        let diagnostic = Diagnostic::error("bad roll", TextRange::new(0.into(), 4.into()), "here")
+            .with_note("dice need at least one side");
+
+        assert_eq!(
+            diagnostic.render(None, "1d20", false),
+            "error: bad roll\n  |\n1 | 1d20\n  | ^^^^ here\n  = note: dice need at least one side\n"
+        );
+    }
+
+    #[test]
+    fn colour() {
+        let diagnostic = Diagnostic::error("bad", TextRange::new(0.into(), 1.into()), "here");
+        let rendered = diagnostic.render(None, "x", true);
+
+        assert!(rendered.starts_with("\x1b[1;31merror\x1b[0m"));
+        assert!(rendered.contains("\x1b[1;31m^\x1b[0m \x1b[1;31mhere\x1b[0m"));
+    }
+
+    #[test]
+    fn json_of_unclosed_paren() {
+        let parse = parser::parse("(foo");
+        let json = Diagnostic::from_parse_error(&parse.errors()[0], "(foo").to_json(None, "(foo");
+
+        assert_eq!(json["code"], "unclosed-delimiter");
+        assert_eq!(json["severity"], "error");
+        assert_eq!(json["message"], "unclosed ‘(’");
+        assert_eq!(
+            json["span"],
+            json!({ "start": 1, "end": 4, "line": 1, "column": 2 })
+        );
+        assert_eq!(json["labels"][1]["message"], "opening paren here");
+        assert_eq!(json["help"], "add a ‘)’ to close it");
+    }
+
+    #[test]
+    fn line_col_of_first_char() {
+        assert_eq!(line_col("1d20", 0.into()), (1, 1));
+    }
+
+    #[test]
+    fn line_col_on_later_line() {
+        assert_eq!(line_col("a := 1\nb := 2d6", 12.into()), (2, 6));
+    }
+
+    #[test]
+    fn line_col_counts_chars() {
+        assert_eq!(line_col("\"é\" x", 5.into()), (1, 5));
+    }
+}
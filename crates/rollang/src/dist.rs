@@ -0,0 +1,305 @@
+use crate::formula::{Dice, Formula, InfixOp, Select};
+use std::collections::BTreeMap;
+use std::fmt;
+
+// Roughly how many additions working out a distribution may take before giving up, so that
+// `:dist 1000d1000` answers right away instead of hanging the REPL
+const MAX_STEPS: u64 = 50_000_000;
+
+const BAR_WIDTH: f64 = 40.0;
+
+// The exact chance of every total a formula can roll
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dist {
+    chances: BTreeMap<i64, f64>,
+}
+
+impl Dist {
+    pub fn of(formula: &Formula) -> Result<Self, String> {
+        let chances = match formula {
+            Formula::Number(n) => BTreeMap::from([(*n, 1.0)]),
+            Formula::Dice(dice) if dice.select.is_some() => selected_dice(dice)?,
+            Formula::Dice(dice) => dice_sum(dice)?,
+            Formula::Infix { op, lhs, rhs } => {
+                combine(*op, &Self::of(lhs)?.chances, &Self::of(rhs)?.chances)?
+            }
+            Formula::Neg(inner) => Self::of(inner)?
+                .chances
+                .into_iter()
+                .map(|(total, chance)| (-total, chance))
+                .collect(),
+            Formula::Annotated { inner, .. } => return Self::of(inner),
+            Formula::Mod { modifier, .. } => {
+                return Err(format!(
+                    "cannot work out the distribution of modifier `{}`",
+                    modifier.name
+                ))
+            }
+        };
+
+        Ok(Self { chances })
+    }
+
+    pub fn chances(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        self.chances.iter().map(|(total, chance)| (*total, *chance))
+    }
+
+    pub fn min(&self) -> i64 {
+        *self.chances.keys().next().unwrap()
+    }
+
+    pub fn max(&self) -> i64 {
+        *self.chances.keys().next_back().unwrap()
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.chances()
+            .map(|(total, chance)| total as f64 * chance)
+            .sum()
+    }
+}
+
+// Every total with its chance and a bar scaled to the likeliest one, then the average
+impl fmt::Display for Dist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .min()
+            .to_string()
+            .len()
+            .max(self.max().to_string().len());
+        let likeliest = self.chances.values().copied().fold(0.0, f64::max);
+
+        for (total, chance) in self.chances() {
+            let bar = (chance / likeliest * BAR_WIDTH).round() as usize;
+            writeln!(
+                f,
+                "{:>width$} {:>6.2}% {}",
+                total,
+                chance * 100.0,
+                "#".repeat(bar.max(1)),
+                width = width
+            )?;
+        }
+
+        write!(f, "mean {:.2}", self.mean())
+    }
+}
+
+// Adding one die at a time to the totals so far
+fn dice_sum(dice: &Dice) -> Result<BTreeMap<i64, f64>, String> {
+    let count = u64::from(dice.count);
+    let sides = u64::from(dice.sides);
+    if count * (count * sides) * sides > MAX_STEPS {
+        return Err(too_many_outcomes(dice));
+    }
+
+    let face_chance = 1.0 / sides as f64;
+    let mut chances = BTreeMap::from([(0, 1.0)]);
+
+    for _ in 0..dice.count {
+        let mut next = BTreeMap::new();
+        for (total, chance) in chances {
+            for face in 1..=i64::from(dice.sides) {
+                *next.entry(total + face).or_insert(0.0) += chance * face_chance;
+            }
+        }
+        chances = next;
+    }
+
+    Ok(chances)
+}
+
+// Which dice are kept depends on all of them at once, so this goes through every combination of
+// faces regardless of order, weighted by how many orders it can come up in
+fn selected_dice(dice: &Dice) -> Result<BTreeMap<i64, f64>, String> {
+    let count = usize::try_from(dice.count).unwrap();
+    let combinations = multichoose(u64::from(dice.sides), u64::from(dice.count));
+    if combinations.saturating_mul(u64::from(dice.count)) > MAX_STEPS {
+        return Err(too_many_outcomes(dice));
+    }
+
+    let ln_factorials: Vec<f64> = (0..=count)
+        .scan(0.0, |ln, n| {
+            if n > 0 {
+                *ln += (n as f64).ln();
+            }
+            Some(*ln)
+        })
+        .collect();
+    let ln_orders = ln_factorials[count] - count as f64 * f64::from(dice.sides).ln();
+
+    let mut chances = BTreeMap::new();
+    let mut faces = Vec::with_capacity(count);
+    for_each_combination(dice.sides, count, &mut faces, &mut |faces| {
+        let mut ln_chance = ln_orders;
+        let mut run = 1;
+        for i in 1..=faces.len() {
+            if i < faces.len() && faces[i] == faces[i - 1] {
+                run += 1;
+            } else {
+                ln_chance -= ln_factorials[run];
+                run = 1;
+            }
+        }
+
+        *chances.entry(kept_total(dice, faces)).or_insert(0.0) += ln_chance.exp();
+    });
+
+    Ok(chances)
+}
+
+// Calls `f` with every ascending run of `count` faces between 1 and `sides`
+fn for_each_combination(
+    sides: u32,
+    count: usize,
+    faces: &mut Vec<u32>,
+    f: &mut impl FnMut(&[u32]),
+) {
+    if faces.len() == count {
+        f(faces);
+        return;
+    }
+
+    let lowest = faces.last().copied().unwrap_or(1);
+    for face in lowest..=sides {
+        faces.push(face);
+        for_each_combination(sides, count, faces, f);
+        faces.pop();
+    }
+}
+
+// `faces` are in ascending order
+fn kept_total(dice: &Dice, faces: &[u32]) -> i64 {
+    let kept = usize::try_from(dice.kept()).unwrap();
+    let kept_faces = match dice.select {
+        Some(Select::KeepHighest(_) | Select::DropLowest(_)) => &faces[faces.len() - kept..],
+        _ => &faces[..kept],
+    };
+
+    kept_faces.iter().map(|&face| i64::from(face)).sum()
+}
+
+// The number of ways to choose `k` of `n` things when each can be chosen more than once
+fn multichoose(n: u64, k: u64) -> u64 {
+    (1..=k).fold(1_u64, |ways, i| ways.saturating_mul(n + i - 1) / i)
+}
+
+fn combine(
+    op: InfixOp,
+    lhs: &BTreeMap<i64, f64>,
+    rhs: &BTreeMap<i64, f64>,
+) -> Result<BTreeMap<i64, f64>, String> {
+    if (lhs.len() as u64).saturating_mul(rhs.len() as u64) > MAX_STEPS {
+        return Err("too many outcomes to work out exactly".to_string());
+    }
+
+    let mut chances = BTreeMap::new();
+    for (&l, &l_chance) in lhs {
+        for (&r, &r_chance) in rhs {
+            let total = op.apply(l, r).map_err(|e| e.to_string())?;
+            *chances.entry(total).or_insert(0.0) += l_chance * r_chance;
+        }
+    }
+
+    Ok(chances)
+}
+
+fn too_many_outcomes(dice: &Dice) -> String {
+    format!("`{}` has too many outcomes to work out exactly", dice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_chances(formula: Formula, expected: &[(i64, f64)]) {
+        let dist = Dist::of(&formula).unwrap();
+        let chances: Vec<_> = dist.chances().collect();
+
+        assert_eq!(chances.len(), expected.len());
+        for ((total, chance), (expected_total, expected_chance)) in chances.iter().zip(expected) {
+            assert_eq!(total, expected_total);
+            assert!(
+                (chance - expected_chance).abs() < 1e-9,
+                "{}: {} != {}",
+                total,
+                chance,
+                expected_chance
+            );
+        }
+    }
+
+    #[test]
+    fn two_d4() {
+        check_chances(
+            Formula::Dice(Dice::new(2, 4)),
+            &[
+                (2, 1.0 / 16.0),
+                (3, 2.0 / 16.0),
+                (4, 3.0 / 16.0),
+                (5, 4.0 / 16.0),
+                (6, 3.0 / 16.0),
+                (7, 2.0 / 16.0),
+                (8, 1.0 / 16.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn advantage() {
+        // The highest of two d4 is `n` in 2n-1 of the 16 ways they can land
+        check_chances(
+            Formula::Dice(Dice::new(2, 4).with_select(Select::KeepHighest(1))),
+            &[
+                (1, 1.0 / 16.0),
+                (2, 3.0 / 16.0),
+                (3, 5.0 / 16.0),
+                (4, 7.0 / 16.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn ability_score_mean() {
+        let dist = Dist::of(&Formula::Dice(
+            Dice::new(4, 6).with_select(Select::DropLowest(1)),
+        ))
+        .unwrap();
+
+        assert_eq!((dist.min(), dist.max()), (3, 18));
+        assert!((dist.mean() - 15869.0 / 1296.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn subtract_dice() {
+        check_chances(
+            Formula::Infix {
+                op: InfixOp::Sub,
+                lhs: Box::new(Formula::Number(3)),
+                rhs: Box::new(Formula::Dice(Dice::new(1, 2))),
+            },
+            &[(1, 0.5), (2, 0.5)],
+        );
+    }
+
+    #[test]
+    fn too_many_outcomes() {
+        assert_eq!(
+            Dist::of(&Formula::Dice(Dice::new(1000, 1000))),
+            Err("`1000d1000` has too many outcomes to work out exactly".to_string())
+        );
+    }
+
+    #[test]
+    fn format_histogram() {
+        let dist = Dist::of(&Formula::Dice(Dice::new(1, 4))).unwrap();
+        assert_eq!(
+            dist.to_string(),
+            "1  25.00% ########################################\n\
+             2  25.00% ########################################\n\
+             3  25.00% ########################################\n\
+             4  25.00% ########################################\n\
+             mean 2.50"
+        );
+    }
+}
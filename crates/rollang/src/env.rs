@@ -0,0 +1,254 @@
+use crate::crit::CritRule;
+use crate::formula::{Dice, Formula, Roll};
+use crate::func::Function;
+use crate::init::TurnOrder;
+use crate::record::RollRecord;
+use crate::rng::Rng;
+use crate::rollset;
+use crate::stdlib::Signature;
+use crate::val::Val;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[derive(Debug, Clone)]
+pub struct Env {
+    bindings: HashMap<String, Val>,
+    // The locals of each function call in progress. A call only sees its own frame and the
+    // top-level bindings, never those of its caller.
+    frames: Vec<HashMap<String, Val>>,
+    functions: HashMap<String, Rc<Function>>,
+    modifiers: HashMap<String, Rc<Function>>,
+    // Modifiers whose bodies are running, so that none can be applied inside itself
+    applying: Vec<String>,
+    rng: Rng,
+    seed: u64,
+    // Rolls made since the seed was set, and those not yet taken by the front end's log
+    seq: u64,
+    rolls: Vec<RollRecord>,
+    // The binding the current statement defines, which is what its rolls are for
+    binding: Option<String>,
+    crit_rule: CritRule,
+    // Kept between inputs so that the REPL can step through combat with `next`
+    initiative: Option<TurnOrder>,
+    // Text written by `print`, waiting for the front end to show it
+    output: String,
+}
+
+impl Default for Env {
+    fn default() -> Self {
+        Self::with_seed(Rng::entropy_seed())
+    }
+}
+
+impl Env {
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            bindings: [
+                ("pass".to_string(), Val::Bool(true)),
+                ("fail".to_string(), Val::Bool(false)),
+                // An attack roll, before any bonus
+                (
+                    "atk".to_string(),
+                    Val::Formula(Formula::Dice(Dice::new(1, 20))),
+                ),
+            ]
+            .into_iter()
+            .chain(rollset::builtin_rollsets())
+            .collect(),
+            frames: Vec::new(),
+            functions: HashMap::new(),
+            modifiers: HashMap::new(),
+            applying: Vec::new(),
+            rng: Rng::new(seed),
+            seed,
+            seq: 0,
+            rolls: Vec::new(),
+            binding: None,
+            crit_rule: CritRule::default(),
+            initiative: None,
+            output: String::new(),
+        }
+    }
+
+    // Restarts the sequence of rolls, as if the session had been started with this seed
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+        self.seed = seed;
+        self.seq = 0;
+    }
+
+    // Forgets everything the session defined, but carries on with the same sequence of rolls and
+    // crit rule, so that a seeded session stays repeatable
+    pub fn reset(&mut self) {
+        let fresh = Self {
+            rng: self.rng.clone(),
+            seed: self.seed,
+            seq: self.seq,
+            rolls: std::mem::take(&mut self.rolls),
+            crit_rule: self.crit_rule,
+            ..Self::with_seed(self.seed)
+        };
+        *self = fresh;
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // The rolls made since the last call, oldest first
+    pub fn take_rolls(&mut self) -> Vec<RollRecord> {
+        std::mem::take(&mut self.rolls)
+    }
+
+    pub(crate) fn record_roll(&mut self, label: Option<&str>, formula: &Formula, roll: &Roll) {
+        self.rolls.push(RollRecord {
+            seed: self.seed,
+            seq: self.seq,
+            binding: label.map(str::to_string).or_else(|| self.binding.clone()),
+            annotation: Some(roll.outcome.annotations().join(", "))
+                .filter(|annotation| !annotation.is_empty()),
+            formula: formula.clone(),
+            roll: roll.clone(),
+        });
+        self.seq += 1;
+    }
+
+    // Sets the binding that rolls are for, handing back the one it replaces
+    pub(crate) fn set_binding(&mut self, binding: Option<String>) -> Option<String> {
+        std::mem::replace(&mut self.binding, binding)
+    }
+
+    pub fn crit_rule(&self) -> CritRule {
+        self.crit_rule
+    }
+
+    pub fn set_crit_rule(&mut self, rule: CritRule) {
+        self.crit_rule = rule;
+    }
+
+    pub fn initiative(&self) -> Option<&TurnOrder> {
+        self.initiative.as_ref()
+    }
+
+    pub(crate) fn initiative_mut(&mut self) -> Option<&mut TurnOrder> {
+        self.initiative.as_mut()
+    }
+
+    pub(crate) fn set_initiative(&mut self, order: TurnOrder) {
+        self.initiative = Some(order);
+    }
+
+    pub fn take_output(&mut self) -> String {
+        std::mem::take(&mut self.output)
+    }
+
+    pub(crate) fn write_output(&mut self, text: &str) {
+        self.output.push_str(text);
+    }
+
+    pub(crate) fn store_binding(&mut self, name: String, val: Val) {
+        self.scope_mut().insert(name, val);
+    }
+
+    // The top-level bindings, sorted by name
+    pub fn bindings(&self) -> Vec<(&str, &Val)> {
+        let mut bindings: Vec<_> = self
+            .bindings
+            .iter()
+            .map(|(name, val)| (name.as_str(), val))
+            .collect();
+        bindings.sort_by_key(|(name, _)| *name);
+        bindings
+    }
+
+    pub(crate) fn get_binding(&self, name: &str) -> Option<&Val> {
+        self.frames
+            .last()
+            .and_then(|frame| frame.get(name))
+            .or_else(|| self.bindings.get(name))
+    }
+
+    // Updates an existing binding, returning `false` if there is none to update
+    pub(crate) fn assign_binding(&mut self, name: &str, val: Val) -> bool {
+        let binding = match self.frames.last_mut() {
+            Some(frame) if frame.contains_key(name) => frame.get_mut(name),
+            _ => self.bindings.get_mut(name),
+        };
+
+        match binding {
+            Some(binding) => {
+                *binding = val;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn scope_mut(&mut self) -> &mut HashMap<String, Val> {
+        self.frames.last_mut().unwrap_or(&mut self.bindings)
+    }
+
+    pub(crate) fn push_frame(&mut self, frame: HashMap<String, Val>) {
+        self.frames.push(frame);
+    }
+
+    pub(crate) fn pop_frame(&mut self) {
+        self.frames.pop();
+    }
+
+    pub(crate) fn call_depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub(crate) fn define_function(&mut self, func: Function) {
+        self.functions.insert(func.name.clone(), Rc::new(func));
+    }
+
+    pub(crate) fn get_function(&self, name: &str) -> Option<Rc<Function>> {
+        self.functions.get(name).cloned()
+    }
+
+    // The user-defined functions, sorted by name
+    pub fn functions(&self) -> Vec<(&str, Signature)> {
+        signatures(&self.functions)
+    }
+
+    pub(crate) fn define_modifier(&mut self, modifier: Function) {
+        self.modifiers
+            .insert(modifier.name.clone(), Rc::new(modifier));
+    }
+
+    pub(crate) fn get_modifier(&self, name: &str) -> Option<Rc<Function>> {
+        self.modifiers.get(name).cloned()
+    }
+
+    pub fn modifiers(&self) -> Vec<(&str, Signature)> {
+        signatures(&self.modifiers)
+    }
+
+    pub(crate) fn start_applying(&mut self, name: &str) -> bool {
+        if self.applying.iter().any(|applying| applying == name) {
+            return false;
+        }
+
+        self.applying.push(name.to_string());
+        true
+    }
+
+    pub(crate) fn finish_applying(&mut self) {
+        self.applying.pop();
+    }
+
+    pub(crate) fn rng(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+}
+
+fn signatures(funcs: &HashMap<String, Rc<Function>>) -> Vec<(&str, Signature)> {
+    let mut signatures: Vec<_> = funcs
+        .iter()
+        .map(|(name, func)| (name.as_str(), func.signature()))
+        .collect();
+    signatures.sort_by_key(|(name, _)| *name);
+    signatures
+}
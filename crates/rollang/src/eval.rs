@@ -0,0 +1,1447 @@
+use crate::comp::Comp;
+use crate::env::Env;
+use crate::formula::{Dice, Formula, InfixOp, Modifier, Roll, Roller, Select};
+use crate::func::{Function, Ret, MAX_CALL_DEPTH, RESULT};
+use crate::rng::Rng;
+use crate::stdlib;
+use crate::val::Val;
+use ast::{Expr, Root, Stmt};
+use std::collections::HashMap;
+use std::fmt;
+use syntax::{SyntaxKind, SyntaxNode};
+use text_size::TextRange;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalError {
+    pub message: String,
+    pub range: TextRange,
+    // The calls of the functions the error happened in, innermost first, by the function's name
+    // and where it was called. A call that recurses from the same place is only listed once.
+    pub calls: Vec<(String, TextRange)>,
+}
+
+impl EvalError {
+    pub(crate) fn new(message: impl Into<String>, node: &SyntaxNode) -> Self {
+        Self {
+            message: message.into(),
+            range: node.text_range(),
+            calls: Vec::new(),
+        }
+    }
+
+    fn in_call(mut self, name: &str, call: &SyntaxNode) -> Self {
+        let range = call.text_range();
+        let at_call = range.contains_range(self.range);
+        if !at_call && self.calls.iter().all(|(_, seen)| *seen != range) {
+            self.calls.push((name.to_string(), range));
+        }
+        self
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+// Evaluates every statement in order, producing the value of the last one
+pub fn eval(root: &Root, env: &mut Env) -> Result<Val, EvalError> {
+    let mut val = Val::Unit;
+
+    for stmt in root.stmts() {
+        val = eval_stmt(&stmt, env)?;
+    }
+
+    Ok(val)
+}
+
+pub fn eval_stmt(stmt: &Stmt, env: &mut Env) -> Result<Val, EvalError> {
+    match stmt {
+        Stmt::VariableDef(def) => {
+            let name = def
+                .name()
+                .ok_or_else(|| EvalError::new("missing binding name", stmt.syntax()))?;
+            let outer = env.set_binding(Some(name.text().to_string()));
+            let value = eval_expr(def.value(), stmt.syntax(), env);
+            env.set_binding(outer);
+            let mut value = value?;
+
+            // A group takes the name it's bound to, so that it's shown as `groupcheck`
+            if let Val::Comp(comp) = &mut value {
+                if comp.name.is_empty() {
+                    comp.name = name.text().to_string();
+                }
+            }
+
+            env.store_binding(name.text().to_string(), value);
+            Ok(Val::Unit)
+        }
+        Stmt::Assignment(assignment) => {
+            let name = assignment
+                .name()
+                .ok_or_else(|| EvalError::new("missing binding name", stmt.syntax()))?;
+            let outer = env.set_binding(Some(name.text().to_string()));
+            let value = eval_expr(assignment.value(), stmt.syntax(), env);
+            env.set_binding(outer);
+            let value = value?;
+
+            if env.assign_binding(name.text(), value) {
+                Ok(Val::Unit)
+            } else {
+                Err(EvalError::new(
+                    format!(
+                        "cannot assign to `{0}` before it is declared; use `{0} := ...` to declare it",
+                        name.text()
+                    ),
+                    stmt.syntax(),
+                ))
+            }
+        }
+        Stmt::FnDef(def) => {
+            let func = Function::from_def(def)?;
+
+            if env.call_depth() > 0 {
+                return Err(EvalError::new(
+                    "functions can only be defined at the top level",
+                    stmt.syntax(),
+                ));
+            }
+
+            if stdlib::builtin(&func.name).is_some() {
+                return Err(EvalError::new(
+                    format!(
+                        "`{}` is a builtin function and cannot be redefined",
+                        func.name
+                    ),
+                    stmt.syntax(),
+                ));
+            }
+
+            env.define_function(func);
+            Ok(Val::Unit)
+        }
+        Stmt::CompDef(def) => {
+            let name = def
+                .name()
+                .ok_or_else(|| EvalError::new("missing comp name", stmt.syntax()))?
+                .text()
+                .to_string();
+            let mut fields: Vec<(String, Val)> = Vec::new();
+
+            for field in def.fields() {
+                let field_name = field
+                    .name()
+                    .ok_or_else(|| EvalError::new("missing field name", field.syntax()))?
+                    .text()
+                    .to_string();
+                if fields.iter().any(|(existing, _)| *existing == field_name) {
+                    return Err(EvalError::new(
+                        format!("`{}` already has a field `{}`", name, field_name),
+                        field.syntax(),
+                    ));
+                }
+
+                let value = eval_expr(field.value(), field.syntax(), env)?;
+                fields.push((field_name, value));
+            }
+
+            env.store_binding(name.clone(), Val::Comp(Comp { name, fields }));
+            Ok(Val::Unit)
+        }
+        Stmt::ModDef(def) => {
+            let modifier = Function::from_mod_def(def)?;
+
+            if env.call_depth() > 0 {
+                return Err(EvalError::new(
+                    "modifiers can only be defined at the top level",
+                    stmt.syntax(),
+                ));
+            }
+
+            env.define_modifier(modifier);
+            Ok(Val::Unit)
+        }
+        // Function bodies deal with `return` themselves, so this one is misplaced
+        Stmt::ReturnStmt(_) => Err(EvalError::new(
+            "`return` outside of a function",
+            stmt.syntax(),
+        )),
+        Stmt::Expr(expr) => eval_expr(Some(expr.clone()), expr.syntax(), env),
+    }
+}
+
+fn call_function(
+    func: &Function,
+    args: Vec<Val>,
+    call: &SyntaxNode,
+    env: &mut Env,
+) -> Result<Val, EvalError> {
+    let args = stdlib::check_args(&func.name, &[func.signature()], args)
+        .map_err(|message| EvalError::new(message, call))?;
+
+    let frame = func
+        .params
+        .iter()
+        .map(|(name, _)| name.clone())
+        .zip(args)
+        .collect();
+
+    run_function(func, frame, call, env).map_err(|error| error.in_call(&func.name, call))
+}
+
+fn run_function(
+    func: &Function,
+    mut frame: HashMap<String, Val>,
+    call: &SyntaxNode,
+    env: &mut Env,
+) -> Result<Val, EvalError> {
+    if env.call_depth() >= MAX_CALL_DEPTH {
+        return Err(EvalError::new(
+            format!(
+                "too many nested calls while calling `{}` (the limit is {})",
+                func.name, MAX_CALL_DEPTH
+            ),
+            call,
+        ));
+    }
+
+    // Named returns start out unassigned, except for a modifier's `result`
+    if let Ret::Named(named) = &func.ret {
+        for (name, _) in named {
+            frame.entry(name.clone()).or_insert(Val::Unit);
+        }
+    }
+
+    env.push_frame(frame);
+    let result = eval_body(func, call, env);
+    env.pop_frame();
+
+    result
+}
+
+fn eval_body(func: &Function, call: &SyntaxNode, env: &mut Env) -> Result<Val, EvalError> {
+    let mut last = Val::Unit;
+    let mut returned = None;
+
+    for stmt in func.body.stmts() {
+        if let Stmt::ReturnStmt(ret) = &stmt {
+            returned = ret
+                .value()
+                .map(|value| eval_expr(Some(value), stmt.syntax(), env))
+                .transpose()?;
+            break;
+        }
+
+        last = eval_stmt(&stmt, env)?;
+    }
+
+    let value = match (&func.ret, returned) {
+        (_, Some(value)) => value,
+        (Ret::Named(named), None) => {
+            let mut values = Vec::new();
+
+            for (name, _) in named {
+                match env.get_binding(name) {
+                    Some(Val::Unit) | None => {
+                        return Err(EvalError::new(
+                            format!("`{}` returned without assigning `{}`", func.name, name),
+                            call,
+                        ))
+                    }
+                    Some(value) => values.push(value.clone()),
+                }
+            }
+
+            if values.len() == 1 {
+                values.pop().unwrap()
+            } else {
+                Val::Vec(values)
+            }
+        }
+        (_, None) => last,
+    };
+
+    let ty = func.ret.ty();
+    let given = value.ty();
+    value.coerce(&ty).ok_or_else(|| {
+        EvalError::new(
+            format!(
+                "`{}` should return {}, but returned {}",
+                func.name, ty, given
+            ),
+            call,
+        )
+    })
+}
+
+// `parent` is used to point at something when the expression itself is missing
+fn eval_expr(expr: Option<Expr>, parent: &SyntaxNode, env: &mut Env) -> Result<Val, EvalError> {
+    let expr = expr.ok_or_else(|| EvalError::new("missing expression", parent))?;
+    let node = expr.syntax().clone();
+
+    match expr {
+        Expr::Literal(literal) => literal
+            .parse()
+            .map(Val::Number)
+            .ok_or_else(|| EvalError::new("number is too large", &node)),
+        Expr::StringLiteral(string) => string
+            .value()
+            .map(Val::Str)
+            .ok_or_else(|| EvalError::new("malformed string", &node)),
+        Expr::DiceExpr(dice) => {
+            let (count, sides) = dice
+                .count()
+                .zip(dice.sides())
+                .ok_or_else(|| EvalError::new("dice are too large", &node))?;
+            let mut formula = Dice::new(count, sides);
+
+            if let Some((select, n)) = dice.select() {
+                let n = n.ok_or_else(|| EvalError::new("dice are too large", &node))?;
+                if n > count {
+                    let verb = match select {
+                        ast::Select::KeepHighest | ast::Select::KeepLowest => "keep",
+                        ast::Select::DropHighest | ast::Select::DropLowest => "drop",
+                    };
+                    return Err(EvalError::new(
+                        format!("cannot {} {} of {} dice", verb, n, count),
+                        &node,
+                    ));
+                }
+
+                formula = formula.with_select(match select {
+                    ast::Select::KeepHighest => Select::KeepHighest(n),
+                    ast::Select::KeepLowest => Select::KeepLowest(n),
+                    ast::Select::DropHighest => Select::DropHighest(n),
+                    ast::Select::DropLowest => Select::DropLowest(n),
+                });
+            }
+
+            Ok(Val::Formula(Formula::Dice(formula)))
+        }
+        Expr::VariableRef(var) => {
+            let name = var
+                .name()
+                .ok_or_else(|| EvalError::new("missing variable name", &node))?;
+            let name = name.text();
+
+            if let Some(val) = env.get_binding(name) {
+                return Ok(val.clone());
+            }
+
+            // A builtin that can take no arguments, like `next`, can also be called by its name
+            match stdlib::builtin(name) {
+                Some(builtin) if builtin.signatures().iter().any(|sig| sig.params.is_empty()) => {
+                    builtin
+                        .call(env, Vec::new())
+                        .map_err(|message| EvalError::new(message, &node))
+                }
+                _ => Err(EvalError::new(
+                    format!("unbound variable `{}`", name),
+                    &node,
+                )),
+            }
+        }
+        Expr::CallExpr(call) => {
+            let name = call
+                .name()
+                .ok_or_else(|| EvalError::new("missing function name", &node))?;
+            let name = name.text();
+
+            if let Some(func) = env.get_function(name) {
+                let args = call
+                    .args()
+                    .map(|arg| eval_expr(Some(arg), &node, env))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                return call_function(&func, args, &node, env);
+            }
+
+            let Some(builtin) = stdlib::builtin(name) else {
+                let message = match env.get_binding(name) {
+                    // `max(a b)` parses as `max(a(b))`, so point out the likely mistake
+                    Some(val) if call.is_juxtaposed() => format!(
+                        "`{}` is a {}, not a function; is there a missing comma or operator after it?",
+                        name,
+                        val.ty()
+                    ),
+                    Some(val) => format!("`{}` is a {}, not a function", name, val.ty()),
+                    None => format!("unknown function `{}`", name),
+                };
+                return Err(EvalError::new(message, &node));
+            };
+
+            let args = call
+                .args()
+                .map(|arg| eval_expr(Some(arg), &node, env))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            builtin
+                .call(env, args)
+                .map_err(|message| EvalError::new(message, &node))
+        }
+        Expr::ModExpr(modded) => {
+            let name = modded
+                .name()
+                .ok_or_else(|| EvalError::new("missing modifier name", &node))?;
+            let name = name.text();
+
+            let inner = match eval_expr(modded.expr(), &node, env)? {
+                Val::Number(n) => Formula::Number(n),
+                Val::Formula(formula) => formula,
+                Val::Roll(_) => {
+                    return Err(EvalError::new(
+                        "cannot apply a modifier to a value that has already been rolled; apply it before rolling",
+                        &node,
+                    ))
+                }
+                val => {
+                    return Err(EvalError::new(
+                        format!("cannot apply a modifier to {}", val.ty()),
+                        &node,
+                    ))
+                }
+            };
+
+            let Some(def) = env.get_modifier(name) else {
+                let message = if env.get_function(name).is_some() || stdlib::builtin(name).is_some()
+                {
+                    format!("`{}` is a function, not a modifier", name)
+                } else {
+                    format!("unknown modifier `{}`", name)
+                };
+                return Err(EvalError::new(message, &node));
+            };
+
+            let args = modded
+                .args()
+                .map(|arg| eval_expr(Some(arg), &node, env))
+                .collect::<Result<Vec<_>, _>>()?;
+            let args = stdlib::check_args(name, &[def.signature()], args)
+                .map_err(|message| EvalError::new(message, &node))?;
+
+            Ok(Val::Formula(Formula::Mod {
+                inner: Box::new(inner),
+                modifier: Modifier {
+                    name: name.to_string(),
+                    args,
+                },
+            }))
+        }
+        Expr::AnnotatedExpr(annotated) => {
+            let annotation = annotated
+                .annotation()
+                .ok_or_else(|| EvalError::new("missing annotation", &node))?;
+
+            let inner = match eval_expr(annotated.expr(), &node, env)? {
+                Val::Number(n) => Formula::Number(n),
+                Val::Formula(formula) => formula,
+                Val::Roll(_) => {
+                    return Err(EvalError::new(
+                        "cannot annotate a value that has already been rolled; annotate it before rolling",
+                        &node,
+                    ))
+                }
+                val => {
+                    return Err(EvalError::new(
+                        format!("cannot annotate {}", val.ty()),
+                        &node,
+                    ))
+                }
+            };
+
+            Ok(Val::Formula(Formula::Annotated {
+                inner: Box::new(inner),
+                annotation,
+            }))
+        }
+        Expr::ParenExpr(paren) => eval_expr(paren.expr(), &node, env),
+        Expr::PrefixExpr(prefix) => eval_expr(prefix.expr(), &node, env)?
+            .neg()
+            .map_err(|message| EvalError::new(message, &node)),
+        Expr::InfixExpr(infix) => {
+            let op = match infix.op().map(|op| op.kind()) {
+                Some(SyntaxKind::Plus) => InfixOp::Add,
+                Some(SyntaxKind::Minus) => InfixOp::Sub,
+                Some(SyntaxKind::Star) => InfixOp::Mul,
+                Some(SyntaxKind::Slash) => InfixOp::Div,
+                _ => return Err(EvalError::new("missing operator", &node)),
+            };
+
+            let lhs = eval_expr(infix.lhs(), &node, env)?;
+            let rhs = eval_expr(infix.rhs(), &node, env)?;
+
+            lhs.infix(op, rhs)
+                .map_err(|message| EvalError::new(message, &node))
+        }
+        Expr::RollExpr(roll) => eval_expr(roll.expr(), &node, env)?
+            .roll(env)
+            .map_err(|message| EvalError::new(message, &node)),
+        Expr::ArrayExpr(array) => {
+            let items = array
+                .items()
+                .map(|item| eval_expr(Some(item), &node, env))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            // Arrays only hold one type of value
+            if let Some(first) = items.first() {
+                if let Some(other) = items.iter().find(|item| item.ty() != first.ty()) {
+                    return Err(EvalError::new(
+                        format!(
+                            "arrays can only hold one type, but this one has {} and {}",
+                            first.ty(),
+                            other.ty()
+                        ),
+                        &node,
+                    ));
+                }
+            }
+
+            Ok(Val::Vec(items))
+        }
+        Expr::GroupExpr(group) => {
+            let mut fields: Vec<(String, Val)> = Vec::new();
+
+            for member in group.members() {
+                let name = member
+                    .name()
+                    .ok_or_else(|| EvalError::new("missing member name", member.syntax()))?
+                    .text()
+                    .to_string();
+                if fields.iter().any(|(existing, _)| *existing == name) {
+                    return Err(EvalError::new(
+                        format!("the group already has a member named `{}`", name),
+                        member.syntax(),
+                    ));
+                }
+
+                let value = eval_expr(member.value(), member.syntax(), env)?;
+                fields.push((name, value));
+            }
+
+            Ok(Val::Comp(Comp {
+                name: String::new(),
+                fields,
+            }))
+        }
+        Expr::IndexExpr(index) => {
+            let base = eval_expr(index.expr(), &node, env)?;
+            let key = eval_expr(index.index(), &node, env)?;
+
+            match (base, key) {
+                (Val::Comp(comp), Val::Str(key)) => comp.field(&key).cloned().ok_or_else(|| {
+                    EvalError::new(format!("`{}` has no entry {:?}", comp.name, key), &node)
+                }),
+                (Val::Vec(items), Val::Number(i)) => usize::try_from(i)
+                    .ok()
+                    .and_then(|i| items.get(i))
+                    .cloned()
+                    .ok_or_else(|| {
+                        EvalError::new(
+                            format!("index {} is out of range for {} items", i, items.len()),
+                            &node,
+                        )
+                    }),
+                (base, key) => Err(EvalError::new(
+                    format!("cannot index {} with {}", base.ty(), key.ty()),
+                    &node,
+                )),
+            }
+        }
+        Expr::FieldExpr(access) => {
+            let field = access
+                .field()
+                .ok_or_else(|| EvalError::new("missing field name", &node))?;
+            let field = field.text();
+
+            match eval_expr(access.expr(), &node, env)? {
+                Val::Comp(comp) => comp.field(field).cloned().ok_or_else(|| {
+                    EvalError::new(format!("`{}` has no field `{}`", comp.name, field), &node)
+                }),
+                val => Err(EvalError::new(
+                    format!("cannot access field `{}` on {}", field, val.ty()),
+                    &node,
+                )),
+            }
+        }
+        Expr::CritExpr(crit) => {
+            let formula = match eval_expr(crit.expr(), &node, env)? {
+                Val::Number(n) => Formula::Number(n),
+                Val::Formula(formula) => formula,
+                Val::Roll(_) => {
+                    return Err(EvalError::new(
+                        "cannot crit a value that has already been rolled; crit the dice before rolling them",
+                        &node,
+                    ))
+                }
+                val => return Err(EvalError::new(format!("cannot crit {}", val.ty()), &node)),
+            };
+
+            Ok(Val::from(formula.crit(env.crit_rule())))
+        }
+    }
+}
+
+// Rolling in an environment runs the bodies of modifiers as they come up
+impl Roller for Env {
+    fn rng(&mut self) -> &mut Rng {
+        Env::rng(self)
+    }
+
+    fn record(&mut self, label: Option<&str>, formula: &Formula, roll: &Roll) {
+        self.record_roll(label, formula, roll);
+    }
+
+    fn apply_mod(&mut self, modifier: &Modifier, total: i64) -> Result<i64, String> {
+        let name = &modifier.name;
+        let def = self
+            .get_modifier(name)
+            .ok_or_else(|| format!("unknown modifier `{}`", name))?;
+
+        if !self.start_applying(name) {
+            return Err(format!(
+                "modifier `{}` cannot be applied inside itself",
+                name
+            ));
+        }
+
+        let mut frame: HashMap<_, _> = def
+            .params
+            .iter()
+            .map(|(param, _)| param.clone())
+            .zip(modifier.args.iter().cloned())
+            .collect();
+        frame.insert(RESULT.to_string(), Val::Number(total));
+
+        let result = run_function(&def, frame, def.body.syntax(), self);
+        self.finish_applying();
+
+        match result {
+            Ok(Val::Number(n)) => Ok(n),
+            Ok(val) => Err(format!(
+                "`{}` must leave `{}` a number, not {}",
+                name,
+                RESULT,
+                val.ty()
+            )),
+            Err(error) => Err(format!("in modifier `{}`: {}", name, error.message)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crit::CritRule;
+
+    fn run(input: &str, env: &mut Env) -> Result<Val, EvalError> {
+        let parse = parser::parse(input);
+        assert!(parse.errors().is_empty(), "{}", parse.debug_tree());
+
+        let root = Root::cast(parse.syntax()).unwrap();
+        eval(&root, env)
+    }
+
+    fn check(input: &str, expected: &str) {
+        let mut env = Env::with_seed(1234);
+        assert_eq!(run(input, &mut env).unwrap().to_string(), expected);
+    }
+
+    fn check_error(input: &str, expected: &str) {
+        let mut env = Env::with_seed(1234);
+        assert_eq!(run(input, &mut env).unwrap_err().message, expected);
+    }
+
+    #[test]
+    fn eval_arithmetic() {
+        check("1+2*3-4", "3");
+    }
+
+    #[test]
+    fn eval_floor_division() {
+        check("7/2", "3");
+        check("-7/2", "-4");
+    }
+
+    #[test]
+    fn dice_are_not_rolled_until_asked() {
+        check("d20+5", "1d20+5");
+    }
+
+    #[test]
+    fn roll_keeps_every_face() {
+        let mut env = Env::with_seed(1234);
+        let val = run("roll 4d6+2", &mut env).unwrap();
+
+        match val {
+            Val::Roll(roll) => {
+                let text = roll.to_string();
+                assert!(text.starts_with("4d6=["), "{}", text);
+                assert!(text.ends_with(&format!(" +2 ->{}", roll.total)), "{}", text);
+                assert!((6..=26).contains(&roll.total));
+            }
+            val => panic!("expected a roll, got {:?}", val),
+        }
+    }
+
+    #[test]
+    fn same_seed_same_result() {
+        let mut a = Env::with_seed(99);
+        let mut b = Env::with_seed(99);
+        assert_eq!(run("roll 10d20", &mut a), run("roll 10d20", &mut b));
+    }
+
+    #[test]
+    fn bindings_are_stored() {
+        check("let dmg = 2d6+3\ndmg*2", "(2d6+3)*2");
+    }
+
+    #[test]
+    fn rolled_value_acts_as_its_total() {
+        let mut env = Env::with_seed(5);
+        let Val::Roll(roll) = run("roll 1d20", &mut env).unwrap() else {
+            panic!("expected a roll");
+        };
+
+        let mut env = Env::with_seed(5);
+        assert_eq!(
+            run("let r = roll 1d20\nr+1", &mut env),
+            Ok(Val::Number(roll.total + 1))
+        );
+    }
+
+    #[test]
+    fn crit_doubles_dice_by_default() {
+        check("crit 3d8+4", "6d8+4");
+    }
+
+    #[test]
+    fn crit_follows_session_rule() {
+        let mut env = Env::with_seed(0);
+        env.set_crit_rule(CritRule::MaxPlusRoll);
+        assert_eq!(run("crit 2d6+1", &mut env).unwrap().to_string(), "2d6+12+1");
+
+        env.set_crit_rule(CritRule::DoubleTotal);
+        assert_eq!(
+            run("crit 2d6+1", &mut env).unwrap().to_string(),
+            "(2d6+1)*2"
+        );
+    }
+
+    #[test]
+    fn crit_of_binding() {
+        check("let dmg = 1d8+3\ncrit dmg", "2d8+3");
+    }
+
+    #[test]
+    fn crit_of_flat_damage() {
+        check("crit 5", "5");
+    }
+
+    #[test]
+    fn roll_crit() {
+        let mut env = Env::with_seed(3);
+        let val = run("roll crit 3d8+4", &mut env).unwrap();
+        assert!(val.to_string().starts_with("6d8=["), "{}", val);
+    }
+
+    #[test]
+    fn cannot_crit_rolled_value() {
+        check_error(
+            "crit (roll 1d8)",
+            "cannot crit a value that has already been rolled; crit the dice before rolling them",
+        );
+    }
+
+    #[test]
+    fn cannot_roll_twice() {
+        check_error("roll (roll d4)", "this value has already been rolled");
+    }
+
+    #[test]
+    fn unbound_variable() {
+        check_error("foo + 1", "unbound variable `foo`");
+    }
+
+    #[test]
+    fn error_points_at_expression() {
+        let mut env = Env::with_seed(0);
+        let error = run("1 + 10/(2-2)", &mut env).unwrap_err();
+        assert_eq!(error.message, "division by zero");
+        assert_eq!(error.range, TextRange::new(4.into(), 12.into()));
+    }
+
+    #[test]
+    fn call_with_parenthesised_args() {
+        check("max(3, 2*5)", "10");
+    }
+
+    #[test]
+    fn call_by_juxtaposition() {
+        check("tostring 1d20", "\"1d20\"");
+        check("abs (-3) + 1", "4");
+    }
+
+    #[test]
+    fn call_of_rolled_dice() {
+        let mut env = Env::with_seed(8);
+        let Val::Roll(roll) = run("roll 4d6", &mut env).unwrap() else {
+            panic!("expected a roll");
+        };
+        let highest = roll.outcome.faces().into_iter().max().unwrap();
+
+        let mut env = Env::with_seed(8);
+        assert_eq!(
+            run("max (roll 4d6)", &mut env),
+            Ok(Val::Number(highest.into()))
+        );
+    }
+
+    #[test]
+    fn print_collects_output() {
+        let mut env = Env::with_seed(0);
+        assert_eq!(run(r#"print ("Attack", ": ")"#, &mut env), Ok(Val::Unit));
+        assert_eq!(run("print (2d6+1)", &mut env), Ok(Val::Unit));
+        assert_eq!(env.take_output(), "Attack: 2d6+1\n");
+    }
+
+    #[test]
+    fn unknown_function() {
+        check_error("foo(1)", "unknown function `foo`");
+    }
+
+    #[test]
+    fn calling_a_binding_hints_at_missing_comma() {
+        check_error(
+            "let a = 3\nmax(a 2)",
+            "`a` is a number, not a function; is there a missing comma or operator after it?",
+        );
+    }
+
+    #[test]
+    fn call_errors_point_at_call() {
+        let mut env = Env::with_seed(0);
+        let error = run("1 + abs(1, 2)", &mut env).unwrap_err();
+        assert_eq!(error.message, "`abs` takes 1 argument but 2 were given");
+        assert_eq!(error.range, TextRange::new(4.into(), 13.into()));
+    }
+
+    #[test]
+    fn short_variable_definition_and_assignment() {
+        check("x := 1\nx = x + 1\nx", "2");
+    }
+
+    #[test]
+    fn cannot_assign_undeclared_variable() {
+        check_error(
+            "x = 1",
+            "cannot assign to `x` before it is declared; use `x := ...` to declare it",
+        );
+    }
+
+    #[test]
+    fn function_with_named_return() {
+        check(
+            "addnums :: fn(a: number, b: number) -> (c: number) { c = a + b; return c }\naddnums(2, 3)",
+            "5",
+        );
+    }
+
+    #[test]
+    fn named_returns_are_returned_at_end_of_body() {
+        check(
+            "split :: fn(x: number) -> (half: number, rest: number) { half = x / 2; rest = x - half }\nsplit 7",
+            "[3, 4]",
+        );
+    }
+
+    #[test]
+    fn function_returns_last_statement() {
+        check(
+            "bonus :: fn(d: dice) -> dice { d + 2 }\nbonus(1d6)",
+            "1d6+2",
+        );
+    }
+
+    #[test]
+    fn arguments_are_coerced_to_parameter_types() {
+        check("double :: fn(x: number) { x * 2 }\ndouble(roll 1d1)", "2");
+    }
+
+    #[test]
+    fn functions_see_top_level_bindings() {
+        check("bonus := 3\nf :: fn(x: number) { x + bonus }\nf 1", "4");
+    }
+
+    #[test]
+    fn functions_do_not_see_caller_locals() {
+        check_error(
+            "g :: fn() { secret }\nf :: fn() { secret := 1; g() }\nf()",
+            "unbound variable `secret`",
+        );
+    }
+
+    #[test]
+    fn locals_do_not_outlive_call() {
+        check_error("f :: fn() { x := 1 }\nf()\nx", "unbound variable `x`");
+    }
+
+    #[test]
+    fn wrong_argument_type_for_function() {
+        check_error(
+            "f :: fn(x: number) { x }\nf(1d6)",
+            "`f` expects (number), but was given (dice)",
+        );
+    }
+
+    #[test]
+    fn wrong_return_type() {
+        check_error(
+            "f :: fn() -> number { 1d6 }\nf()",
+            "`f` should return number, but returned dice",
+        );
+    }
+
+    #[test]
+    fn unassigned_named_return() {
+        check_error(
+            "f :: fn() -> (c: number) { return }\nf()",
+            "`f` returned without assigning `c`",
+        );
+    }
+
+    #[test]
+    fn unknown_parameter_type() {
+        check_error("f :: fn(x: numbr) { x }", "unknown type `numbr`");
+    }
+
+    #[test]
+    fn recursion_is_limited() {
+        check_error(
+            "f :: fn() { f() }\nf()",
+            "too many nested calls while calling `f` (the limit is 64)",
+        );
+    }
+
+    #[test]
+    fn error_in_function_keeps_its_calls() {
+        let input =
+            "f :: fn(n: number) -> number { return n / 0 }\ng :: fn() -> number { f(1) }\ng()";
+        let error = run(input, &mut Env::with_seed(0)).unwrap_err();
+        let calls: Vec<_> = error
+            .calls
+            .iter()
+            .map(|(name, range)| (name.as_str(), input[*range].trim_end()))
+            .collect();
+
+        assert_eq!(&input[error.range], "n / 0 ");
+        assert_eq!(calls, [("f", "f(1)"), ("g", "g()")]);
+    }
+
+    #[test]
+    fn recursive_call_is_kept_once() {
+        let input = "f :: fn() { f() }\nf()";
+        let error = run(input, &mut Env::with_seed(0)).unwrap_err();
+        assert_eq!(error.calls.len(), 1);
+    }
+
+    #[test]
+    fn cannot_redefine_builtin() {
+        check_error(
+            "max :: fn(a: number) { a }",
+            "`max` is a builtin function and cannot be redefined",
+        );
+    }
+
+    #[test]
+    fn return_outside_function() {
+        check_error("return 1", "`return` outside of a function");
+    }
+
+    #[test]
+    fn modifier_subtracts_from_result() {
+        let mut env = Env::with_seed(1234);
+        let input =
+            "Poison :: mod(d: Roll<Pending>) { result = result - (roll d) }\nroll d20:Poison(d4)";
+        for _ in 0..50 {
+            match run(input, &mut env).unwrap() {
+                Val::Roll(roll) => assert!(roll.total <= 19),
+                val => panic!("expected a roll, got {}", val),
+            }
+        }
+    }
+
+    #[test]
+    fn modifier_display() {
+        check(
+            "Poison :: mod(d: Roll<Pending>) { result = result - (roll d) }\nroll d20:Poison(d4)",
+            "1d20=16:Poison(1d4)=15",
+        );
+    }
+
+    #[test]
+    fn annotated_roll() {
+        check(
+            "roll (1d20:\"Fire\" -4:\"Debuff\")",
+            "1d20:\"Fire\"=16 -4:\"Debuff\" ->12",
+        );
+        check("x := 2d4:\"Fire attack\"\nx", "2d4:\"Fire attack\"");
+        check("crit 2d6:\"Sneak\"", "4d6:\"Sneak\"");
+        check_error(
+            "x := roll d20\nx:\"late\"",
+            "cannot annotate a value that has already been rolled; annotate it before rolling",
+        );
+        check_error("[1]:\"list\"", "cannot annotate [number]");
+    }
+
+    #[test]
+    fn attack_shorthand() {
+        check("roll atk+4", "1d20=16 +4 ->20");
+    }
+
+    #[test]
+    fn advantage_modifier() {
+        check(
+            "advantage :: mod() {\n    second_roll := roll d20\n    result = max(result, second_roll)\n}\nroll d20:advantage",
+            "1d20=16:advantage=16",
+        );
+    }
+
+    #[test]
+    fn modifier_on_constant() {
+        check(
+            "Double :: mod() { result = result * 2 }\nroll 3:Double",
+            "3:Double=6",
+        );
+    }
+
+    #[test]
+    fn modifier_cannot_apply_itself() {
+        check_error(
+            "Again :: mod() { result = roll result:Again }",
+            "modifier `Again` cannot apply itself",
+        );
+    }
+
+    #[test]
+    fn modifier_cannot_recurse_indirectly() {
+        check_error(
+            "A :: mod() { x := roll 1d6:B }\nB :: mod() { x := roll 1d6:A }\nroll 1d6:A",
+            "in modifier `A`: in modifier `B`: modifier `A` cannot be applied inside itself",
+        );
+    }
+
+    #[test]
+    fn modifier_cannot_return_value() {
+        check_error(
+            "M :: mod() { return 1 }",
+            "modifiers hand back `result`; assign to it instead of returning a value",
+        );
+    }
+
+    #[test]
+    fn modifier_on_rolled_value() {
+        check_error(
+            "M :: mod() { result = 1 }\n(roll 1d6):M",
+            "cannot apply a modifier to a value that has already been rolled; apply it before rolling",
+        );
+    }
+
+    #[test]
+    fn unknown_modifier() {
+        check_error("roll 1d6:Nope", "unknown modifier `Nope`");
+    }
+
+    #[test]
+    fn function_is_not_modifier() {
+        check_error("roll 1d6:max", "`max` is a function, not a modifier");
+    }
+
+    #[test]
+    fn modifier_argument_types() {
+        check_error(
+            "Poison :: mod(d: Roll<Pending>) { result = result - (roll d) }\nroll d20:Poison(\"x\")",
+            "`Poison` expects (dice), but was given (str)",
+        );
+    }
+
+    #[test]
+    fn modifier_must_leave_number() {
+        check_error(
+            "M :: mod() { result = 1d6 }\nroll 1d6:M",
+            "in modifier `M`: `M` should return number, but returned dice",
+        );
+    }
+
+    #[test]
+    fn comp_field_access() {
+        check(
+            "Dragon :: Comp(fire_breath: 4d8+4, stomp: 3d6+2)\nDragon.fire_breath",
+            "4d8+4",
+        );
+    }
+
+    #[test]
+    fn roll_comp_field() {
+        check(
+            "Dragon :: Comp(fire_breath: 4d8+4, stomp: 3d6+2)\nroll Dragon.stomp",
+            "3d6=[2, 3, 5] +2 ->12",
+        );
+    }
+
+    #[test]
+    fn roll_whole_comp() {
+        check(
+            "Dragon :: Comp(fire_breath: 4d8+4, stomp: 3d6+2, name: \"Smaug\")\nroll Dragon",
+            "Dragon\n    .fire_breath  4d8=[4, 5, 3, 4] +4  ->20\n    .stomp        3d6=[4, 6, 5] +2     ->17\n    .name         \"Smaug\"",
+        );
+    }
+
+    #[test]
+    fn roll_nested_comp() {
+        check(
+            "Abilities :: comp { STR: 4d6, DEX: 4d6 }\nHero :: Comp(abilities: Abilities, hp: 2d10)\nroll Hero",
+            "Hero\n    .abilities  Abilities\n        .STR  4d6=[2, 3, 5, 4]  ->14\n        .DEX  4d6=[4, 6, 5, 6]  ->21\n    .hp         2d10=[4, 6]  ->10",
+        );
+    }
+
+    #[test]
+    fn rolled_comp_keeps_rolled_fields() {
+        check(
+            "Orc :: Comp(hp: roll 2d8, speed: 30)\nroll Orc",
+            "Orc\n    .hp     2d8=[4, 5]  ->9\n    .speed  30",
+        );
+    }
+
+    #[test]
+    fn unknown_comp_field() {
+        check_error(
+            "Dragon :: Comp(stomp: 3d6+2)\nDragon.tail",
+            "`Dragon` has no field `tail`",
+        );
+    }
+
+    #[test]
+    fn field_access_on_number() {
+        check_error("x := 3\nx.total", "cannot access field `total` on number");
+    }
+
+    #[test]
+    fn duplicate_comp_field() {
+        check_error(
+            "Dragon :: Comp(stomp: 3d6, stomp: 1d6)",
+            "`Dragon` already has a field `stomp`",
+        );
+    }
+
+    #[test]
+    fn roll_dropping_lowest() {
+        check("roll 4d6dl1", "4d6dl1=[~~2~~, 3, 5, 4] ->12");
+    }
+
+    #[test]
+    fn roll_keeping_highest() {
+        check("roll 2d20kh1+3", "2d20kh1=[16, ~~5~~] +3 ->19");
+    }
+
+    #[test]
+    fn cannot_drop_more_dice_than_rolled() {
+        check_error("4d6dl5", "cannot drop 5 of 4 dice");
+    }
+
+    #[test]
+    fn crit_doubles_selection() {
+        check("crit 4d6dl1", "8d6dl2");
+    }
+
+    #[test]
+    fn roll_ability_scores_rollset() {
+        check(
+            "roll dnd['Ability Scores']",
+            "Ability Scores\n    .STR  4d6dl1=[~~2~~, 3, 5, 4]  ->12\n    .DEX  4d6dl1=[~~4~~, 6, 5, 6]  ->17\n    .CON  4d6dl1=[~~4~~, 6, 4, 6]  ->16\n    .INT  4d6dl1=[4, ~~1~~, 3, 6]  ->13\n    .WIS  4d6dl1=[5, 3, 5, ~~1~~]  ->13\n    .CHA  4d6dl1=[6, ~~3~~, 3, 5]  ->14",
+        );
+    }
+
+    #[test]
+    fn roll_builtin_abilities() {
+        check("roll Abilities.STR", "4d6dl1=[~~2~~, 3, 5, 4] ->12");
+    }
+
+    #[test]
+    fn standard_array() {
+        check("dnd['Standard Array']", "[15, 14, 13, 12, 10, 8]");
+    }
+
+    #[test]
+    fn index_vector() {
+        check("dnd['Standard Array'][1]", "14");
+    }
+
+    #[test]
+    fn unknown_rollset_entry() {
+        check_error("dnd['Point Buy']", "`dnd` has no entry \"Point Buy\"");
+    }
+
+    #[test]
+    fn point_buy_standard_array() {
+        check("point_buy dnd['Standard Array']", "27");
+    }
+
+    #[test]
+    fn point_buy_comp() {
+        check(
+            "Hero :: Comp(STR: 15, DEX: 14, CON: 13, INT: 8, WIS: 12, CHA: 10)\npoint_buy Hero",
+            "27",
+        );
+        check_error(
+            "Hero :: Comp(STR: 15, DEX: 15, CON: 14, INT: 8, WIS: 12, CHA: 10)\npoint_buy Hero",
+            "these scores cost 31 points, but point buy only has 27",
+        );
+    }
+
+    #[test]
+    fn point_buy_needs_numbers() {
+        check_error(
+            "Hero :: Comp(STR: 1d6, DEX: 14, CON: 14, INT: 8, WIS: 12, CHA: 10)\npoint_buy Hero",
+            "STR should be a number, not dice",
+        );
+    }
+
+    fn check_output(input: &str, expected: &str) {
+        let mut env = Env::with_seed(1234);
+        run(input, &mut env).unwrap();
+        assert_eq!(env.take_output(), expected);
+    }
+
+    #[test]
+    fn array_literal() {
+        check("['a', 'b']", r#"["a", "b"]"#);
+    }
+
+    #[test]
+    fn array_of_mixed_types() {
+        check_error(
+            "[1, 'two']",
+            "arrays can only hold one type, but this one has number and str",
+        );
+    }
+
+    #[test]
+    fn roll_initiative() {
+        check_output(
+            "init(['Utahkh', 'Mythelia', 'Boro-Boro'])",
+            "Initiative\n----------\nUtahkh      16\nBoro-Boro   7\nMythelia    5\n",
+        );
+    }
+
+    #[test]
+    fn initiative_with_modifiers() {
+        check_output(
+            "init(['Utahkh', 'Mythelia'], [-1, 3])",
+            "Initiative\n----------\nUtahkh     15\nMythelia   8\n",
+        );
+    }
+
+    #[test]
+    fn initiative_from_comp() {
+        check_output(
+            "Party :: Comp(Utahkh: -1, Mythelia: d20+1)\ninit(Party)",
+            "Initiative\n----------\nUtahkh     15\nMythelia   6\n",
+        );
+    }
+
+    #[test]
+    fn next_turn() {
+        check_output(
+            "init(['Utahkh', 'Mythelia'])\nnext\nnext\nnext()",
+            "Initiative\n----------\nUtahkh     16\nMythelia   5\nRound 1: Utahkh (16)\nRound 1: Mythelia (5)\nRound 2: Utahkh (16)\n",
+        );
+    }
+
+    #[test]
+    fn next_without_initiative() {
+        check_error(
+            "next",
+            "there is no initiative order yet; start one with `init`",
+        );
+    }
+
+    #[test]
+    fn initiative_modifier_count_must_match() {
+        check_error(
+            "init(['Utahkh', 'Mythelia'], [1])",
+            "`init` was given 2 names but 1 modifiers",
+        );
+    }
+
+    #[test]
+    fn bare_name_of_builtin_with_arguments_is_unbound() {
+        check_error("abs", "unbound variable `abs`");
+    }
+
+    #[test]
+    fn group_members_are_scoped() {
+        check(
+            "groupcheck := (uthal := d20+2, mycin := d20+4)\ngroupcheck.mycin",
+            "1d20+4",
+        );
+        check_error(
+            "groupcheck := (uthal := d20+2, mycin := d20+4)\nmycin",
+            "unbound variable `mycin`",
+        );
+    }
+
+    #[test]
+    fn roll_group() {
+        check(
+            "groupcheck := (\n    uthal := d20+2,\n    mycin := d20+4,\n)\nroll groupcheck",
+            "groupcheck\n    .uthal  1d20=16 +2  ->18\n    .mycin  1d20=5 +4   ->9",
+        );
+    }
+
+    #[test]
+    fn roll_unbound_group() {
+        check(
+            "roll (a := d20, b := d20)",
+            "(group)\n    .a  1d20=16  ->16\n    .b  1d20=5   ->5",
+        );
+    }
+
+    #[test]
+    fn duplicate_group_member() {
+        check_error(
+            "(a := d20, a := d4)",
+            "the group already has a member named `a`",
+        );
+    }
+
+    fn group_check(dc: i64) -> (Val, String) {
+        let mut env = Env::with_seed(1234);
+        let input = format!(
+            "party := (uthal := d20+2, mycin := d20+4, gold := d20+3)\ngroup_check(party, {})",
+            dc
+        );
+        let val = run(&input, &mut env).unwrap();
+        (val, env.take_output())
+    }
+
+    #[test]
+    fn group_check_passes_with_half() {
+        assert_eq!(
+            group_check(10),
+            (
+                Val::Bool(true),
+                "party\n    .uthal  1d20=16 +2  ->18\n    .mycin  1d20=5 +4   ->9\n    .gold   1d20=7 +3   ->10\n2 of 3 succeeded against DC 10\n".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn group_check_fails_with_less_than_half() {
+        let (val, output) = group_check(12);
+        assert_eq!(val, Val::Bool(false));
+        assert!(output.ends_with("1 of 3 succeeded against DC 12\n"));
+    }
+
+    #[test]
+    fn group_check_of_strings() {
+        check_error(
+            "group_check((a := 'x'), 10)",
+            "a can't take part in a group check: it's str, not a roll",
+        );
+    }
+
+    #[test]
+    fn pass_and_fail() {
+        check("pass", "pass");
+        check("fail", "fail");
+    }
+
+    fn bindings(input: &str) -> Vec<(u64, Option<String>)> {
+        let mut env = Env::with_seed(1234);
+        run(input, &mut env).unwrap();
+        env.take_rolls()
+            .into_iter()
+            .map(|record| (record.seq, record.binding))
+            .collect()
+    }
+
+    #[test]
+    fn rolls_are_recorded_in_order() {
+        assert_eq!(
+            bindings("roll 1d20\nattack := roll 1d20 + 5\nroll 3"),
+            [(0, None), (1, Some("attack".to_string()))]
+        );
+    }
+
+    #[test]
+    fn composite_rolls_are_recorded_by_field() {
+        assert_eq!(
+            bindings(
+                "Goblin :: comp(attack: 1d20 + 4, damage: 1d6 + 2, hp: 7)\nhit := roll Goblin"
+            ),
+            [
+                (0, Some("attack".to_string())),
+                (1, Some("damage".to_string()))
+            ]
+        );
+    }
+
+    #[test]
+    fn rolls_are_recorded_with_their_annotations() {
+        let mut env = Env::with_seed(1234);
+        run(
+            "hit := roll (1d20:\"Fire\" -4:\"Debuff\")\nroll 1d6",
+            &mut env,
+        )
+        .unwrap();
+
+        let records: Vec<_> = env
+            .take_rolls()
+            .into_iter()
+            .map(|record| (record.binding, record.annotation))
+            .collect();
+        assert_eq!(
+            records,
+            [
+                (Some("hit".to_string()), Some("Fire, Debuff".to_string())),
+                (None, None)
+            ]
+        );
+    }
+
+    #[test]
+    fn record_keeps_every_face() {
+        let mut env = Env::with_seed(1234);
+        run("roll 4d6dl1", &mut env).unwrap();
+
+        let record = env.take_rolls().remove(0);
+        let dice = record.roll.outcome.dice();
+        assert_eq!(dice.len(), 1);
+        assert_eq!(dice[0].1.len(), 4);
+        assert_eq!(record.seed, 1234);
+    }
+
+    // Trees the parser recovered from have pieces missing, which are errors rather than panics
+    #[test]
+    fn eval_anything() {
+        let pieces = [
+            "x", ":=", "=", "::", "(", ")", ",", "{", "}", ":", "fn", "comp", "1", "d6", ".", "\n",
+        ];
+        let mut inputs = vec![String::new()];
+        for _ in 0..3 {
+            inputs = inputs
+                .iter()
+                .flat_map(|input| pieces.iter().map(move |piece| format!("{input}{piece}")))
+                .collect();
+        }
+
+        for input in inputs {
+            let root = Root::cast(parser::parse(&input).syntax()).unwrap();
+            let mut env = Env::with_seed(0);
+            for stmt in root.stmts() {
+                let _ = eval_stmt(&stmt, &mut env);
+            }
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use ast::Root;
+use syntax::{SyntaxKind, SyntaxToken};
+
+const INDENT: &str = "    ";
+
+// Rewrites the whitespace of a script into the usual style: one space around most operators and
+// after commas, four spaces of indentation per open bracket and at most one blank line in a row.
+// Everything else is kept as it is, comments included. Line breaks stay where they are too, since
+// they can change what a script means, e.g. `repr\n1d20` isn't a call.
+//
+// Scripts with parse errors should be fixed before they're formatted; their broken parts are kept
+// but may not come out pretty.
+pub fn format(root: &Root) -> String {
+    let mut out = String::new();
+    let mut prev: Option<SyntaxToken> = None;
+    let mut newlines = 0;
+    let mut line = 0;
+    // The line each open bracket is on
+    let mut open: Vec<usize> = Vec::new();
+
+    let tokens = root
+        .syntax()
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token());
+
+    for token in tokens {
+        match token.kind() {
+            SyntaxKind::Whitespace => continue,
+            SyntaxKind::Newline => {
+                newlines += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        let closes = matches!(
+            token.kind(),
+            SyntaxKind::RParen | SyntaxKind::RBracket | SyntaxKind::RBrace
+        );
+
+        match &prev {
+            Some(_) if newlines > 0 => {
+                out.push_str(if newlines > 1 { "\n\n" } else { "\n" });
+                line += 1;
+
+                if closes {
+                    open.pop();
+                }
+                out.push_str(&INDENT.repeat(indent_level(&open)));
+            }
+            Some(prev) => {
+                if closes {
+                    open.pop();
+                }
+                if space_between(prev, &token) {
+                    out.push(' ');
+                }
+            }
+            None => {}
+        }
+
+        out.push_str(token.text());
+        line += token.text().matches('\n').count();
+
+        if matches!(
+            token.kind(),
+            SyntaxKind::LParen | SyntaxKind::LBracket | SyntaxKind::LBrace
+        ) {
+            open.push(line);
+        }
+
+        newlines = 0;
+        prev = Some(token);
+    }
+
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+// Several brackets opened on the same line only indent the lines after it once, so that
+// `f([` doesn't push its contents two levels in
+fn indent_level(open: &[usize]) -> usize {
+    let mut lines = open.to_vec();
+    lines.dedup();
+    lines.len()
+}
+
+fn space_between(prev: &SyntaxToken, next: &SyntaxToken) -> bool {
+    use SyntaxKind::*;
+
+    let parent = |token: &SyntaxToken| token.parent().map(|node| node.kind());
+
+    match (prev.kind(), next.kind()) {
+        (_, Comment) => true,
+        (_, Comma | Semicolon | RParen | RBracket | Dot | Colon | Lt | Gt) => false,
+        (LParen | LBracket | Dot | Lt, _) => false,
+        (LBrace, RBrace) => false,
+        // `d20:adv` and `d20:"Stealth"`, but `a: number` and `hp: 2d6`
+        (Colon, _) => !matches!(parent(prev), Some(ModExpr | AnnotatedExpr)),
+        (Minus, _) if parent(prev) == Some(PrefixExpr) => false,
+        // `max(1, 2)`, `fn(a: number)` and `comp(hp: 15)`, but `roll (1d20+4)`
+        (Ident | FnKw | ModKw | CompKw, LParen) => {
+            !matches!(parent(next), Some(ArgList | ParamList | CompDef))
+        }
+        (_, LBracket) => parent(next) != Some(IndexExpr),
+        (Plus | Minus, _) if is_dice_modifier(prev) => false,
+        (_, Plus | Minus) if is_dice_modifier(next) => false,
+        _ => true,
+    }
+}
+
+// The spec only allows spaces between dice and their modifier inside parentheses, so `1d20+4`
+// is kept together wherever it is
+fn is_dice_modifier(op: &SyntaxToken) -> bool {
+    let Some(infix) = op
+        .parent()
+        .filter(|node| node.kind() == SyntaxKind::InfixExpr)
+    else {
+        return false;
+    };
+
+    let mut operands = infix.children();
+    operands
+        .next()
+        .is_some_and(|lhs| lhs.kind() == SyntaxKind::DiceExpr)
+        && operands
+            .next()
+            .is_some_and(|rhs| rhs.kind() == SyntaxKind::Literal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(input: &str, expected: &str) {
+        let parse = parser::parse(input);
+        assert_eq!(parse.errors(), &[]);
+
+        let formatted = format(&Root::cast(parse.syntax()).unwrap());
+        assert_eq!(formatted, expected);
+
+        // Formatting twice changes nothing more
+        let reparsed = parser::parse(&formatted);
+        assert_eq!(format(&Root::cast(reparsed.syntax()).unwrap()), expected);
+    }
+
+    #[test]
+    fn space_around_operators() {
+        check("x:=a*2-b/ 3", "x := a * 2 - b / 3\n");
+    }
+
+    #[test]
+    fn dice_modifier_stays_together() {
+        check("roll 1d20 + 4", "roll 1d20+4\n");
+        check("roll (1d20 +4)", "roll (1d20+4)\n");
+        check("2d6+1d4", "2d6 + 1d4\n");
+    }
+
+    #[test]
+    fn let_and_assignment() {
+        check("let hp=2d8\nhp =  hp+1", "let hp = 2d8\nhp = hp + 1\n");
+    }
+
+    #[test]
+    fn prefix_and_modifiers() {
+        check("-  d20 : adv", "-d20:adv\n");
+        check("roll 1d20 : \"Stealth\"", "roll 1d20:\"Stealth\"\n");
+        check("d20:Poison( d4 )", "d20:Poison(d4)\n");
+    }
+
+    #[test]
+    fn vectors_and_calls() {
+        check(
+            "xs := [ 1,2 ,3 ]\nmax( xs [1],4)",
+            "xs := [1, 2, 3]\nmax(xs[1], 4)\n",
+        );
+        check("repr   1d20", "repr 1d20\n");
+    }
+
+    #[test]
+    fn function_definition() {
+        check(
+            "add::fn( a:number,b : number )->( c:number ){c=a+b;return c}",
+            "add :: fn(a: number, b: number) -> (c: number) { c = a + b; return c }\n",
+        );
+    }
+
+    #[test]
+    fn indent_blocks() {
+        check(
+            "best :: fn(xs: [number]) -> number {\nx := max(xs)\n        x\n  }",
+            "best :: fn(xs: [number]) -> number {\n    x := max(xs)\n    x\n}\n",
+        );
+    }
+
+    #[test]
+    fn indent_comp_fields() {
+        check(
+            "Orc :: comp(\nhit: d20+5,\n  damage: 1d12  +3,\n)",
+            "Orc :: comp(\n    hit: d20+5,\n    damage: 1d12+3,\n)\n",
+        );
+    }
+
+    #[test]
+    fn keep_comments() {
+        check("(1\n  + 1 # Add one\n)", "(1\n    + 1 # Add one\n)\n");
+        check(
+            "# Fighter\n/* hit\n   dice */ x := 1d10",
+            "# Fighter\n/* hit\n   dice */ x := 1d10\n",
+        );
+    }
+
+    #[test]
+    fn collapse_blank_lines() {
+        check("\n\nx := 1\n\n\n\ny := 2\n\n", "x := 1\n\ny := 2\n");
+    }
+
+    #[test]
+    fn types() {
+        check(
+            "hit :: mod( d: Roll < Pending > ) { result }",
+            "hit :: mod(d: Roll<Pending>) { result }\n",
+        );
+    }
+
+    #[test]
+    fn empty_input() {
+        check("", "");
+    }
+}
@@ -0,0 +1,680 @@
+use crate::rng::Rng;
+use crate::val::Val;
+use std::fmt;
+
+// Upper bound on the number of dice in a single roll, so that `999999d6` doesn't hang the REPL
+pub const MAX_DICE: u32 = 1000;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Dice {
+    pub count: u32,
+    pub sides: u32,
+    pub select: Option<Select>,
+}
+
+impl Dice {
+    pub fn new(count: u32, sides: u32) -> Self {
+        Self {
+            count,
+            sides,
+            select: None,
+        }
+    }
+
+    pub fn with_select(self, select: Select) -> Self {
+        Self {
+            select: Some(select),
+            ..self
+        }
+    }
+
+    // How many of the dice count towards the total
+    pub fn kept(&self) -> u32 {
+        match self.select {
+            None => self.count,
+            Some(Select::KeepHighest(n) | Select::KeepLowest(n)) => n.min(self.count),
+            Some(Select::DropHighest(n) | Select::DropLowest(n)) => self.count.saturating_sub(n),
+        }
+    }
+
+    pub fn min(&self) -> i64 {
+        i64::from(self.kept())
+    }
+
+    pub fn max(&self) -> i64 {
+        i64::from(self.kept()) * i64::from(self.sides)
+    }
+
+    // Which of the rolled faces count towards the total. Of equal faces, the ones rolled first
+    // are dropped first.
+    pub fn kept_faces(&self, faces: &[u32]) -> Vec<bool> {
+        let Some(select) = self.select else {
+            return vec![true; faces.len()];
+        };
+
+        let kept = usize::try_from(self.kept()).unwrap();
+        let mut order: Vec<_> = (0..faces.len()).collect();
+        order.sort_by_key(|&i| faces[i]);
+
+        let kept_indices = match select {
+            Select::KeepHighest(_) | Select::DropLowest(_) => &order[faces.len() - kept..],
+            Select::KeepLowest(_) | Select::DropHighest(_) => &order[..kept],
+        };
+
+        let mut mask = vec![false; faces.len()];
+        for &i in kept_indices {
+            mask[i] = true;
+        }
+        mask
+    }
+}
+
+impl fmt::Display for Dice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}d{}", self.count, self.sides)?;
+
+        match self.select {
+            Some(select) => write!(f, "{}", select),
+            None => Ok(()),
+        }
+    }
+}
+
+// Keeping or dropping the highest or lowest few dice, e.g. the `dl1` in `4d6dl1`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Select {
+    KeepHighest(u32),
+    KeepLowest(u32),
+    DropHighest(u32),
+    DropLowest(u32),
+}
+
+impl fmt::Display for Select {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::KeepHighest(n) => write!(f, "kh{}", n),
+            Self::KeepLowest(n) => write!(f, "kl{}", n),
+            Self::DropHighest(n) => write!(f, "dh{}", n),
+            Self::DropLowest(n) => write!(f, "dl{}", n),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InfixOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl InfixOp {
+    pub fn apply(self, lhs: i64, rhs: i64) -> Result<i64, ArithError> {
+        match self {
+            Self::Add => lhs.checked_add(rhs).ok_or(ArithError::Overflow),
+            Self::Sub => lhs.checked_sub(rhs).ok_or(ArithError::Overflow),
+            Self::Mul => lhs.checked_mul(rhs).ok_or(ArithError::Overflow),
+            Self::Div => floor_div(lhs, rhs),
+        }
+    }
+
+    fn precedence(self) -> u8 {
+        match self {
+            Self::Add | Self::Sub => 1,
+            Self::Mul | Self::Div => 2,
+        }
+    }
+}
+
+impl fmt::Display for InfixOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+        })
+    }
+}
+
+// Division always rounds down in D&D
+fn floor_div(lhs: i64, rhs: i64) -> Result<i64, ArithError> {
+    if rhs == 0 {
+        return Err(ArithError::DivisionByZero);
+    }
+
+    let quotient = lhs.checked_div(rhs).ok_or(ArithError::Overflow)?;
+
+    if (lhs % rhs != 0) && ((lhs < 0) != (rhs < 0)) {
+        Ok(quotient - 1)
+    } else {
+        Ok(quotient)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ArithError {
+    DivisionByZero,
+    Overflow,
+    TooManyDice,
+}
+
+impl fmt::Display for ArithError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DivisionByZero => f.write_str("division by zero"),
+            Self::Overflow => f.write_str("number is too large"),
+            Self::TooManyDice => write!(f, "cannot roll more than {} dice at once", MAX_DICE),
+        }
+    }
+}
+
+// A user-defined modifier bound to part of a formula with `:`, e.g. the `Poison(d4)` in
+// `d20:Poison(d4)`. The arguments have already been evaluated, but not rolled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Modifier {
+    pub name: String,
+    pub args: Vec<Val>,
+}
+
+impl fmt::Display for Modifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+
+        if !self.args.is_empty() {
+            let args: Vec<_> = self.args.iter().map(ToString::to_string).collect();
+            write!(f, "({})", args.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+// Rolling needs randomness, and modifiers need something that can run their body with the total
+// of what they're bound to, which is `Env`. A bare `Rng` can roll anything without modifiers.
+pub trait Roller {
+    fn rng(&mut self) -> &mut Rng;
+    fn apply_mod(&mut self, modifier: &Modifier, total: i64) -> Result<i64, String>;
+
+    // Sees every finished roll that had dice in it, labelled with what it was rolled for when
+    // that's known, so that it can be logged
+    fn record(&mut self, _label: Option<&str>, _formula: &Formula, _roll: &Roll) {}
+}
+
+impl Roller for Rng {
+    fn rng(&mut self) -> &mut Rng {
+        self
+    }
+
+    fn apply_mod(&mut self, modifier: &Modifier, _: i64) -> Result<i64, String> {
+        Err(format!(
+            "modifier `{}` can only be rolled in an environment",
+            modifier.name
+        ))
+    }
+}
+
+// A dice expression that hasn't been rolled yet, e.g. `3d8+4`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Formula {
+    Number(i64),
+    Dice(Dice),
+    Infix {
+        op: InfixOp,
+        lhs: Box<Formula>,
+        rhs: Box<Formula>,
+    },
+    Neg(Box<Formula>),
+    Mod {
+        inner: Box<Formula>,
+        modifier: Modifier,
+    },
+    // `2d4:"Fire attack"`, to say what the dice are for
+    Annotated {
+        inner: Box<Formula>,
+        annotation: String,
+    },
+}
+
+impl Formula {
+    pub fn has_dice(&self) -> bool {
+        match self {
+            Self::Number(_) => false,
+            Self::Dice(_) => true,
+            Self::Infix { lhs, rhs, .. } => lhs.has_dice() || rhs.has_dice(),
+            Self::Neg(inner) => inner.has_dice(),
+            Self::Mod { inner, .. } | Self::Annotated { inner, .. } => inner.has_dice(),
+        }
+    }
+
+    // The value of a formula that contains no dice
+    pub fn constant(&self) -> Option<i64> {
+        match self {
+            Self::Number(n) => Some(*n),
+            Self::Dice(_) => None,
+            Self::Infix { op, lhs, rhs } => op.apply(lhs.constant()?, rhs.constant()?).ok(),
+            Self::Neg(inner) => inner.constant()?.checked_neg(),
+            // A modifier can do anything with the value, even if it's constant
+            Self::Mod { .. } => None,
+            Self::Annotated { inner, .. } => inner.constant(),
+        }
+    }
+
+    pub fn roll(&self, roller: &mut impl Roller) -> Result<Roll, String> {
+        if self.dice_count() > u64::from(MAX_DICE) {
+            return Err(ArithError::TooManyDice.to_string());
+        }
+
+        let outcome = self.roll_outcome(roller)?;
+        let total = outcome.total().map_err(|e| e.to_string())?;
+
+        Ok(Roll { outcome, total })
+    }
+
+    fn dice_count(&self) -> u64 {
+        match self {
+            Self::Number(_) => 0,
+            Self::Dice(dice) => u64::from(dice.count),
+            Self::Infix { lhs, rhs, .. } => lhs.dice_count() + rhs.dice_count(),
+            Self::Neg(inner) => inner.dice_count(),
+            Self::Mod { inner, .. } | Self::Annotated { inner, .. } => inner.dice_count(),
+        }
+    }
+
+    fn roll_outcome(&self, roller: &mut impl Roller) -> Result<Outcome, String> {
+        let outcome = match self {
+            Self::Number(n) => Outcome::Number(*n),
+            Self::Dice(dice) => {
+                let rng = roller.rng();
+                Outcome::Dice {
+                    dice: *dice,
+                    faces: (0..dice.count).map(|_| rng.roll_die(dice.sides)).collect(),
+                }
+            }
+            Self::Infix { op, lhs, rhs } => Outcome::Infix {
+                op: *op,
+                lhs: Box::new(lhs.roll_outcome(roller)?),
+                rhs: Box::new(rhs.roll_outcome(roller)?),
+            },
+            Self::Neg(inner) => Outcome::Neg(Box::new(inner.roll_outcome(roller)?)),
+            Self::Mod { inner, modifier } => {
+                let inner = inner.roll_outcome(roller)?;
+                let total = inner.total().map_err(|e| e.to_string())?;
+                let total = roller.apply_mod(modifier, total)?;
+
+                Outcome::Mod {
+                    inner: Box::new(inner),
+                    modifier: modifier.clone(),
+                    total,
+                }
+            }
+            Self::Annotated { inner, annotation } => Outcome::Annotated {
+                inner: Box::new(inner.roll_outcome(roller)?),
+                annotation: annotation.clone(),
+            },
+        };
+
+        Ok(outcome)
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Infix { op, .. } => op.precedence(),
+            Self::Neg(_) => 3,
+            Self::Number(_) | Self::Dice(_) | Self::Mod { .. } | Self::Annotated { .. } => 4,
+        }
+    }
+}
+
+impl fmt::Display for Formula {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(n) => write!(f, "{}", n),
+            Self::Dice(dice) => write!(f, "{}", dice),
+            Self::Infix { op, lhs, rhs } => {
+                let (lhs_parens, rhs_parens) =
+                    needs_parens(op.precedence(), lhs.precedence(), rhs.precedence());
+                write_operand(f, lhs, lhs_parens)?;
+                write!(f, "{}", op)?;
+                write_operand(f, rhs, rhs_parens)
+            }
+            Self::Neg(inner) => {
+                f.write_str("-")?;
+                write_operand(f, inner, inner.precedence() < 3)
+            }
+            Self::Mod { inner, modifier } => {
+                write_operand(f, inner, inner.precedence() < 4)?;
+                write!(f, ":{}", modifier)
+            }
+            Self::Annotated { inner, annotation } => {
+                write_operand(f, inner, inner.precedence() < 4)?;
+                write!(f, ":{:?}", annotation)
+            }
+        }
+    }
+}
+
+// The result of rolling a `Formula`, keeping every face that came up
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Number(i64),
+    Dice {
+        dice: Dice,
+        faces: Vec<u32>,
+    },
+    Infix {
+        op: InfixOp,
+        lhs: Box<Outcome>,
+        rhs: Box<Outcome>,
+    },
+    Neg(Box<Outcome>),
+    // What the modifier made of the inner outcome's total
+    Mod {
+        inner: Box<Outcome>,
+        modifier: Modifier,
+        total: i64,
+    },
+    Annotated {
+        inner: Box<Outcome>,
+        annotation: String,
+    },
+}
+
+impl Outcome {
+    // The annotations in the order they appear, like `Fire` and `Debuff` in
+    // `1d20:"Fire" -4:"Debuff"`
+    pub fn annotations(&self) -> Vec<&str> {
+        match self {
+            Self::Number(_) | Self::Dice { .. } => Vec::new(),
+            Self::Infix { lhs, rhs, .. } => {
+                let mut annotations = lhs.annotations();
+                annotations.extend(rhs.annotations());
+                annotations
+            }
+            Self::Neg(inner) | Self::Mod { inner, .. } => inner.annotations(),
+            Self::Annotated { inner, annotation } => {
+                let mut annotations = inner.annotations();
+                annotations.push(annotation);
+                annotations
+            }
+        }
+    }
+
+    // Whether writing the outcome already shows its total, as a single die or a modifier does
+    fn shows_total(&self) -> bool {
+        match self {
+            Self::Number(_) | Self::Mod { .. } => true,
+            Self::Dice { faces, .. } => faces.len() == 1,
+            Self::Annotated { inner, .. } => inner.shows_total(),
+            Self::Infix { .. } | Self::Neg(_) => false,
+        }
+    }
+
+    // Every face that counts towards the total, in the order the dice appear
+    pub fn faces(&self) -> Vec<u32> {
+        match self {
+            Self::Number(_) => Vec::new(),
+            Self::Dice { dice, faces } => faces
+                .iter()
+                .zip(dice.kept_faces(faces))
+                .filter(|(_, kept)| *kept)
+                .map(|(&face, _)| face)
+                .collect(),
+            Self::Infix { lhs, rhs, .. } => {
+                let mut faces = lhs.faces();
+                faces.extend(rhs.faces());
+                faces
+            }
+            Self::Neg(inner) => inner.faces(),
+            Self::Mod { inner, .. } | Self::Annotated { inner, .. } => inner.faces(),
+        }
+    }
+
+    // Every group of dice with all of its faces, dropped ones included, in the order they appear
+    pub fn dice(&self) -> Vec<(Dice, &[u32])> {
+        match self {
+            Self::Number(_) => Vec::new(),
+            Self::Dice { dice, faces } => vec![(*dice, faces.as_slice())],
+            Self::Infix { lhs, rhs, .. } => {
+                let mut dice = lhs.dice();
+                dice.extend(rhs.dice());
+                dice
+            }
+            Self::Neg(inner) => inner.dice(),
+            Self::Mod { inner, .. } | Self::Annotated { inner, .. } => inner.dice(),
+        }
+    }
+
+    pub fn total(&self) -> Result<i64, ArithError> {
+        match self {
+            Self::Number(n) => Ok(*n),
+            Self::Dice { .. } => Ok(self.faces().into_iter().map(i64::from).sum()),
+            Self::Infix { op, lhs, rhs } => op.apply(lhs.total()?, rhs.total()?),
+            Self::Neg(inner) => inner.total()?.checked_neg().ok_or(ArithError::Overflow),
+            Self::Mod { total, .. } => Ok(*total),
+            Self::Annotated { inner, .. } => inner.total(),
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Infix { op, .. } => op.precedence(),
+            Self::Neg(_) => 3,
+            Self::Number(_) | Self::Dice { .. } | Self::Mod { .. } | Self::Annotated { .. } => 4,
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(n) => write!(f, "{}", n),
+            Self::Dice { dice, faces } => {
+                write!(f, "{}=", dice)?;
+                write_faces(f, dice, faces)
+            }
+            Self::Infix { op, lhs, rhs } => {
+                let (lhs_parens, rhs_parens) =
+                    needs_parens(op.precedence(), lhs.precedence(), rhs.precedence());
+                write_operand(f, lhs, lhs_parens)?;
+                // Modifiers are spaced out from the dice, like `1d20=6 +4`
+                match op {
+                    InfixOp::Add | InfixOp::Sub => write!(f, " {}", op)?,
+                    InfixOp::Mul | InfixOp::Div => write!(f, "{}", op)?,
+                }
+                write_operand(f, rhs, rhs_parens)
+            }
+            Self::Neg(inner) => {
+                f.write_str("-")?;
+                write_operand(f, inner, inner.precedence() < 3)
+            }
+            // `1d20=6:adv=17`
+            Self::Mod {
+                inner,
+                modifier,
+                total,
+            } => {
+                write_operand(f, inner, inner.precedence() < 4)?;
+                write!(f, ":{}={}", modifier, total)
+            }
+            // The annotation goes straight after the dice, like `1d20:"Fire"=6`
+            Self::Annotated { inner, annotation } => match inner.as_ref() {
+                Self::Dice { dice, faces } => {
+                    write!(f, "{}:{:?}=", dice, annotation)?;
+                    write_faces(f, dice, faces)
+                }
+                inner => {
+                    write_operand(f, inner, inner.precedence() < 4)?;
+                    write!(f, ":{:?}", annotation)
+                }
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Roll {
+    pub outcome: Outcome,
+    pub total: i64,
+}
+
+impl fmt::Display for Roll {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.outcome)?;
+
+        if self.outcome.shows_total() {
+            Ok(())
+        } else {
+            write!(f, " ->{}", self.total)
+        }
+    }
+}
+
+// Operators are left-associative, so the right operand also needs parentheses when it binds
+// equally tightly, e.g. `1-(2+3)`
+fn needs_parens(op: u8, lhs: u8, rhs: u8) -> (bool, bool) {
+    (lhs < op, rhs <= op)
+}
+
+fn write_faces(f: &mut fmt::Formatter<'_>, dice: &Dice, faces: &[u32]) -> fmt::Result {
+    if let [face] = faces {
+        write!(f, "{}", face)
+    } else {
+        // Dropped dice are struck through, like `4d6dl1=[5, 3, ~~1~~, 6]`
+        let faces: Vec<_> = faces
+            .iter()
+            .zip(dice.kept_faces(faces))
+            .map(|(face, kept)| {
+                if kept {
+                    face.to_string()
+                } else {
+                    format!("~~{}~~", face)
+                }
+            })
+            .collect();
+        write!(f, "[{}]", faces.join(", "))
+    }
+}
+
+fn write_operand(
+    f: &mut fmt::Formatter<'_>,
+    operand: &impl fmt::Display,
+    parens: bool,
+) -> fmt::Result {
+    if parens {
+        write!(f, "({})", operand)
+    } else {
+        write!(f, "{}", operand)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dice(count: u32, sides: u32) -> Box<Formula> {
+        Box::new(Formula::Dice(Dice::new(count, sides)))
+    }
+
+    fn number(n: i64) -> Box<Formula> {
+        Box::new(Formula::Number(n))
+    }
+
+    #[test]
+    fn format_dice_with_modifier() {
+        let formula = Formula::Infix {
+            op: InfixOp::Add,
+            lhs: dice(3, 8),
+            rhs: number(4),
+        };
+        assert_eq!(formula.to_string(), "3d8+4");
+    }
+
+    #[test]
+    fn format_keeps_needed_parentheses() {
+        let formula = Formula::Infix {
+            op: InfixOp::Mul,
+            lhs: Box::new(Formula::Infix {
+                op: InfixOp::Add,
+                lhs: dice(1, 6),
+                rhs: number(1),
+            }),
+            rhs: number(2),
+        };
+        assert_eq!(formula.to_string(), "(1d6+1)*2");
+    }
+
+    #[test]
+    fn format_right_operand_of_subtraction() {
+        let formula = Formula::Infix {
+            op: InfixOp::Sub,
+            lhs: number(10),
+            rhs: Box::new(Formula::Infix {
+                op: InfixOp::Sub,
+                lhs: dice(1, 4),
+                rhs: number(1),
+            }),
+        };
+        assert_eq!(formula.to_string(), "10-(1d4-1)");
+    }
+
+    #[test]
+    fn division_rounds_down() {
+        assert_eq!(InfixOp::Div.apply(7, 2), Ok(3));
+        assert_eq!(InfixOp::Div.apply(-7, 2), Ok(-4));
+        assert_eq!(InfixOp::Div.apply(1, 0), Err(ArithError::DivisionByZero));
+    }
+
+    #[test]
+    fn roll_stays_within_bounds() {
+        let mut rng = Rng::new(42);
+        let formula = Formula::Infix {
+            op: InfixOp::Add,
+            lhs: dice(3, 8),
+            rhs: number(4),
+        };
+
+        for _ in 0..100 {
+            let roll = formula.roll(&mut rng).unwrap();
+            assert!((7..=28).contains(&roll.total));
+        }
+    }
+
+    #[test]
+    fn format_roll_of_single_die() {
+        let roll = Roll {
+            outcome: Outcome::Dice {
+                dice: Dice::new(1, 20),
+                faces: vec![7],
+            },
+            total: 7,
+        };
+        assert_eq!(roll.to_string(), "1d20=7");
+    }
+
+    #[test]
+    fn format_roll_with_modifier() {
+        let roll = Roll {
+            outcome: Outcome::Infix {
+                op: InfixOp::Add,
+                lhs: Box::new(Outcome::Dice {
+                    dice: Dice::new(3, 8),
+                    faces: vec![8, 3, 6],
+                }),
+                rhs: Box::new(Outcome::Number(4)),
+            },
+            total: 21,
+        };
+        assert_eq!(roll.to_string(), "3d8=[8, 3, 6] +4 ->21");
+    }
+
+    #[test]
+    fn refuse_to_roll_too_many_dice() {
+        let mut rng = Rng::new(0);
+        let formula = Formula::Dice(Dice::new(MAX_DICE + 1, 6));
+        assert_eq!(
+            formula.roll(&mut rng),
+            Err(ArithError::TooManyDice.to_string())
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use crate::eval::EvalError;
+use crate::stdlib::Signature;
+use crate::ty::Ty;
+use ast::{Block, Expr, FnDef, ModDef, TypeExpr};
+use syntax::{SyntaxKind, SyntaxNode};
+
+// Deep enough for any sensible helper, shallow enough to fail long before the stack overflows
+pub(crate) const MAX_CALL_DEPTH: usize = 64;
+
+// A function defined with `name :: fn(...) { ... }`, or a modifier defined with
+// `name :: mod(...) { ... }`. Its types are resolved when it's defined, so a typo in a signature
+// is reported right away rather than on the first call.
+#[derive(Debug)]
+pub(crate) struct Function {
+    pub(crate) name: String,
+    pub(crate) params: Vec<(String, Ty)>,
+    pub(crate) ret: Ret,
+    pub(crate) body: Block,
+}
+
+#[derive(Debug)]
+pub(crate) enum Ret {
+    // No `->`: whatever the body produces
+    Any,
+    // `-> number`
+    Ty(Ty),
+    // `-> (c: number)`: locals that are handed back by a bare `return` or the end of the body
+    Named(Vec<(String, Ty)>),
+}
+
+impl Function {
+    pub(crate) fn from_def(def: &FnDef) -> Result<Self, EvalError> {
+        let name = def
+            .name()
+            .ok_or_else(|| EvalError::new("missing function name", def.syntax()))?
+            .text()
+            .to_string();
+
+        let params = def
+            .params()
+            .map(|param| param_binding(&param, def.syntax()))
+            .collect::<Result<_, _>>()?;
+
+        let ret = match def.ret_type() {
+            None => Ret::Any,
+            Some(ret_type) => match (ret_type.named(), ret_type.ty()) {
+                (Some(named), _) => Ret::Named(
+                    named
+                        .iter()
+                        .map(|param| param_binding(param, def.syntax()))
+                        .collect::<Result<_, _>>()?,
+                ),
+                (None, Some(ty)) => Ret::Ty(resolve_ty(&ty)?),
+                (None, None) => return Err(EvalError::new("missing return type", def.syntax())),
+            },
+        };
+
+        let body = def
+            .body()
+            .ok_or_else(|| EvalError::new(format!("`{}` has no body", name), def.syntax()))?;
+
+        Ok(Self {
+            name,
+            params,
+            ret,
+            body,
+        })
+    }
+
+    // A modifier is a function whose only named return is `result`, which starts out as the total
+    // of whatever the modifier is bound to
+    pub(crate) fn from_mod_def(def: &ModDef) -> Result<Self, EvalError> {
+        let name = def
+            .name()
+            .ok_or_else(|| EvalError::new("missing modifier name", def.syntax()))?
+            .text()
+            .to_string();
+
+        let params: Vec<_> = def
+            .params()
+            .map(|param| param_binding(&param, def.syntax()))
+            .collect::<Result<_, _>>()?;
+
+        if params.iter().any(|(param, _)| param == RESULT) {
+            return Err(EvalError::new(
+                format!("every modifier already has a `{}`", RESULT),
+                def.syntax(),
+            ));
+        }
+
+        let body = def
+            .body()
+            .ok_or_else(|| EvalError::new(format!("`{}` has no body", name), def.syntax()))?;
+
+        for node in body.syntax().descendants() {
+            match node.kind() {
+                SyntaxKind::ReturnStmt
+                    if node.children().any(|child| Expr::cast(child).is_some()) =>
+                {
+                    return Err(EvalError::new(
+                        format!(
+                            "modifiers hand back `{}`; assign to it instead of returning a value",
+                            RESULT
+                        ),
+                        &node,
+                    ))
+                }
+                SyntaxKind::ModExpr if applies(&node, &name) => {
+                    return Err(EvalError::new(
+                        format!("modifier `{}` cannot apply itself", name),
+                        &node,
+                    ))
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            name,
+            params,
+            ret: Ret::Named(vec![(RESULT.to_string(), Ty::Number)]),
+            body,
+        })
+    }
+
+    pub(crate) fn signature(&self) -> Signature {
+        let params = self.params.iter().map(|(_, ty)| ty.clone()).collect();
+        Signature::new(params, self.ret.ty())
+    }
+}
+
+impl Ret {
+    pub(crate) fn ty(&self) -> Ty {
+        match self {
+            Self::Any => Ty::Any,
+            Self::Ty(ty) => ty.clone(),
+            Self::Named(named) => match named.as_slice() {
+                [(_, ty)] => ty.clone(),
+                _ => Ty::vec(Ty::Any),
+            },
+        }
+    }
+}
+
+pub(crate) const RESULT: &str = "result";
+
+fn applies(mod_expr: &SyntaxNode, name: &str) -> bool {
+    Expr::cast(mod_expr.clone()).is_some_and(|expr| match expr {
+        Expr::ModExpr(modded) => modded.name().is_some_and(|token| token.text() == name),
+        _ => false,
+    })
+}
+
+fn param_binding(param: &ast::Param, def: &SyntaxNode) -> Result<(String, Ty), EvalError> {
+    let name = param
+        .name()
+        .ok_or_else(|| EvalError::new("missing parameter name", def))?;
+    let ty = param
+        .ty()
+        .ok_or_else(|| EvalError::new(format!("missing type for `{}`", name.text()), def))?;
+
+    Ok((name.text().to_string(), resolve_ty(&ty)?))
+}
+
+pub(crate) fn resolve_ty(ty: &TypeExpr) -> Result<Ty, EvalError> {
+    if let Some(name) = ty.name() {
+        let arg = ty.inner();
+        let arg_name = arg.as_ref().and_then(TypeExpr::name);
+
+        return match (name.text(), arg_name.as_ref().map(|arg| arg.text())) {
+            ("number" | "num", None) => Ok(Ty::Number),
+            ("dice", None) => Ok(Ty::Dice),
+            ("roll" | "Roll", None) => Ok(Ty::Roll),
+            // Dice that are waiting to be rolled, and dice that already have been
+            ("Roll", Some("Pending")) => Ok(Ty::Dice),
+            ("Roll", Some("Rolled")) => Ok(Ty::Roll),
+            ("str", None) => Ok(Ty::Str),
+            ("bool", None) => Ok(Ty::Bool),
+            ("comp", None) => Ok(Ty::Comp),
+            ("any", None) => Ok(Ty::Any),
+            ("Roll", Some(_)) => Err(EvalError::new(
+                "expected `Roll<Pending>` or `Roll<Rolled>`",
+                ty.syntax(),
+            )),
+            (name, None) => Err(EvalError::new(
+                format!("unknown type `{}`", name),
+                ty.syntax(),
+            )),
+            (name, Some(_)) => Err(EvalError::new(
+                format!("`{}` takes no type argument", name),
+                ty.syntax(),
+            )),
+        };
+    }
+
+    let elem = ty
+        .inner()
+        .ok_or_else(|| EvalError::new("missing element type", ty.syntax()))?;
+    Ok(Ty::vec(resolve_ty(&elem)?))
+}
@@ -0,0 +1,254 @@
+use ast::Root;
+use syntax::{SyntaxKind, SyntaxToken};
+use text_size::TextRange;
+
+// What a token is, as far as colouring it goes
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HighlightClass {
+    Keyword,
+    Dice,
+    Number,
+    String,
+    // The name being defined, e.g. `hp` in `hp := 2d8` or `a` in `fn(a: number)`
+    Definition,
+    // A name used anywhere else
+    Reference,
+    Type,
+    Operator,
+    Punctuation,
+    Comment,
+    // Anything the lexer didn't recognise
+    Error,
+}
+
+impl HighlightClass {
+    // The name of the class in HTML, after `rl-`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Keyword => "keyword",
+            Self::Dice => "dice",
+            Self::Number => "number",
+            Self::String => "string",
+            Self::Definition => "definition",
+            Self::Reference => "reference",
+            Self::Type => "type",
+            Self::Operator => "operator",
+            Self::Punctuation => "punctuation",
+            Self::Comment => "comment",
+            Self::Error => "error",
+        }
+    }
+
+    fn ansi(self) -> Option<&'static str> {
+        match self {
+            Self::Keyword => Some("\x1b[1;35m"),
+            Self::Dice => Some("\x1b[33m"),
+            Self::Number => Some("\x1b[36m"),
+            Self::String => Some("\x1b[32m"),
+            Self::Definition => Some("\x1b[1;34m"),
+            Self::Type => Some("\x1b[34m"),
+            Self::Comment => Some("\x1b[90m"),
+            Self::Error => Some("\x1b[4;31m"),
+            Self::Reference | Self::Operator | Self::Punctuation => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Highlight {
+    pub range: TextRange,
+    pub class: HighlightClass,
+}
+
+// Every token but whitespace, in order, with its class
+pub fn highlight(root: &Root) -> Vec<Highlight> {
+    tokens(root)
+        .filter_map(|token| {
+            Some(Highlight {
+                range: token.text_range(),
+                class: classify(&token)?,
+            })
+        })
+        .collect()
+}
+
+// The script with ANSI colours, for terminals
+pub fn highlight_ansi(root: &Root) -> String {
+    let mut out = String::new();
+
+    for token in tokens(root) {
+        match classify(&token).and_then(HighlightClass::ansi) {
+            Some(colour) => {
+                out.push_str(colour);
+                out.push_str(token.text());
+                out.push_str("\x1b[0m");
+            }
+            None => out.push_str(token.text()),
+        }
+    }
+
+    out
+}
+
+// The script as a `<pre>` block, with every token in a `<span>` of class `rl-<class>`
+pub fn highlight_html(root: &Root) -> String {
+    let mut out = String::from("<pre class=\"rollang\"><code>");
+
+    for token in tokens(root) {
+        let text = escape_html(token.text());
+        match classify(&token) {
+            Some(class) => {
+                out.push_str(&format!(
+                    "<span class=\"rl-{}\">{}</span>",
+                    class.name(),
+                    text
+                ));
+            }
+            None => out.push_str(&text),
+        }
+    }
+
+    out.push_str("</code></pre>");
+    out
+}
+
+// A whole page for the script, with a stylesheet for the classes of `highlight_html`
+pub fn highlight_html_page(root: &Root, title: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>\n{}</style>\n</head>\n<body>\n{}\n</body>\n</html>\n",
+        escape_html(title),
+        STYLESHEET,
+        highlight_html(root)
+    )
+}
+
+pub const STYLESHEET: &str = "\
+pre.rollang { background: #fdf6e3; color: #383a42; padding: 1em; }
+.rl-keyword { color: #a626a4; font-weight: bold; }
+.rl-dice { color: #c18401; }
+.rl-number { color: #0184bc; }
+.rl-string { color: #50a14f; }
+.rl-definition { color: #4078f2; font-weight: bold; }
+.rl-type { color: #4078f2; }
+.rl-comment { color: #a0a1a7; font-style: italic; }
+.rl-error { color: #e45649; text-decoration: underline wavy; }
+";
+
+fn tokens(root: &Root) -> impl Iterator<Item = SyntaxToken> {
+    root.syntax()
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+}
+
+fn classify(token: &SyntaxToken) -> Option<HighlightClass> {
+    use SyntaxKind::*;
+
+    Some(match token.kind() {
+        Whitespace | Newline => return None,
+        kind if kind.is_keyword() => HighlightClass::Keyword,
+        Dice => HighlightClass::Dice,
+        Number => HighlightClass::Number,
+        String => HighlightClass::String,
+        Ident => match token.parent().map(|node| node.kind()) {
+            Some(VariableDef | FnDef | ModDef | CompDef | CompField | Param) => {
+                HighlightClass::Definition
+            }
+            Some(TypeExpr) => HighlightClass::Type,
+            _ => HighlightClass::Reference,
+        },
+        Plus | Minus | Star | Slash | Equals | ColonColon | ColonEquals | Colon | Dot | Arrow => {
+            HighlightClass::Operator
+        }
+        LParen | RParen | LBrace | RBrace | LBracket | RBracket | Comma | Semicolon | Lt | Gt => {
+            HighlightClass::Punctuation
+        }
+        Comment => HighlightClass::Comment,
+        _ => HighlightClass::Error,
+    })
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classes(input: &str) -> Vec<(&str, HighlightClass)> {
+        let root = Root::cast(parser::parse(input).syntax()).unwrap();
+        highlight(&root)
+            .into_iter()
+            .map(|highlight| (&input[highlight.range], highlight.class))
+            .collect()
+    }
+
+    #[test]
+    fn classify_definition_and_reference() {
+        use HighlightClass::*;
+
+        assert_eq!(
+            classes("let atk = 1d20+4\nroll atk # to hit"),
+            [
+                ("let", Keyword),
+                ("atk", Definition),
+                ("=", Operator),
+                ("1d20", Dice),
+                ("+", Operator),
+                ("4", Number),
+                ("roll", Keyword),
+                ("atk", Reference),
+                ("# to hit", Comment),
+            ]
+        );
+    }
+
+    #[test]
+    fn classify_function() {
+        use HighlightClass::*;
+
+        assert_eq!(
+            classes("f :: fn(a: number) { a }"),
+            [
+                ("f", Definition),
+                ("::", Operator),
+                ("fn", Keyword),
+                ("(", Punctuation),
+                ("a", Definition),
+                (":", Operator),
+                ("number", Type),
+                (")", Punctuation),
+                ("{", Punctuation),
+                ("a", Reference),
+                ("}", Punctuation),
+            ]
+        );
+    }
+
+    #[test]
+    fn ansi_keeps_text() {
+        let root = Root::cast(parser::parse("roll d20 + x").syntax()).unwrap();
+        assert_eq!(
+            highlight_ansi(&root),
+            "\x1b[1;35mroll\x1b[0m \x1b[33md20\x1b[0m + x"
+        );
+    }
+
+    #[test]
+    fn html_escapes() {
+        let root = Root::cast(parser::parse("print(\"<b>\")").syntax()).unwrap();
+        assert_eq!(
+            highlight_html(&root),
+            "<pre class=\"rollang\"><code>\
+             <span class=\"rl-reference\">print</span>\
+             <span class=\"rl-punctuation\">(</span>\
+             <span class=\"rl-string\">&quot;&lt;b&gt;&quot;</span>\
+             <span class=\"rl-punctuation\">)</span>\
+             </code></pre>"
+        );
+    }
+}
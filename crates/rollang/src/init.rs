@@ -0,0 +1,222 @@
+use crate::env::Env;
+use crate::formula::{Dice, Formula, InfixOp, Roll, Roller};
+use std::fmt;
+
+// A creature joining the initiative order, before it has rolled
+pub(crate) struct Entry {
+    pub(crate) name: String,
+    pub(crate) formula: Formula,
+    pub(crate) modifier: i64,
+    pub(crate) dex: Option<i64>,
+}
+
+impl Entry {
+    // `d20` plus the creature's initiative modifier
+    pub(crate) fn with_modifier(name: String, modifier: i64, dex: Option<i64>) -> Self {
+        let d20 = Formula::Dice(Dice::new(1, 20));
+        let formula = match modifier {
+            0 => d20,
+            _ => Formula::Infix {
+                op: if modifier < 0 {
+                    InfixOp::Sub
+                } else {
+                    InfixOp::Add
+                },
+                lhs: Box::new(d20),
+                rhs: Box::new(Formula::Number(modifier.abs())),
+            },
+        };
+
+        Self {
+            name,
+            formula,
+            modifier,
+            dex,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Combatant {
+    pub name: String,
+    pub roll: Roll,
+    pub modifier: i64,
+    pub dex: Option<i64>,
+}
+
+// The order creatures act in during combat, and whose turn it is
+#[derive(Debug, Clone, PartialEq)]
+pub struct TurnOrder {
+    combatants: Vec<Combatant>,
+    // `None` until the first turn starts
+    current: Option<usize>,
+    round: u32,
+}
+
+impl TurnOrder {
+    pub(crate) fn roll(entries: Vec<Entry>, env: &mut Env) -> Result<Self, String> {
+        let mut combatants = entries
+            .into_iter()
+            .map(|entry| {
+                let roll = entry.formula.roll(env)?;
+                env.record(Some(&entry.name), &entry.formula, &roll);
+
+                Ok(Combatant {
+                    roll,
+                    name: entry.name,
+                    modifier: entry.modifier,
+                    dex: entry.dex,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        // Ties go to the higher dexterity score, then the higher modifier. The sort is stable, so
+        // creatures that are still tied keep the order they were given in.
+        combatants.sort_by(|a, b| {
+            b.roll
+                .total
+                .cmp(&a.roll.total)
+                .then(b.dex.cmp(&a.dex))
+                .then(b.modifier.cmp(&a.modifier))
+        });
+
+        Ok(Self {
+            combatants,
+            current: None,
+            round: 0,
+        })
+    }
+
+    pub fn combatants(&self) -> &[Combatant] {
+        &self.combatants
+    }
+
+    pub fn round(&self) -> u32 {
+        self.round
+    }
+
+    pub fn current(&self) -> Option<&Combatant> {
+        self.current.map(|i| &self.combatants[i])
+    }
+
+    // Starts the next creature's turn, moving on to a new round after the last one
+    pub(crate) fn advance(&mut self) -> &Combatant {
+        let next = match self.current {
+            Some(i) if i + 1 < self.combatants.len() => i + 1,
+            _ => {
+                self.round += 1;
+                0
+            }
+        };
+
+        self.current = Some(next);
+        &self.combatants[next]
+    }
+}
+
+impl fmt::Display for TurnOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .combatants
+            .iter()
+            .map(|combatant| combatant.name.chars().count())
+            .max()
+            .unwrap_or(0)
+            + 3;
+
+        let title = match self.round {
+            0 => "Initiative".to_string(),
+            round => format!("Initiative (round {})", round),
+        };
+        write!(f, "{}\n{}", title, "-".repeat(title.chars().count()))?;
+
+        for (i, combatant) in self.combatants.iter().enumerate() {
+            // Once combat has started, point out whose turn it is
+            let marker = match self.current {
+                None => "",
+                Some(current) if current == i => "> ",
+                Some(_) => "  ",
+            };
+            write!(
+                f,
+                "\n{}{:<width$}{}",
+                marker, combatant.name, combatant.roll.total
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(entries: Vec<Entry>) -> TurnOrder {
+        TurnOrder::roll(entries, &mut Env::with_seed(3)).unwrap()
+    }
+
+    fn fixed(name: &str, total: i64, modifier: i64, dex: Option<i64>) -> Entry {
+        Entry {
+            name: name.to_string(),
+            formula: Formula::Number(total),
+            modifier,
+            dex,
+        }
+    }
+
+    fn names(order: &TurnOrder) -> Vec<&str> {
+        order
+            .combatants()
+            .iter()
+            .map(|combatant| combatant.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn highest_total_goes_first() {
+        let order = order(vec![
+            fixed("Utahkh", 4, 0, None),
+            fixed("Mythelia", 16, 0, None),
+            fixed("Boro-Boro", 12, 0, None),
+        ]);
+
+        assert_eq!(
+            order.to_string(),
+            "Initiative\n----------\nMythelia    16\nBoro-Boro   12\nUtahkh      4"
+        );
+    }
+
+    #[test]
+    fn ties_go_to_dex_then_modifier_then_input_order() {
+        let order = order(vec![
+            fixed("a", 10, 0, None),
+            fixed("b", 10, 2, None),
+            fixed("c", 10, 0, Some(14)),
+            fixed("d", 10, 0, None),
+        ]);
+
+        assert_eq!(names(&order), ["c", "b", "a", "d"]);
+    }
+
+    #[test]
+    fn advancing_wraps_into_next_round() {
+        let mut order = order(vec![fixed("a", 2, 0, None), fixed("b", 1, 0, None)]);
+
+        assert_eq!(order.advance().name, "a");
+        assert_eq!(order.advance().name, "b");
+        assert_eq!(order.round(), 1);
+        assert_eq!(order.advance().name, "a");
+        assert_eq!(order.round(), 2);
+        assert_eq!(
+            order.to_string(),
+            "Initiative (round 2)\n--------------------\n> a   2\n  b   1"
+        );
+    }
+
+    #[test]
+    fn modifier_is_added_to_d20() {
+        let entry = Entry::with_modifier("a".to_string(), -1, None);
+        assert_eq!(entry.formula.to_string(), "1d20-1");
+    }
+}
@@ -0,0 +1,40 @@
+mod check;
+mod comp;
+mod crit;
+mod dist;
+mod env;
+mod eval;
+mod format;
+mod formula;
+mod func;
+mod highlight;
+mod init;
+mod lint;
+mod record;
+mod rng;
+mod rollset;
+mod stdlib;
+mod ty;
+mod val;
+
+pub use check::{check, name_types};
+pub use comp::Comp;
+pub use crit::CritRule;
+pub use dist::Dist;
+pub use env::Env;
+pub use eval::{eval, eval_stmt, EvalError};
+pub use format::format;
+pub use formula::{
+    ArithError, Dice, Formula, InfixOp, Modifier, Outcome, Roll, Roller, Select, MAX_DICE,
+};
+pub use highlight::{
+    highlight, highlight_ansi, highlight_html, highlight_html_page, Highlight, HighlightClass,
+    STYLESHEET,
+};
+pub use init::{Combatant, TurnOrder};
+pub use lint::{lint, Lint, LINTS};
+pub use record::RollRecord;
+pub use rng::Rng;
+pub use stdlib::{builtin, builtins, Builtin, Signature};
+pub use ty::Ty;
+pub use val::Val;
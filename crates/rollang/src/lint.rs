@@ -0,0 +1,557 @@
+use crate::dist::Dist;
+use crate::env::Env;
+use crate::stdlib;
+use crate::val::Val;
+use ast::{Expr, Root, Stmt};
+use syntax::{SyntaxElement, SyntaxKind, SyntaxNode};
+use text_size::{TextRange, TextSize};
+
+// Every lint, with what it warns about; the names are what `# allow(...)` takes
+pub const LINTS: [(&str, &str); 7] = [
+    ("unused-binding", "a binding that is never used"),
+    (
+        "shadowed-name",
+        "a definition that hides a built-in or an earlier definition",
+    ),
+    (
+        "dice-spacing",
+        "spaces between dice and their modifier outside parentheses",
+    ),
+    ("impossible-dice", "dice that can only roll one number"),
+    (
+        "constant-roll",
+        "rolling something without dice, which always gives the same total",
+    ),
+    (
+        "division-to-zero",
+        "a division that always rounds down to 0",
+    ),
+    (
+        "modifier-after-roll",
+        "a modifier applied to something that has already been rolled",
+    ),
+];
+
+// Something that's allowed but probably isn't what was meant. Unlike a `check` error, a script
+// with lints still runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub name: &'static str,
+    pub message: String,
+    pub range: TextRange,
+    pub help: Option<String>,
+}
+
+impl Lint {
+    fn new(name: &'static str, message: impl Into<String>, range: TextRange) -> Self {
+        Self {
+            name,
+            message: message.into(),
+            range,
+            help: None,
+        }
+    }
+
+    fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+}
+
+// Finds the lints in a script that is about to run in `env`, leaving out the ones a comment
+// like `# allow(unused-binding)` turns off. The comment works on its own line and the next, and
+// above a statement it covers the whole statement.
+pub fn lint(root: &Root, env: &Env) -> Vec<Lint> {
+    let defaults = Env::with_seed(0);
+    let mut linter = Linter {
+        env,
+        // `atk` is only there until a character defines its own attack roll
+        defaults: defaults
+            .bindings()
+            .iter()
+            .map(|(name, _)| *name)
+            .filter(|name| *name != "atk")
+            .collect(),
+        scopes: Vec::new(),
+        lints: Vec::new(),
+    };
+    linter.stmts(root.stmts());
+
+    let text = root.syntax().text().to_string();
+    let allowed = allow_comments(root, &text);
+    let mut lints: Vec<_> = linter
+        .lints
+        .into_iter()
+        .filter(|lint| !is_allowed(root, &text, &allowed, lint))
+        .collect();
+    lints.sort_by_key(|lint| lint.range.start());
+    lints
+}
+
+struct Def {
+    name: String,
+    range: TextRange,
+    // Only bindings are reported when unused; an unused function may well be called later
+    binding: bool,
+    used: bool,
+    rolled: bool,
+}
+
+struct Linter<'a> {
+    env: &'a Env,
+    // The names every script starts out with, like `pass` and the rollsets
+    defaults: Vec<&'a str>,
+    scopes: Vec<Vec<Def>>,
+    lints: Vec<Lint>,
+}
+
+impl Linter<'_> {
+    fn stmts(&mut self, stmts: impl Iterator<Item = Stmt>) {
+        self.scopes.push(Vec::new());
+        for stmt in stmts {
+            self.stmt(&stmt);
+        }
+        self.pop_scope();
+    }
+
+    fn pop_scope(&mut self) {
+        let unused = self
+            .scopes
+            .pop()
+            .unwrap()
+            .into_iter()
+            .filter(|def| def.binding && !def.used)
+            .map(|def| {
+                Lint::new(
+                    "unused-binding",
+                    format!("`{}` is never used", def.name),
+                    def.range,
+                )
+            });
+        self.lints.extend(unused);
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::VariableDef(def) => {
+                let value = def.value();
+                self.expr(value.as_ref());
+                if let Some(name) = def.name() {
+                    let rolled = value.as_ref().is_some_and(|value| self.is_rolled(value));
+                    self.define(name.text(), name.text_range(), true, rolled);
+                }
+            }
+            Stmt::Assignment(assignment) => {
+                let value = assignment.value();
+                self.expr(value.as_ref());
+                let rolled = value.as_ref().is_some_and(|value| self.is_rolled(value));
+                if let Some(def) = assignment.name().and_then(|name| self.lookup(name.text())) {
+                    def.rolled = rolled;
+                }
+            }
+            Stmt::FnDef(def) => {
+                if let Some(name) = def.name() {
+                    self.define(name.text(), name.text_range(), false, false);
+                }
+                let named = def
+                    .ret_type()
+                    .and_then(|ret| ret.named())
+                    .unwrap_or_default();
+                self.body(def.params().chain(named), def.body());
+            }
+            Stmt::ModDef(def) => {
+                if let Some(name) = def.name() {
+                    self.define(name.text(), name.text_range(), false, false);
+                }
+                self.body(def.params(), def.body());
+            }
+            Stmt::CompDef(def) => {
+                for field in def.fields() {
+                    self.expr(field.value().as_ref());
+                }
+                if let Some(name) = def.name() {
+                    self.define(name.text(), name.text_range(), false, false);
+                }
+            }
+            Stmt::ReturnStmt(ret) => self.expr(ret.value().as_ref()),
+            Stmt::Expr(expr) => self.expr(Some(expr)),
+        }
+    }
+
+    fn body(&mut self, params: impl Iterator<Item = ast::Param>, body: Option<ast::Block>) {
+        self.scopes.push(Vec::new());
+        for name in params.filter_map(|param| param.name()) {
+            self.define(name.text(), name.text_range(), false, false);
+        }
+        if let Some(body) = body {
+            self.stmts(body.stmts());
+        }
+        self.pop_scope();
+    }
+
+    fn define(&mut self, name: &str, range: TextRange, binding: bool, rolled: bool) {
+        if self.lookup(name).is_some() {
+            self.lints.push(
+                Lint::new(
+                    "shadowed-name",
+                    format!("`{}` hides an earlier definition", name),
+                    range,
+                )
+                .with_help(format!(
+                    "rename it, or use `{} = ...` to change a binding",
+                    name
+                )),
+            );
+        } else if stdlib::builtin(name).is_some() || self.defaults.contains(&name) {
+            self.lints.push(
+                Lint::new(
+                    "shadowed-name",
+                    format!("`{}` hides the built-in of the same name", name),
+                    range,
+                )
+                .with_help("rename it"),
+            );
+        }
+
+        self.scopes.last_mut().unwrap().push(Def {
+            name: name.to_string(),
+            range,
+            binding,
+            used: false,
+            rolled,
+        });
+    }
+
+    fn lookup(&mut self, name: &str) -> Option<&mut Def> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|def| def.name == name)
+    }
+
+    // Whether an expression is a roll that has already been made, rather than dice. `crit` gives
+    // back dice, so it doesn't count.
+    fn is_rolled(&mut self, expr: &Expr) -> bool {
+        match expr {
+            Expr::RollExpr(_) => true,
+            Expr::ParenExpr(paren) => paren.expr().is_some_and(|inner| self.is_rolled(&inner)),
+            Expr::VariableRef(var) => {
+                let Some(name) = var.name() else {
+                    return false;
+                };
+                match self.lookup(name.text()) {
+                    Some(def) => def.rolled,
+                    None => matches!(self.env.get_binding(name.text()), Some(Val::Roll(_))),
+                }
+            }
+            _ => false,
+        }
+    }
+
+    fn expr(&mut self, expr: Option<&Expr>) {
+        let Some(expr) = expr else {
+            return;
+        };
+
+        for node in expr.syntax().descendants() {
+            match Expr::cast(node.clone()) {
+                Some(Expr::VariableRef(var)) => {
+                    if let Some(def) = var.name().and_then(|name| self.lookup(name.text())) {
+                        def.used = true;
+                    }
+                }
+                Some(Expr::DiceExpr(dice)) if dice.sides() == Some(1) => {
+                    self.lints.push(
+                        Lint::new(
+                            "impossible-dice",
+                            "a die with one side always rolls 1",
+                            trimmed(&node),
+                        )
+                        .with_help("use a number instead"),
+                    );
+                }
+                Some(Expr::RollExpr(roll))
+                    if roll.expr().is_some_and(|inner| is_constant(&inner)) =>
+                {
+                    self.lints.push(
+                        Lint::new(
+                            "constant-roll",
+                            "this roll has no dice, so it always gives the same total",
+                            trimmed(&node),
+                        )
+                        .with_help("did you mean to add dice, e.g. `d20`?"),
+                    );
+                }
+                Some(Expr::ModExpr(modded))
+                    if modded.expr().is_some_and(|inner| self.is_rolled(&inner)) =>
+                {
+                    self.lints.push(
+                        Lint::new(
+                            "modifier-after-roll",
+                            "this modifier is applied to a value that has already been rolled",
+                            trimmed(&node),
+                        )
+                        .with_help("apply it to the dice before rolling, e.g. `roll d20:adv`"),
+                    );
+                }
+                Some(Expr::InfixExpr(infix)) => {
+                    self.lints.extend(dice_spacing(&infix, &node));
+                    self.lints.extend(division_to_zero(&infix, &node));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// `1d20 +4` reads as the dice and a separate `+4`, so the spec wants it written `1d20+4`, or in
+// parentheses when it's spaced out
+fn dice_spacing(infix: &ast::InfixExpr, node: &SyntaxNode) -> Option<Lint> {
+    let op = infix.op()?;
+    let (lhs, rhs) = (infix.lhs()?, infix.rhs()?);
+    if !matches!(lhs, Expr::DiceExpr(_))
+        || !matches!(rhs, Expr::Literal(_))
+        || !matches!(op.kind(), SyntaxKind::Plus | SyntaxKind::Minus)
+        || node.parent()?.kind() == SyntaxKind::ParenExpr
+    {
+        return None;
+    }
+
+    let dice = lhs.syntax().first_token()?;
+    let number = rhs.syntax().first_token()?;
+    if dice.text_range().end() == op.text_range().start()
+        && op.text_range().end() == number.text_range().start()
+    {
+        return None;
+    }
+
+    Some(
+        Lint::new(
+            "dice-spacing",
+            "spaces between dice and their modifier need parentheses",
+            trimmed(node),
+        )
+        .with_help(format!(
+            "write `{}{}{}`",
+            dice.text(),
+            op.text(),
+            number.text()
+        )),
+    )
+}
+
+// Division rounds down, so dividing by more than the dividend can ever be gives 0 every time
+fn division_to_zero(infix: &ast::InfixExpr, node: &SyntaxNode) -> Option<Lint> {
+    if infix.op()?.kind() != SyntaxKind::Slash {
+        return None;
+    }
+    let Some(Expr::Literal(divisor)) = infix.rhs() else {
+        return None;
+    };
+    let divisor = divisor.parse()?;
+    let lhs = infix.lhs()?;
+    if !is_formula(&lhs) {
+        return None;
+    }
+
+    let mut env = Env::with_seed(0);
+    let (min, max) = match crate::eval::eval_stmt(&Stmt::Expr(lhs), &mut env).ok()? {
+        Val::Number(n) => (n, n),
+        Val::Formula(formula) => {
+            let dist = Dist::of(&formula).ok()?;
+            (dist.min(), dist.max())
+        }
+        _ => return None,
+    };
+
+    (min >= 0 && max < divisor).then(|| {
+        Lint::new(
+            "division-to-zero",
+            format!(
+                "this is always 0, since it's at most {} and division rounds down",
+                max
+            ),
+            trimmed(node),
+        )
+    })
+}
+
+// Numbers and dice only, so they can be worked out without running anything
+fn is_formula(expr: &Expr) -> bool {
+    expr.syntax().descendants().all(|node| {
+        matches!(
+            node.kind(),
+            SyntaxKind::Literal
+                | SyntaxKind::DiceExpr
+                | SyntaxKind::InfixExpr
+                | SyntaxKind::PrefixExpr
+                | SyntaxKind::ParenExpr
+        )
+    })
+}
+
+fn is_constant(expr: &Expr) -> bool {
+    is_formula(expr)
+        && !expr
+            .syntax()
+            .descendants()
+            .any(|node| node.kind() == SyntaxKind::DiceExpr)
+}
+
+// Nodes take the whitespace after them along
+fn trimmed(node: &SyntaxNode) -> TextRange {
+    let end = node
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !token.kind().is_trivia())
+        .last()
+        .map_or(node.text_range().end(), |token| token.text_range().end());
+
+    TextRange::new(node.text_range().start(), end)
+}
+
+// The lint names of every `# allow(...)` comment, with the offset of the line it's on
+fn allow_comments(root: &Root, text: &str) -> Vec<(usize, Vec<String>)> {
+    root.syntax()
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| token.kind() == SyntaxKind::Comment)
+        .filter_map(|comment| {
+            let (_, rest) = comment.text().split_once("allow(")?;
+            let (names, _) = rest.split_once(')')?;
+            let names = names.split(',').map(|name| name.trim().to_string());
+
+            Some((line_of(text, comment.text_range().start()), names.collect()))
+        })
+        .collect()
+}
+
+fn is_allowed(root: &Root, text: &str, allowed: &[(usize, Vec<String>)], lint: &Lint) -> bool {
+    let line = line_of(text, lint.range.start());
+
+    // The lint's own line and the one above it, and the same for every statement it's in
+    let mut lines = vec![line, line.saturating_sub(1)];
+    let node = match root.syntax().covering_element(lint.range) {
+        SyntaxElement::Node(node) => node,
+        SyntaxElement::Token(token) => token.parent().unwrap(),
+    };
+    for stmt in node.ancestors().filter(|node| {
+        node.parent()
+            .is_some_and(|parent| matches!(parent.kind(), SyntaxKind::Root | SyntaxKind::Block))
+    }) {
+        let start = line_of(text, stmt.text_range().start());
+        lines.extend([start, start.saturating_sub(1)]);
+    }
+
+    allowed.iter().any(|(comment_line, names)| {
+        lines.contains(comment_line) && names.iter().any(|name| name == lint.name)
+    })
+}
+
+fn line_of(text: &str, offset: TextSize) -> usize {
+    text[..usize::from(offset)].matches('\n').count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(input: &str, expected: &[(&str, &str)]) {
+        let parse = parser::parse(input);
+        assert_eq!(parse.errors(), &[]);
+        let root = Root::cast(parse.syntax()).unwrap();
+
+        let lints: Vec<_> = lint(&root, &Env::with_seed(0))
+            .into_iter()
+            .map(|lint| (lint.name, &input[lint.range]))
+            .collect();
+        assert_eq!(lints, expected);
+    }
+
+    #[test]
+    fn clean_script() {
+        check(
+            "atk := 1d20+4\nbonus :: fn(x: number) -> number { x + 2 }\nroll bonus(atk)",
+            &[],
+        );
+    }
+
+    #[test]
+    fn unused_binding() {
+        check(
+            "hp := 2d8\nlet ac = 15\nroll hp",
+            &[("unused-binding", "ac")],
+        );
+    }
+
+    #[test]
+    fn unused_binding_in_function() {
+        check(
+            "f :: fn(x: number) -> number { y := 2; x }\nf(1)",
+            &[("unused-binding", "y")],
+        );
+    }
+
+    #[test]
+    fn shadowed_name() {
+        check("x := 1\nx := x + 1\nroll x", &[("shadowed-name", "x")]);
+        check(
+            "bonus := 2\nf :: fn(bonus: number) { bonus }\nroll f(bonus)",
+            &[("shadowed-name", "bonus")],
+        );
+        check("max := 3\nroll max", &[("shadowed-name", "max")]);
+    }
+
+    #[test]
+    fn dice_spacing() {
+        check("roll 1d20 +4", &[("dice-spacing", "1d20 +4")]);
+        check("roll 1d20+4", &[]);
+        check("roll (1d20 + 4)", &[]);
+    }
+
+    #[test]
+    fn impossible_dice() {
+        check("roll 3d1", &[("impossible-dice", "3d1")]);
+    }
+
+    #[test]
+    fn constant_roll() {
+        check("roll 2 + 3", &[("constant-roll", "roll 2 + 3")]);
+        check("roll 2 + d4", &[]);
+    }
+
+    #[test]
+    fn division_to_zero() {
+        check("roll 1d4 / 10", &[("division-to-zero", "1d4 / 10")]);
+        check("roll 2d6 / 2", &[]);
+    }
+
+    #[test]
+    fn modifier_after_roll() {
+        check(
+            "atk := roll d20\natk:adv",
+            &[("modifier-after-roll", "atk:adv")],
+        );
+        check(
+            "(roll d20):adv",
+            &[("modifier-after-roll", "(roll d20):adv")],
+        );
+        check("atk := d20\nroll atk:adv", &[]);
+        check("x := crit 1d8\nroll x:adv", &[]);
+        check("roll (crit 1d8):adv", &[]);
+    }
+
+    #[test]
+    fn allow_comment() {
+        check("ac := 15 # allow(unused-binding)", &[]);
+        check(
+            "# allow(shadowed-name, unused-binding)\nf :: fn(max: number) {\n    x := 1\n    max\n}",
+            &[],
+        );
+        check(
+            "# allow(dice-spacing)\nac := 15",
+            &[("unused-binding", "ac")],
+        );
+    }
+}
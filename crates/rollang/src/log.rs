@@ -0,0 +1,238 @@
+use crate::session::SessionWriter;
+use rollang::{Outcome, RollRecord};
+use serde_json::{json, Value};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub(crate) enum LogFormat {
+    // One JSON object per roll, for tools
+    #[default]
+    Json,
+    // One line per roll, for people
+    Text,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "text" => Ok(Self::Text),
+            _ => Err(format!(
+                "unknown log format `{}` (expected json or text)",
+                s
+            )),
+        }
+    }
+}
+
+// Every roll of a session, appended to a file as it's made so that nothing is lost if the
+// session ends abruptly
+pub(crate) struct RollLog {
+    file: File,
+    format: LogFormat,
+}
+
+impl RollLog {
+    pub(crate) fn open(path: &str, format: LogFormat) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file, format })
+    }
+
+    // `source` is the input the rolls were made by
+    pub(crate) fn write(&mut self, source: &str, rolls: &[RollRecord]) -> io::Result<()> {
+        let time = timestamp(SystemTime::now());
+
+        for record in rolls {
+            let line = match self.format {
+                LogFormat::Json => json_line(&time, source, record),
+                LogFormat::Text => text_line(&time, source, record),
+            };
+            writeln!(self.file, "{}", line)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Where the rolls of a session go: the roll log, the recorded session, or neither
+#[derive(Default)]
+pub(crate) struct Recorder {
+    pub(crate) log: Option<RollLog>,
+    pub(crate) session: Option<SessionWriter>,
+    // The rolls of the input that's running, whichever of its statements made them
+    pending: Vec<RollRecord>,
+}
+
+impl Recorder {
+    // Takes the rolls made by `source` out of the environment, handing them back. They're taken
+    // even when nothing is recorded, so that they don't pile up there.
+    pub(crate) fn take_rolls(
+        &mut self,
+        source: &str,
+        env: &mut rollang::Env,
+    ) -> io::Result<&[RollRecord]> {
+        let rolls = env.take_rolls();
+        if let Some(log) = &mut self.log {
+            log.write(source, &rolls)?;
+        }
+
+        let start = self.pending.len();
+        self.pending.extend(rolls);
+        Ok(&self.pending[start..])
+    }
+
+    // Ends an input, handing back every roll it made
+    pub(crate) fn finish_input(
+        &mut self,
+        input: &str,
+        reseed: Option<u64>,
+    ) -> io::Result<Vec<RollRecord>> {
+        let rolls = std::mem::take(&mut self.pending);
+        if let Some(session) = &mut self.session {
+            session.write(input, &rolls, reseed)?;
+        }
+
+        Ok(rolls)
+    }
+}
+
+fn json_line(time: &str, source: &str, record: &RollRecord) -> String {
+    let mut line = json!({ "time": time, "source": source });
+    line.as_object_mut()
+        .unwrap()
+        .extend(roll_json(record).as_object().unwrap().clone());
+
+    line.to_string()
+}
+
+// A roll as both the log and `--format json` show it
+pub(crate) fn roll_json(record: &RollRecord) -> Value {
+    json!({
+        "binding": record.binding,
+        "annotation": record.annotation,
+        "formula": record.formula.to_string(),
+        "dice": dice_json(&record.roll.outcome),
+        "total": record.roll.total,
+        "seed": record.seed,
+        "seq": record.seq,
+    })
+}
+
+// Every die of an outcome with its faces and the ones that counted
+pub(crate) fn dice_json(outcome: &Outcome) -> Value {
+    outcome
+        .dice()
+        .into_iter()
+        .map(|(dice, faces)| {
+            json!({
+                "dice": dice.to_string(),
+                "faces": faces,
+                "kept": dice.kept_faces(faces),
+            })
+        })
+        .collect()
+}
+
+// `2026-10-19T18:30:00Z #3 attack: 1d20=14 +5 ->19`, then the input it came from
+fn text_line(time: &str, source: &str, record: &RollRecord) -> String {
+    let binding = record
+        .binding
+        .as_ref()
+        .map_or(String::new(), |binding| format!("{}: ", binding));
+
+    format!(
+        "{} #{} {}{}    | {}",
+        time,
+        record.seq,
+        binding,
+        record.roll,
+        source.trim().replace('\n', " ")
+    )
+}
+
+// UTC in RFC 3339, e.g. `2026-10-19T18:30:00Z`
+fn timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    )
+}
+
+// Howard Hinnant's algorithm for turning days since 1970-01-01 into a date
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn record(input: &str) -> RollRecord {
+        let mut env = rollang::Env::with_seed(1234);
+        let root = ast::Root::cast(parser::parse(input).syntax()).unwrap();
+        rollang::eval(&root, &mut env).unwrap();
+        env.take_rolls().remove(0)
+    }
+
+    #[test]
+    fn timestamp_of_epoch() {
+        assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn timestamp_in_leap_year() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        assert_eq!(timestamp(time), "2024-02-29T12:34:56Z");
+    }
+
+    #[test]
+    fn json_line_has_every_field() {
+        let record = record("best := roll 2d20kh1 + 3");
+        let line: serde_json::Value =
+            serde_json::from_str(&json_line("T", "best := roll 2d20kh1 + 3", &record)).unwrap();
+
+        assert_eq!(line["source"], "best := roll 2d20kh1 + 3");
+        assert_eq!(line["binding"], "best");
+        assert_eq!(line["annotation"], serde_json::Value::Null);
+        assert_eq!(line["formula"], "2d20kh1+3");
+        assert_eq!(line["dice"][0]["dice"], "2d20kh1");
+        assert_eq!(line["dice"][0]["faces"].as_array().unwrap().len(), 2);
+        assert_eq!(line["total"], record.roll.total);
+        assert_eq!(line["seed"], 1234);
+        assert_eq!(line["seq"], 0);
+    }
+
+    #[test]
+    fn text_line_shows_the_roll() {
+        let record = record("roll 1d20");
+        assert_eq!(
+            text_line("T", "roll 1d20\n", &record),
+            format!("T #0 {}    | roll 1d20", record.roll)
+        );
+    }
+}
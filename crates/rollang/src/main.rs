@@ -1,44 +1,723 @@
+mod diagnostic;
+mod log;
+mod output;
+mod repl;
+mod session;
+
+use diagnostic::{Diagnostic, Severity};
+use log::{LogFormat, Recorder, RollLog};
+use output::OutputFormat;
 use parser::parse;
+use session::{ReplayResult, SessionWriter};
 use std::io::{self, Write};
+use std::process;
+
+// Exit statuses, the same for every command. Mistakes in the code being run (or a replay that
+// diverged) are failures; usage errors are a wrong flag or a file that can't be read.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Exit {
+    Success = 0,
+    Failure = 1,
+    Usage = 2,
+}
+
+const USAGE: &str = "\
+Usage: rollang [FLAGS] [COMMAND | FILE]
+
+With no command, starts a session at the `>>>` prompt.
+
+Commands:
+    FILE                  Runs a .roll file
+    run FILE              Runs a .roll file, whatever it's called
+    eval CODE             Runs CODE as if it were a file
+    check FILE            Reports the mistakes and warnings in a file without rolling anything
+    dist CODE             Shows the chance of every total CODE can roll
+    fmt [--check] FILE    Formats a file in place, or checks that it's formatted
+    highlight [--html] FILE
+                          Prints a file in colour, or as a web page
+    tokens FILE           Shows how a file is split into tokens
+    ast FILE              Shows how a file is parsed
+    replay FILE           Replays a recorded session
+
+A FILE of `-` is read from stdin, and anything after `--` is the file to run.
+
+Flags:
+    --seed N              Rolls from seed N, so the same rolls come up every time
+    --crit RULE           How crits are rolled: double-dice, max-plus-roll or double-total
+    --format FORMAT       text or json
+    --log FILE            Writes every roll to FILE
+    --log-format FORMAT   text or json
+    --record FILE         Records a session so it can be replayed
+    -h, --help            Shows this help
+";
 
 fn main() -> io::Result<()> {
-    println!("Rollang v0.1.0");
+    let mut env = rollang::Env::default();
+
+    let options = match configure(&mut env, std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            process::exit(Exit::Usage as i32);
+        }
+    };
+
+    let mut recorder = Recorder::default();
+    if let Some(path) = &options.log {
+        recorder.log = Some(
+            RollLog::open(path, options.log_format).unwrap_or_else(|err| {
+                eprintln!("{}: {}", path, err);
+                process::exit(Exit::Usage as i32);
+            }),
+        );
+    }
+
+    let exit = match &options.command {
+        Command::Repl => {
+            if let Some(path) = &options.record {
+                recorder.session = Some(SessionWriter::create(path, &env).unwrap_or_else(|err| {
+                    eprintln!("{}: {}", path, err);
+                    process::exit(Exit::Usage as i32);
+                }));
+            }
+
+            if let Err(err) = repl::run(&mut env, &mut recorder) {
+                eprintln!("{}", err);
+                process::exit(Exit::Failure as i32);
+            }
+            Exit::Success
+        }
+        Command::Help => {
+            print!("{}", USAGE);
+            Exit::Success
+        }
+        Command::Run(path) => run_script(path, &mut env, &mut recorder, options.format)?,
+        Command::Eval(code) => run_source(None, code, &mut env, &mut recorder, options.format)?,
+        Command::Check(path) => check(path, &env, options.format),
+        Command::Tokens(path) => tokens(path),
+        Command::Ast(path) => ast(path, options.format),
+        Command::Dist(code) => dist(code, &env, options.format),
+        Command::Fmt { path, check } => fmt(path, *check),
+        Command::Highlight { path, html } => highlight(path, *html),
+        Command::Replay(path) => replay(path),
+    };
+
+    process::exit(exit as i32);
+}
+
+// What rollang was asked to do; everything but the REPL runs once and exits
+#[derive(Debug, Default, PartialEq)]
+enum Command {
+    #[default]
+    Repl,
+    Help,
+    Run(String),
+    Eval(String),
+    Check(String),
+    Tokens(String),
+    Ast(String),
+    Dist(String),
+    // `check` only reports whether the file is formatted, without changing it
+    Fmt {
+        path: String,
+        check: bool,
+    },
+    // `html` makes a web page rather than colouring for a terminal
+    Highlight {
+        path: String,
+        html: bool,
+    },
+    Replay(String),
+}
+
+impl Command {
+    // `arg` is a file for the commands that read one (`-` for stdin) and code for the others
+    fn new(name: &str, arg: String) -> Option<Self> {
+        Some(match name {
+            "run" => Self::Run(arg),
+            "eval" => Self::Eval(arg),
+            "check" => Self::Check(arg),
+            "tokens" => Self::Tokens(arg),
+            "ast" => Self::Ast(arg),
+            "dist" => Self::Dist(arg),
+            "replay" => Self::Replay(arg),
+            _ => return None,
+        })
+    }
+
+    fn needs(name: &str) -> Option<&'static str> {
+        match name {
+            "eval" | "dist" => Some("code"),
+            "run" | "check" | "tokens" | "ast" => Some("a file"),
+            "replay" => Some("a session file"),
+            _ => None,
+        }
+    }
+}
+
+// What the flags ask for beyond setting up the environment
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    command: Command,
+    format: OutputFormat,
+    log: Option<String>,
+    log_format: LogFormat,
+    record: Option<String>,
+}
+
+// Applies the flags to the environment and hands back the rest
+fn configure(
+    env: &mut rollang::Env,
+    mut args: impl Iterator<Item = String>,
+) -> Result<Options, String> {
+    let mut options = Options::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--crit" => {
+                let rule = args
+                    .next()
+                    .ok_or_else(|| "--crit needs a rule".to_string())?;
+                env.set_crit_rule(rule.parse()?);
+            }
+            "--seed" => {
+                let seed = args
+                    .next()
+                    .ok_or_else(|| "--seed needs a number".to_string())?;
+                let seed = seed
+                    .parse()
+                    .map_err(|_| format!("`{}` is not a seed; use a whole number", seed))?;
+                env.set_seed(seed);
+            }
+            "--format" => {
+                let format = args
+                    .next()
+                    .ok_or_else(|| "--format needs a format".to_string())?;
+                options.format = format.parse()?;
+            }
+            "--log" => {
+                let path = args
+                    .next()
+                    .ok_or_else(|| "--log needs a file".to_string())?;
+                options.log = Some(path);
+            }
+            "--log-format" => {
+                let format = args
+                    .next()
+                    .ok_or_else(|| "--log-format needs a format".to_string())?;
+                options.log_format = format.parse()?;
+            }
+            "--record" => {
+                let path = args
+                    .next()
+                    .ok_or_else(|| "--record needs a file".to_string())?;
+                options.record = Some(path);
+            }
+            "--help" | "-h" => {
+                options.command = Command::Help;
+                return Ok(options);
+            }
+            // The file to run, even if it's named like a command or a flag
+            "--" if options.command == Command::Repl => {
+                let path = args.next().ok_or_else(|| "-- needs a file".to_string())?;
+                options.command = Command::Run(path);
+            }
+            // A lone `-` is stdin rather than a flag
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("unknown argument `{}`", arg))
+            }
+            "fmt" if options.command == Command::Repl => {
+                let mut path = args.next();
+                let check = path.as_deref() == Some("--check");
+                if check {
+                    path = args.next();
+                }
+
+                let path = path.ok_or_else(|| "fmt needs a file".to_string())?;
+                options.command = Command::Fmt { path, check };
+            }
+            "highlight" if options.command == Command::Repl => {
+                let mut path = args.next();
+                let html = path.as_deref() == Some("--html");
+                if html {
+                    path = args.next();
+                }
+
+                let path = path.ok_or_else(|| "highlight needs a file".to_string())?;
+                options.command = Command::Highlight { path, html };
+            }
+            // The argument of a command is taken as it is, so that `rollang eval -1d4` works
+            name if options.command == Command::Repl => {
+                options.command = match Command::needs(name) {
+                    Some(needs) => {
+                        let arg = args
+                            .next()
+                            .ok_or_else(|| format!("{} needs {}", name, needs))?;
+                        Command::new(name, arg).unwrap()
+                    }
+                    None => Command::Run(arg),
+                };
+            }
+            _ => return Err(format!("unexpected argument `{}`", arg)),
+        }
+    }
+
+    if options.record.is_some() && options.command != Command::Repl {
+        return Err(
+            "--record only records REPL sessions; a script can be run again as it is".to_string(),
+        );
+    }
+
+    let takes_format = matches!(
+        options.command,
+        Command::Run(_) | Command::Eval(_) | Command::Check(_) | Command::Ast(_) | Command::Dist(_)
+    );
+    if options.format == OutputFormat::Json && !takes_format {
+        return Err("--format json only works with files, eval, check, ast and dist".to_string());
+    }
+
+    Ok(options)
+}
+
+fn replay(path: &str) -> Exit {
+    let Some(text) = read_source(path) else {
+        return Exit::Usage;
+    };
+
+    match session::replay(&text) {
+        Ok(ReplayResult::Matched(inputs, rolls)) => {
+            println!("{}: all {} rolls of {} inputs matched", path, rolls, inputs);
+            Exit::Success
+        }
+        Ok(ReplayResult::Diverged(divergence)) => {
+            eprintln!("{}: {}", path, divergence);
+            Exit::Failure
+        }
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            Exit::Failure
+        }
+    }
+}
+
+// `-` reads stdin, so that tools can pipe code in
+fn read_source(path: &str) -> Option<String> {
+    let source = if path == "-" {
+        io::read_to_string(io::stdin())
+    } else {
+        std::fs::read_to_string(path)
+    };
+
+    source.map_err(|err| eprintln!("{}: {}", path, err)).ok()
+}
+
+// Parses, checks and lints a file without running any of it. Lints are only warnings, so they
+// don't make it fail.
+fn check(path: &str, env: &rollang::Env, format: OutputFormat) -> Exit {
+    let Some(source) = read_source(path) else {
+        return Exit::Usage;
+    };
+
+    if report_mistakes(Some(path), &source, env, true, format) {
+        Exit::Failure
+    } else {
+        Exit::Success
+    }
+}
+
+// Formats a file in place, or prints it formatted when it's stdin. Files that don't parse are
+// left alone, since there's no telling what their broken parts should look like.
+fn fmt(path: &str, check: bool) -> Exit {
+    let Some(source) = read_source(path) else {
+        return Exit::Usage;
+    };
+
+    let parse = parse(&source);
+    if !parse.errors().is_empty() {
+        for (idx, error) in parse.errors().iter().enumerate() {
+            if idx > 0 {
+                eprintln!();
+            }
+            let diagnostic = Diagnostic::from_parse_error(error, &source);
+            report(&diagnostic, Some(path), &source, OutputFormat::Text);
+        }
+        return Exit::Failure;
+    }
+
+    let formatted = rollang::format(&ast::Root::cast(parse.syntax()).unwrap());
+
+    if check {
+        if formatted == source {
+            return Exit::Success;
+        }
+        eprintln!(
+            "{}: not formatted; `rollang fmt {}` would fix it",
+            path, path
+        );
+        return Exit::Failure;
+    }
+
+    if path == "-" {
+        print!("{}", formatted);
+    } else if formatted != source {
+        if let Err(err) = std::fs::write(path, formatted) {
+            eprintln!("{}: {}", path, err);
+            return Exit::Usage;
+        }
+    }
+    Exit::Success
+}
 
-    let stdin = io::stdin();
+// Broken code is highlighted too, with what couldn't be lexed marked as an error
+fn highlight(path: &str, html: bool) -> Exit {
+    let Some(source) = read_source(path) else {
+        return Exit::Usage;
+    };
+
+    let root = ast::Root::cast(parse(&source).syntax()).unwrap();
+    if html {
+        print!("{}", rollang::highlight_html_page(&root, path));
+    } else {
+        print!("{}", rollang::highlight_ansi(&root));
+    }
+    Exit::Success
+}
+
+fn tokens(path: &str) -> Exit {
+    let Some(source) = read_source(path) else {
+        return Exit::Usage;
+    };
+
+    print!("{}", repl::tokens(&source));
+    if lexer::Lexer::new(&source).any(|token| token.kind.is_err()) {
+        Exit::Failure
+    } else {
+        Exit::Success
+    }
+}
+
+// The syntax tree and any parse errors, the way the parser's own tests show them
+fn ast(path: &str, format: OutputFormat) -> Exit {
+    let Some(source) = read_source(path) else {
+        return Exit::Usage;
+    };
+
+    let parse = parse(&source);
+    match format {
+        OutputFormat::Text => println!("{}", parse.debug_tree()),
+        OutputFormat::Json => println!("{}", output::ast_json(&parse)),
+    }
+    if parse.errors().is_empty() {
+        Exit::Success
+    } else {
+        Exit::Failure
+    }
+}
+
+fn dist(code: &str, env: &rollang::Env, format: OutputFormat) -> Exit {
+    match repl::dist(code, env) {
+        Ok(dist) => {
+            match format {
+                OutputFormat::Text => println!("{}", dist),
+                OutputFormat::Json => println!("{}", output::dist_json(&dist)),
+            }
+            Exit::Success
+        }
+        Err(diagnostic) => {
+            report(&diagnostic, None, code, format);
+            Exit::Failure
+        }
+    }
+}
+
+fn run_script(
+    path: &str,
+    env: &mut rollang::Env,
+    recorder: &mut Recorder,
+    format: OutputFormat,
+) -> io::Result<Exit> {
+    match read_source(path) {
+        Some(source) => run_source(Some(path), &source, env, recorder, format),
+        None => Ok(Exit::Usage),
+    }
+}
+
+// Every parse and check error is reported before anything runs, so a typo at the bottom of a
+// script doesn't waste the rolls above it. `path` is `None` for code given on the command line.
+fn run_source(
+    path: Option<&str>,
+    source: &str,
+    env: &mut rollang::Env,
+    recorder: &mut Recorder,
+    format: OutputFormat,
+) -> io::Result<Exit> {
+    if report_mistakes(path, source, env, false, format) {
+        return Ok(Exit::Failure);
+    }
+
+    let root = ast::Root::cast(parse(source).syntax()).unwrap();
     let mut stdout = io::stdout();
-    let mut stderr = io::stderr();
 
-    let mut input = String::new();
-    // let mut env = rollang::Env::default();
+    for (idx, stmt) in root.stmts().enumerate() {
+        let result = rollang::eval_stmt(&stmt, env);
+        let output = env.take_output();
+        let rolls = recorder.take_rolls(&stmt.syntax().text().to_string(), env)?;
 
-    loop {
-        write!(stdout, ">> ")?;
-        stdout.flush()?;
+        match (result, format) {
+            (Ok(val), OutputFormat::Json) => {
+                let range = stmt.syntax().text_range();
+                let json = output::statement_json(source, range, &val, &output, rolls);
+                writeln!(stdout, "{}", json)?;
+            }
+            (Ok(rollang::Val::Unit), OutputFormat::Text) => write!(stdout, "{}", output)?,
+            (Ok(val), OutputFormat::Text) => writeln!(stdout, "{}{}", output, val)?,
+            (Err(error), _) => {
+                write!(stdout, "{}", output)?;
+                let mut diagnostic = Diagnostic::from_eval_error(&error);
+                if idx > 0 {
+                    // An error inside a function isn't in the statement that called it
+                    let note = if error.calls.is_empty() {
+                        "the statements above this one have already run".to_string()
+                    } else {
+                        let start = stmt.syntax().text_range().start();
+                        let (line, _) = diagnostic::line_col(source, start);
+                        format!("the statements above line {} have already run", line)
+                    };
+                    diagnostic = diagnostic.with_note(note);
+                }
+                report(&diagnostic, path, source, format);
+                return Ok(Exit::Failure);
+            }
+        }
+    }
+
+    Ok(Exit::Success)
+}
+
+// Prints every parse and check error in `source` as it would run in `env`, and its lints when
+// `lint` is set, returning whether there were any errors
+fn report_mistakes(
+    path: Option<&str>,
+    source: &str,
+    env: &rollang::Env,
+    lint: bool,
+    format: OutputFormat,
+) -> bool {
+    let parse = parse(source);
+    let root = ast::Root::cast(parse.syntax()).unwrap();
+
+    let mut diagnostics: Vec<_> = parse
+        .errors()
+        .iter()
+        .map(|error| {
+            (
+                error.range.start(),
+                Diagnostic::from_parse_error(error, source),
+            )
+        })
+        .collect();
+    diagnostics.extend(rollang::check(&root, env).iter().map(|error| {
+        let diagnostic = Diagnostic::from_eval_error(error).with_code("check");
+        (error.range.start(), diagnostic)
+    }));
+    // Lints of code that doesn't parse would mostly be about what's missing
+    if lint && parse.errors().is_empty() {
+        diagnostics.extend(
+            rollang::lint(&root, env)
+                .iter()
+                .map(|lint| (lint.range.start(), Diagnostic::from_lint(lint))),
+        );
+    }
 
-        stdin.read_line(&mut input)?;
-        let parse = parse(&input);
-        println!("{}", parse.debug_tree());
+    diagnostics.sort_by_key(|(offset, _)| *offset);
+    for (idx, (_, diagnostic)) in diagnostics.iter().enumerate() {
+        if idx > 0 && format == OutputFormat::Text {
+            eprintln!();
+        }
+        report(diagnostic, path, source, format);
+    }
 
-        // match run(input.trim(), &mut env) {
-        //     Ok(Some(val)) => writeln!(stdout, "{}", val)?,
-        //     Ok(None) => {}
-        //     Err(msg) => writeln!(stderr, "{}", msg)?,
-        // }
+    diagnostics
+        .iter()
+        .any(|(_, diagnostic)| diagnostic.severity() == Severity::Error)
+}
 
-        input.clear();
+// Rendered on stderr for people, or as a line of JSON on stdout along with the values
+fn report(diagnostic: &Diagnostic, path: Option<&str>, source: &str, format: OutputFormat) {
+    match format {
+        OutputFormat::Text => eprint!(
+            "{}",
+            diagnostic.render(path, source, diagnostic::use_colour())
+        ),
+        OutputFormat::Json => println!("{}", diagnostic.to_json(path, source)),
     }
 }
 
-// fn run(input: &str, env: &mut rollang::Env) -> Result<Option<rollang::Val>, String> {
-//     let parse = eldiro::parse(input).map_err(|msg| format!("Parse error: {}", msg))?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configure_takes_script_path() {
+        let mut env = rollang::Env::default();
+        let args = ["--crit", "double-dice", "fighter.roll"].map(String::from);
+        assert_eq!(
+            configure(&mut env, args.into_iter()),
+            Ok(Options {
+                command: Command::Run("fighter.roll".to_string()),
+                ..Options::default()
+            })
+        );
+    }
+
+    #[test]
+    fn configure_takes_stdin_script() {
+        let mut env = rollang::Env::default();
+        let args = ["--seed", "3", "-"].map(String::from);
+        assert_eq!(
+            configure(&mut env, args.into_iter()),
+            Ok(Options {
+                command: Command::Run("-".to_string()),
+                ..Options::default()
+            })
+        );
+    }
+
+    #[test]
+    fn configure_takes_replay() {
+        let mut env = rollang::Env::default();
+        let args = ["replay", "session.jsonl"].map(String::from);
+        assert_eq!(
+            configure(&mut env, args.into_iter()),
+            Ok(Options {
+                command: Command::Replay("session.jsonl".to_string()),
+                ..Options::default()
+            })
+        );
+    }
+
+    #[test]
+    fn configure_takes_eval_code_as_it_is() {
+        let mut env = rollang::Env::default();
+        let args = ["--seed", "3", "eval", "-1d4"].map(String::from);
+        assert_eq!(
+            configure(&mut env, args.into_iter()),
+            Ok(Options {
+                command: Command::Eval("-1d4".to_string()),
+                ..Options::default()
+            })
+        );
+        assert_eq!(env.seed(), 3);
+    }
 
-//     let evaluated = parse
-//         .eval(env)
-//         .map_err(|msg| format!("Evaluation error: {}", msg))?;
+    #[test]
+    fn configure_takes_help() {
+        let mut env = rollang::Env::default();
+        let args = ["--format", "json", "-h"].map(String::from);
+        assert_eq!(
+            configure(&mut env, args.into_iter()),
+            Ok(Options {
+                command: Command::Help,
+                format: OutputFormat::Json,
+                ..Options::default()
+            })
+        );
+    }
 
-//     if evaluated == rollang::Val::Unit {
-//         Ok(None)
-//     } else {
-//         Ok(Some(evaluated))
-//     }
-// }
+    #[test]
+    fn configure_runs_script_named_like_command() {
+        for args in [["run", "check"], ["--", "check"]] {
+            let mut env = rollang::Env::default();
+            assert_eq!(
+                configure(&mut env, args.map(String::from).into_iter()),
+                Ok(Options {
+                    command: Command::Run("check".to_string()),
+                    ..Options::default()
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn configure_needs_command_argument() {
+        let mut env = rollang::Env::default();
+        let args = ["check"].map(String::from);
+        assert_eq!(
+            configure(&mut env, args.into_iter()),
+            Err("check needs a file".to_string())
+        );
+    }
+
+    #[test]
+    fn configure_rejects_record_outside_repl() {
+        let mut env = rollang::Env::default();
+        let args = ["--record", "s.jsonl", "eval", "1d20"].map(String::from);
+        assert!(configure(&mut env, args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn configure_takes_json_format() {
+        let mut env = rollang::Env::default();
+        let args = ["--format", "json", "check", "fighter.roll"].map(String::from);
+        assert_eq!(
+            configure(&mut env, args.into_iter()),
+            Ok(Options {
+                command: Command::Check("fighter.roll".to_string()),
+                format: OutputFormat::Json,
+                ..Options::default()
+            })
+        );
+    }
+
+    #[test]
+    fn configure_rejects_json_in_repl() {
+        let mut env = rollang::Env::default();
+        let args = ["--format", "json"].map(String::from);
+        assert_eq!(
+            configure(&mut env, args.into_iter()),
+            Err("--format json only works with files, eval, check, ast and dist".to_string())
+        );
+    }
+
+    #[test]
+    fn configure_takes_fmt_check() {
+        let mut env = rollang::Env::default();
+        let args = ["fmt", "--check", "fighter.roll"].map(String::from);
+        assert_eq!(
+            configure(&mut env, args.into_iter()),
+            Ok(Options {
+                command: Command::Fmt {
+                    path: "fighter.roll".to_string(),
+                    check: true,
+                },
+                ..Options::default()
+            })
+        );
+    }
+
+    #[test]
+    fn configure_takes_highlight_html() {
+        let mut env = rollang::Env::default();
+        let args = ["highlight", "--html", "fighter.roll"].map(String::from);
+        assert_eq!(
+            configure(&mut env, args.into_iter()),
+            Ok(Options {
+                command: Command::Highlight {
+                    path: "fighter.roll".to_string(),
+                    html: true,
+                },
+                ..Options::default()
+            })
+        );
+    }
+
+    #[test]
+    fn configure_rejects_second_script() {
+        let mut env = rollang::Env::default();
+        let args = ["a.roll", "b.roll"].map(String::from);
+        assert_eq!(
+            configure(&mut env, args.into_iter()),
+            Err("unexpected argument `b.roll`".to_string())
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use crate::log::{dice_json, roll_json};
+use parser::Parse;
+use rollang::{Dist, RollRecord, Val};
+use serde_json::{json, Value};
+use std::str::FromStr;
+use text_size::{TextRange, TextSize};
+
+// Bumped whenever a field is removed or changes meaning; adding fields doesn't bump it
+pub(crate) const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub(crate) enum OutputFormat {
+    // Values on stdout and rendered diagnostics on stderr, for people
+    #[default]
+    Text,
+    // One JSON object per line on stdout, diagnostics included, for tools
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("unknown format `{}` (expected text or json)", s)),
+        }
+    }
+}
+
+// What a statement evaluated to, with everything it printed and rolled on the way
+pub(crate) fn statement_json(
+    source: &str,
+    range: TextRange,
+    val: &Val,
+    output: &str,
+    rolls: &[RollRecord],
+) -> Value {
+    // Statements take the whitespace after them along, which isn't worth pointing at
+    let statement = &source[range];
+    let leading = statement.len() - statement.trim_start().len();
+    let range = TextRange::at(
+        range.start() + TextSize::of(&statement[..leading]),
+        TextSize::of(statement.trim()),
+    );
+
+    let text = match val {
+        Val::Unit => None,
+        val => Some(val.to_string()),
+    };
+
+    json!({
+        "version": SCHEMA_VERSION,
+        "kind": "value",
+        "statement": &source[range],
+        "span": crate::diagnostic::span_json(range, source),
+        "type": val.ty().to_string(),
+        "value": val_json(val),
+        "text": text,
+        "output": output,
+        "rolls": rolls.iter().map(roll_json).collect::<Vec<_>>(),
+    })
+}
+
+pub(crate) fn dist_json(dist: &Dist) -> Value {
+    let chances: Vec<_> = dist
+        .chances()
+        .map(|(total, chance)| json!({ "total": total, "chance": chance }))
+        .collect();
+
+    json!({
+        "version": SCHEMA_VERSION,
+        "kind": "dist",
+        "chances": chances,
+        "min": dist.min(),
+        "max": dist.max(),
+        "mean": dist.mean(),
+    })
+}
+
+pub(crate) fn ast_json(parse: &Parse) -> Value {
+    let ast = parse.to_json();
+
+    json!({
+        "version": SCHEMA_VERSION,
+        "kind": "ast",
+        "tree": ast["tree"],
+        "errors": ast["errors"],
+    })
+}
+
+// Values JSON has a type for are written as that type; dice formulas are written the way
+// they'd be typed
+fn val_json(val: &Val) -> Value {
+    match val {
+        Val::Unit => Value::Null,
+        Val::Bool(b) => json!(b),
+        Val::Number(n) => json!(n),
+        Val::Formula(formula) => json!(formula.to_string()),
+        Val::Roll(roll) => json!({
+            "total": roll.total,
+            "dice": dice_json(&roll.outcome),
+        }),
+        Val::Str(s) => json!(s),
+        Val::Vec(items) => items.iter().map(val_json).collect(),
+        Val::Comp(comp) => json!({
+            "name": comp.name,
+            "fields": comp
+                .fields
+                .iter()
+                .map(|(name, val)| json!({ "name": name, "value": val_json(val) }))
+                .collect::<Vec<_>>(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(input: &str) -> Val {
+        let mut env = rollang::Env::with_seed(1);
+        let root = ast::Root::cast(parser::parse(input).syntax()).unwrap();
+        rollang::eval(&root, &mut env).unwrap()
+    }
+
+    #[test]
+    fn json_of_roll() {
+        let Val::Roll(roll) = eval("roll 2d6 + 1") else {
+            panic!("`roll` should make a roll");
+        };
+        let json = val_json(&Val::Roll(roll.clone()));
+
+        assert_eq!(json["total"], roll.total);
+        assert_eq!(json["dice"][0]["dice"], "2d6");
+        assert_eq!(json["dice"][0]["faces"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn json_of_comp() {
+        assert_eq!(
+            val_json(&eval("Orc :: comp(hp: 15, name: \"orc\")\nOrc")),
+            json!({
+                "name": "Orc",
+                "fields": [
+                    { "name": "hp", "value": 15 },
+                    { "name": "name", "value": "orc" },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn json_of_statement() {
+        let source = "x := 1d4\nx\n";
+        let range = TextRange::new(9.into(), 11.into());
+        let json = statement_json(source, range, &eval(source), "", &[]);
+
+        assert_eq!(json["kind"], "value");
+        assert_eq!(json["statement"], "x");
+        assert_eq!(json["span"]["end"], 10);
+        assert_eq!(json["type"], "dice");
+        assert_eq!(json["value"], "1d4");
+        assert_eq!(json["span"]["line"], 2);
+    }
+
+    #[test]
+    fn json_of_ast() {
+        let json = ast_json(&parser::parse("roll d6"));
+
+        assert_eq!(json["kind"], "ast");
+        assert_eq!(json["tree"]["kind"], "Root");
+        assert_eq!(json["tree"]["children"][0]["kind"], "RollExpr");
+        assert_eq!(json["tree"]["children"][0]["end"], 7);
+        assert_eq!(json["errors"], json!([]));
+    }
+}
@@ -0,0 +1,16 @@
+use crate::formula::{Formula, Roll};
+
+// A roll as it's kept for the session log
+#[derive(Debug, Clone, PartialEq)]
+pub struct RollRecord {
+    // The seed the session's rolls come from, and how many rolls were recorded before this one
+    // since it was set
+    pub seed: u64,
+    pub seq: u64,
+    // The binding, field or combatant the roll belongs to
+    pub binding: Option<String>,
+    // What the roll's own annotations say it's for, like `Fire` in `1d20:"Fire"`
+    pub annotation: Option<String>,
+    pub formula: Formula,
+    pub roll: Roll,
+}
@@ -0,0 +1,488 @@
+use crate::diagnostic::{self, Diagnostic};
+use crate::log::Recorder;
+use crate::output::OutputFormat;
+use parser::{parse, Parse};
+use rollang::RollRecord;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Editor, Helper};
+use std::borrow::Cow;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use syntax::SyntaxKind;
+use text_size::{TextRange, TextSize};
+
+const PROMPT: &str = ">>> ";
+const CONTINUATION_PROMPT: &str = "... ";
+
+pub(crate) fn run(env: &mut rollang::Env, recorder: &mut Recorder) -> rustyline::Result<()> {
+    let mut editor: Editor<Echo, DefaultHistory> = Editor::new()?;
+    editor.set_helper(Some(Echo {
+        colour: io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+    }));
+    let history = history_path();
+    if let Some(path) = &history {
+        // There's no history yet on the first run
+        let _ = editor.load_history(path);
+    }
+
+    println!("Rollang v0.1.0");
+
+    let mut stdout = io::stdout();
+    let mut stderr = io::stderr();
+    let mut input = String::new();
+
+    loop {
+        let prompt = if input.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            // Ctrl-C throws away whatever has been typed so far, like a shell
+            Err(ReadlineError::Interrupted) => {
+                input.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(err),
+        };
+
+        if input.is_empty() && is_exit(&line) {
+            break;
+        }
+
+        input.push_str(&line);
+        input.push('\n');
+
+        // Commands are always a single line
+        if !input.starts_with(':') && is_incomplete(&parse(&input)) {
+            continue;
+        }
+
+        let entry = input.trim();
+        if !entry.is_empty() {
+            editor.add_history_entry(entry)?;
+
+            let reply = respond(entry, env, recorder)?;
+            for warning in &reply.warnings {
+                writeln!(stderr, "{}", warning)?;
+            }
+            write!(stdout, "{}", reply.output)?;
+            if let Some(msg) = reply.error {
+                writeln!(stderr, "{}", msg)?;
+            }
+        }
+
+        input.clear();
+    }
+
+    if let Some(path) = &history {
+        editor.save_history(path)?;
+    }
+
+    Ok(())
+}
+
+// Colours code as it's typed, the same way `rollang highlight` does. Commands are left alone.
+struct Echo {
+    colour: bool,
+}
+
+impl Highlighter for Echo {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        if !self.colour || line.starts_with(':') {
+            return Cow::Borrowed(line);
+        }
+        let root = ast::Root::cast(parse(line).syntax()).unwrap();
+        Cow::Owned(rollang::highlight_ansi(&root))
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize, _forced: bool) -> bool {
+        self.colour
+    }
+}
+
+impl Completer for Echo {
+    type Candidate = String;
+}
+
+impl Hinter for Echo {
+    type Hint = String;
+}
+
+impl Validator for Echo {}
+
+impl Helper for Echo {}
+
+fn history_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".rollang_history"))
+}
+
+// `end` closes a session in the spec; `exit` is what everyone types first anyway
+fn is_exit(line: &str) -> bool {
+    matches!(line.trim(), "exit" | "end")
+}
+
+// Input that ran out while a bracket was still open is unfinished rather than wrong, so the REPL
+// asks for another line instead of reporting the error
+fn is_incomplete(parse: &Parse) -> bool {
+    parse.errors().iter().any(|error| {
+        error.found.is_none()
+            && error.expected.iter().any(|kind| {
+                matches!(
+                    kind,
+                    SyntaxKind::RParen | SyntaxKind::RBrace | SyntaxKind::RBracket
+                )
+            })
+    })
+}
+
+// What the REPL shows for an input, and the rolls the input made
+pub(crate) struct Reply {
+    pub(crate) output: String,
+    pub(crate) error: Option<String>,
+    pub(crate) warnings: Vec<String>,
+    pub(crate) rolls: Vec<RollRecord>,
+}
+
+// Runs one complete input, whether it's code or a `:command`. Replaying a session goes through
+// here too, so that it runs inputs exactly the way they were run the first time.
+pub(crate) fn respond(
+    input: &str,
+    env: &mut rollang::Env,
+    recorder: &mut Recorder,
+) -> io::Result<Reply> {
+    let seed = env.seed();
+
+    let mut warnings = Vec::new();
+
+    let (output, error) = if input.starts_with(':') {
+        match command(input, env, recorder) {
+            Ok(output) => (output, None),
+            Err(msg) => (String::new(), Some(msg)),
+        }
+    } else {
+        let parse = parse(input);
+        warnings = lint(&parse, env);
+        let result = eval(&parse, env);
+        let mut output = env.take_output();
+        recorder.take_rolls(input, env)?;
+
+        match result {
+            Ok(Some(val)) => {
+                output += &format!("{}\n", val);
+                (output, None)
+            }
+            Ok(None) => (output, None),
+            Err(msg) => (output, Some(msg)),
+        }
+    };
+
+    let reseed = Some(env.seed()).filter(|&reseed| reseed != seed);
+    let rolls = recorder.finish_input(input, reseed)?;
+
+    Ok(Reply {
+        output,
+        error,
+        warnings,
+        rolls,
+    })
+}
+
+// Bindings are there for later inputs to use, so an unused one isn't worth a warning here
+fn lint(parse: &Parse, env: &rollang::Env) -> Vec<String> {
+    if !parse.errors().is_empty() {
+        return Vec::new();
+    }
+
+    let source = parse.syntax().text().to_string();
+    let root = ast::Root::cast(parse.syntax()).unwrap();
+    rollang::lint(&root, env)
+        .iter()
+        .filter(|lint| lint.name != "unused-binding")
+        .map(|lint| render(Diagnostic::from_lint(lint), &source))
+        .collect()
+}
+
+fn eval(parse: &Parse, env: &mut rollang::Env) -> Result<Option<rollang::Val>, String> {
+    let source = parse.syntax().text().to_string();
+    if let Some(error) = parse.errors().first() {
+        return Err(render(
+            Diagnostic::from_parse_error(error, &source),
+            &source,
+        ));
+    }
+
+    let root = ast::Root::cast(parse.syntax()).unwrap();
+    let evaluated = rollang::eval(&root, env)
+        .map_err(|error| render(Diagnostic::from_eval_error(&error), &source))?;
+
+    if evaluated == rollang::Val::Unit {
+        Ok(None)
+    } else {
+        Ok(Some(evaluated))
+    }
+}
+
+const HELP: &str = "\
+:tokens <code>   show what the lexer makes of <code>
+:tree <code>     show the syntax tree of <code>
+:type <expr>     show the type of <expr> without rolling anything
+:dist <expr>     show the chance of every total <expr> can roll
+:env             list bindings, functions and modifiers
+:reset           forget everything defined this session
+:seed <n>        restart the rolls from seed <n>
+:load <file>     run a .roll file in this session
+:help            show this list
+";
+
+// Runs a `:command`, returning what it prints
+fn command(line: &str, env: &mut rollang::Env, recorder: &mut Recorder) -> Result<String, String> {
+    let (name, arg) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let arg = arg.trim();
+
+    match name {
+        ":tokens" => Ok(tokens(arg)),
+        ":tree" => Ok(format!("{}\n", parse(arg).debug_tree())),
+        ":type" => Ok(format!(
+            "{}\n",
+            peek(arg, env)
+                .map_err(|diagnostic| render(diagnostic, arg))?
+                .ty()
+        )),
+        ":dist" => Ok(format!(
+            "{}\n",
+            dist(arg, env).map_err(|diagnostic| render(diagnostic, arg))?
+        )),
+        ":env" => Ok(describe_env(env)),
+        ":reset" => {
+            env.reset();
+            Ok(String::new())
+        }
+        ":seed" => {
+            let seed = arg
+                .parse()
+                .map_err(|_| format!("`{}` is not a seed; use a whole number", arg))?;
+            env.set_seed(seed);
+            Ok(String::new())
+        }
+        ":load" if arg.is_empty() => Err(":load needs a file".to_string()),
+        ":load" => {
+            crate::run_script(arg, env, recorder, OutputFormat::Text)
+                .map_err(|err| err.to_string())?;
+            Ok(String::new())
+        }
+        ":help" => Ok(HELP.to_string()),
+        _ => Err(format!(
+            "unknown command `{}`; type `:help` for a list",
+            name
+        )),
+    }
+}
+
+// Every token with its range, one per line
+pub(crate) fn tokens(input: &str) -> String {
+    lexer::Lexer::new(input)
+        .map(|token| match token.kind {
+            Ok(kind) => format!("{:?}@{:?} {:?}\n", kind, token.range, token.text),
+            Err(()) => format!("error@{:?} {:?}\n", token.range, token.text),
+        })
+        .collect()
+}
+
+pub(crate) fn dist(input: &str, env: &rollang::Env) -> Result<rollang::Dist, Diagnostic> {
+    let whole = TextRange::up_to(TextSize::of(input.trim_end()));
+    let no_dist = |msg: String| Diagnostic::error(msg, whole, "").with_code("no-distribution");
+
+    let formula = match peek(input, env)? {
+        rollang::Val::Formula(formula) => formula,
+        rollang::Val::Number(n) => rollang::Formula::Number(n),
+        rollang::Val::Roll(_) => return Err(no_dist(format!("`{}` is already rolled", input))),
+        val => return Err(no_dist(format!("a {} has no distribution", val.ty()))),
+    };
+    rollang::Dist::of(&formula).map_err(no_dist)
+}
+
+// Evaluates `input` in a copy of the environment, so that looking doesn't roll any dice or
+// change any bindings
+fn peek(input: &str, env: &rollang::Env) -> Result<rollang::Val, Diagnostic> {
+    let parse = parse(input);
+    if let Some(error) = parse.errors().first() {
+        return Err(Diagnostic::from_parse_error(error, input));
+    }
+
+    let root = ast::Root::cast(parse.syntax()).unwrap();
+    rollang::eval(&root, &mut env.clone()).map_err(|error| Diagnostic::from_eval_error(&error))
+}
+
+// The input is right above the error, so there's no need for a location
+fn render(diagnostic: Diagnostic, source: &str) -> String {
+    diagnostic
+        .render(None, source, diagnostic::use_colour())
+        .trim_end()
+        .to_string()
+}
+
+fn describe_env(env: &rollang::Env) -> String {
+    let mut description = String::new();
+
+    for (name, val) in env.bindings() {
+        match val {
+            // Composites are whole tables, which would drown out everything else
+            rollang::Val::Comp(_) => description += &format!("{}: {}\n", name, val.ty()),
+            _ => description += &format!("{}: {} = {}\n", name, val.ty(), val),
+        }
+    }
+
+    for (name, signature) in env.functions() {
+        description += &format!("{} :: fn{}\n", name, signature);
+    }
+
+    for (name, signature) in env.modifiers() {
+        description += &format!("{} :: mod{}\n", name, signature.params_to_string());
+    }
+
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_incomplete(input: &str, expected: bool) {
+        assert_eq!(is_incomplete(&parse(input)), expected);
+    }
+
+    #[test]
+    fn complete_expression() {
+        check_incomplete("1d20 + 5\n", false);
+    }
+
+    #[test]
+    fn unclosed_paren() {
+        check_incomplete("x := (2 +\n", true);
+    }
+
+    #[test]
+    fn unclosed_function_body() {
+        check_incomplete("f :: fn(x: number) {\n  return x\n", true);
+    }
+
+    #[test]
+    fn unclosed_array() {
+        check_incomplete("[1, 2,\n", true);
+    }
+
+    #[test]
+    fn error_without_open_bracket() {
+        check_incomplete("1 +\n", false);
+    }
+
+    fn check_command(line: &str, env: &mut rollang::Env, expected: Result<&str, &str>) {
+        assert_eq!(
+            command(line, env, &mut Recorder::default()),
+            expected.map(str::to_string).map_err(str::to_string)
+        );
+    }
+
+    #[test]
+    fn type_does_not_roll() {
+        let mut env = rollang::Env::with_seed(1);
+        check_command(":type roll 1d20", &mut env, Ok("roll\n"));
+
+        let mut unrolled = rollang::Env::with_seed(1);
+        assert_eq!(
+            peek("roll 1d20", &env).unwrap(),
+            peek("roll 1d20", &unrolled).unwrap()
+        );
+        check_command(":type 1d20 + 2", &mut unrolled, Ok("dice\n"));
+    }
+
+    #[test]
+    fn dist_of_number() {
+        let mut env = rollang::Env::with_seed(1);
+        check_command(
+            ":dist 4",
+            &mut env,
+            Ok("4 100.00% ########################################\nmean 4.00\n"),
+        );
+    }
+
+    #[test]
+    fn dist_of_str() {
+        let mut env = rollang::Env::with_seed(1);
+        check_command(
+            ":dist \"sword\"",
+            &mut env,
+            Err("error: a str has no distribution\n  |\n1 | \"sword\"\n  | ^^^^^^^"),
+        );
+    }
+
+    #[test]
+    fn env_lists_user_definitions() {
+        let mut env = rollang::Env::with_seed(1);
+        rollang::eval(
+            &ast::Root::cast(parse("bonus :: fn(x: number) -> number { x + 2 }").syntax()).unwrap(),
+            &mut env,
+        )
+        .unwrap();
+
+        let listing = command(":env", &mut env, &mut Recorder::default()).unwrap();
+        assert!(listing.contains("pass: bool = pass\n"));
+        assert!(listing.contains("bonus :: fn(number) -> number\n"));
+    }
+
+    #[test]
+    fn reset_forgets_bindings() {
+        let mut env = rollang::Env::with_seed(1);
+        rollang::eval(
+            &ast::Root::cast(parse("x := 1").syntax()).unwrap(),
+            &mut env,
+        )
+        .unwrap();
+        let unreset = env.clone();
+        check_command(":reset", &mut env, Ok(""));
+        assert!(peek("x", &env).is_err());
+
+        // The dice carry on from where they were
+        assert_eq!(env.seed(), 1);
+        assert_eq!(
+            peek("roll 4d20", &env).unwrap(),
+            peek("roll 4d20", &unreset).unwrap()
+        );
+    }
+
+    #[test]
+    fn bad_seed() {
+        let mut env = rollang::Env::with_seed(1);
+        check_command(
+            ":seed d20",
+            &mut env,
+            Err("`d20` is not a seed; use a whole number"),
+        );
+    }
+
+    #[test]
+    fn unknown_command() {
+        let mut env = rollang::Env::with_seed(1);
+        check_command(
+            ":roll",
+            &mut env,
+            Err("unknown command `:roll`; type `:help` for a list"),
+        );
+    }
+
+    #[test]
+    fn exit_commands() {
+        assert!(is_exit("exit"));
+        assert!(is_exit("  end "));
+        assert!(!is_exit("ending"));
+    }
+}
@@ -0,0 +1,84 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// SplitMix64. We use our own generator rather than pulling in a crate so that a seed always
+// produces the same sequence of rolls, no matter which version of a dependency is in use.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn from_entropy() -> Self {
+        Self::new(Self::entropy_seed())
+    }
+
+    // A seed that differs from one run to the next
+    pub fn entropy_seed() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Returns a face between 1 and `sides` (inclusive), without modulo bias
+    pub fn roll_die(&mut self, sides: u32) -> u32 {
+        assert!(sides > 0);
+
+        let sides = u64::from(sides);
+        let zone = u64::MAX - (u64::MAX % sides);
+
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return (x % sides) as u32 + 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_rolls() {
+        let mut a = Rng::new(1234);
+        let mut b = Rng::new(1234);
+
+        for _ in 0..100 {
+            assert_eq!(a.roll_die(20), b.roll_die(20));
+        }
+    }
+
+    #[test]
+    fn faces_are_in_range() {
+        let mut rng = Rng::new(7);
+        let mut seen = [false; 6];
+
+        for _ in 0..1000 {
+            let face = rng.roll_die(6);
+            assert!((1..=6).contains(&face));
+            seen[face as usize - 1] = true;
+        }
+
+        assert!(seen.iter().all(|&s| s));
+    }
+
+    #[test]
+    fn one_sided_die_always_rolls_one() {
+        let mut rng = Rng::new(99);
+        assert!((0..50).all(|_| rng.roll_die(1) == 1));
+    }
+}
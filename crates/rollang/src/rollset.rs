@@ -0,0 +1,133 @@
+use crate::comp::Comp;
+use crate::formula::{Dice, Formula, Select};
+use crate::val::Val;
+
+pub(crate) const ABILITIES: [&str; 6] = ["STR", "DEX", "CON", "INT", "WIS", "CHA"];
+
+// The standard array from the Player's Handbook, assigned to abilities in any order
+const STANDARD_ARRAY: [i64; 6] = [15, 14, 13, 12, 10, 8];
+
+const POINT_BUY_BUDGET: i64 = 27;
+
+// The rollsets every environment starts out with: `dnd`, a rollset of the usual ways to
+// generate ability scores, and `Abilities`, the one most tables use
+pub(crate) fn builtin_rollsets() -> Vec<(String, Val)> {
+    let dnd = Comp {
+        name: "dnd".to_string(),
+        fields: vec![
+            (
+                "Ability Scores".to_string(),
+                abilities("Ability Scores", four_drop_lowest()),
+            ),
+            (
+                "In Order".to_string(),
+                abilities("In Order", Dice::new(3, 6)),
+            ),
+            (
+                "Standard Array".to_string(),
+                Val::Vec(STANDARD_ARRAY.into_iter().map(Val::Number).collect()),
+            ),
+        ],
+    };
+
+    vec![
+        ("dnd".to_string(), Val::Comp(dnd)),
+        (
+            "Abilities".to_string(),
+            abilities("Abilities", four_drop_lowest()),
+        ),
+    ]
+}
+
+// Roll 4d6 and drop the lowest die
+fn four_drop_lowest() -> Dice {
+    Dice::new(4, 6).with_select(Select::DropLowest(1))
+}
+
+fn abilities(name: &str, dice: Dice) -> Val {
+    Val::Comp(Comp {
+        name: name.to_string(),
+        fields: ABILITIES
+            .iter()
+            .map(|ability| (ability.to_string(), Val::Formula(Formula::Dice(dice))))
+            .collect(),
+    })
+}
+
+// How many points a set of ability scores costs under the point buy rules, or why it isn't allowed
+pub(crate) fn point_buy_cost(scores: &[(String, i64)]) -> Result<i64, String> {
+    if scores.len() != ABILITIES.len() {
+        return Err(format!(
+            "point buy needs {} scores, but was given {}",
+            ABILITIES.len(),
+            scores.len()
+        ));
+    }
+
+    let mut total = 0;
+    for (name, score) in scores {
+        total += match score {
+            8..=13 => score - 8,
+            14 => 7,
+            15 => 9,
+            _ => {
+                return Err(format!(
+                    "{} is {}, but point buy scores must be between 8 and 15",
+                    name, score
+                ))
+            }
+        };
+    }
+
+    if total > POINT_BUY_BUDGET {
+        return Err(format!(
+            "these scores cost {} points, but point buy only has {}",
+            total, POINT_BUY_BUDGET
+        ));
+    }
+
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scores(scores: [i64; 6]) -> Vec<(String, i64)> {
+        ABILITIES
+            .iter()
+            .map(|ability| ability.to_string())
+            .zip(scores)
+            .collect()
+    }
+
+    #[test]
+    fn standard_array_costs_the_whole_budget() {
+        assert_eq!(point_buy_cost(&scores(STANDARD_ARRAY)), Ok(27));
+    }
+
+    #[test]
+    fn point_buy_over_budget() {
+        assert_eq!(point_buy_cost(&scores([15, 15, 15, 8, 8, 8])), Ok(27));
+        assert_eq!(
+            point_buy_cost(&scores([15, 15, 15, 9, 8, 8])),
+            Err("these scores cost 28 points, but point buy only has 27".to_string())
+        );
+    }
+
+    #[test]
+    fn point_buy_score_out_of_range() {
+        assert_eq!(
+            point_buy_cost(&scores([16, 8, 8, 8, 8, 8])),
+            Err("STR is 16, but point buy scores must be between 8 and 15".to_string())
+        );
+    }
+
+    #[test]
+    fn abilities_drop_the_lowest_die() {
+        let Val::Comp(abilities) = abilities("Abilities", four_drop_lowest()) else {
+            panic!("expected a composite");
+        };
+        assert_eq!(abilities.field("CHA").unwrap().to_string(), "4d6dl1");
+    }
+}
@@ -0,0 +1,309 @@
+use crate::log::Recorder;
+use crate::repl;
+use rollang::RollRecord;
+use serde_json::{json, Value};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+
+// A recorded session is a JSON Lines file: a header with everything the environment started out
+// with, then every input in the order it was given along with the rolls it made
+pub(crate) struct SessionWriter {
+    file: File,
+}
+
+impl SessionWriter {
+    pub(crate) fn create(path: &str, env: &rollang::Env) -> io::Result<Self> {
+        let mut file = File::create(path)?;
+        writeln!(file, "{}", header(env))?;
+
+        Ok(Self { file })
+    }
+
+    pub(crate) fn write(
+        &mut self,
+        input: &str,
+        rolls: &[RollRecord],
+        reseed: Option<u64>,
+    ) -> io::Result<()> {
+        writeln!(self.file, "{}", entry(input, rolls, reseed))
+    }
+}
+
+fn header(env: &rollang::Env) -> Value {
+    json!({
+        "rollang": env!("CARGO_PKG_VERSION"),
+        "seed": env.seed(),
+        "crit": env.crit_rule().to_string(),
+    })
+}
+
+// `reseed` is the seed the input left behind, if it changed it, as `:seed` does
+fn entry(input: &str, rolls: &[RollRecord], reseed: Option<u64>) -> Value {
+    let rolls: Vec<_> = rolls
+        .iter()
+        .map(|record| SessionRoll::from(record).to_json())
+        .collect();
+
+    let mut entry = json!({ "input": input, "rolls": rolls });
+    if let Some(seed) = reseed {
+        entry["seed"] = json!(seed);
+    }
+    entry
+}
+
+// What's checked of every roll on replay
+#[derive(Debug, Clone, PartialEq)]
+struct SessionRoll {
+    formula: String,
+    faces: Vec<Vec<u32>>,
+    total: i64,
+}
+
+impl From<&RollRecord> for SessionRoll {
+    fn from(record: &RollRecord) -> Self {
+        Self {
+            formula: record.formula.to_string(),
+            faces: record
+                .roll
+                .outcome
+                .dice()
+                .into_iter()
+                .map(|(_, faces)| faces.to_vec())
+                .collect(),
+            total: record.roll.total,
+        }
+    }
+}
+
+impl SessionRoll {
+    fn to_json(&self) -> Value {
+        json!({ "formula": self.formula, "faces": self.faces, "total": self.total })
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            formula: value["formula"].as_str()?.to_string(),
+            faces: serde_json::from_value(value["faces"].clone()).ok()?,
+            total: value["total"].as_i64()?,
+        })
+    }
+}
+
+// `4d6dl1 [[2, 1, 1, 5]] ->8`
+impl fmt::Display for SessionRoll {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:?} ->{}", self.formula, self.faces, self.total)
+    }
+}
+
+struct Entry {
+    input: String,
+    rolls: Vec<SessionRoll>,
+    reseed: Option<u64>,
+}
+
+struct Session {
+    seed: u64,
+    crit_rule: rollang::CritRule,
+    entries: Vec<Entry>,
+}
+
+impl Session {
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+
+        let (_, header) = lines.next().ok_or("the session is empty")?;
+        let header: Value =
+            serde_json::from_str(header).map_err(|err| format!("line 1: {}", err))?;
+        let seed = header["seed"]
+            .as_u64()
+            .ok_or("line 1: the header has no seed")?;
+        let crit_rule = header["crit"]
+            .as_str()
+            .ok_or("line 1: the header has no crit rule")?
+            .parse()
+            .map_err(|err| format!("line 1: {}", err))?;
+
+        let entries = lines
+            .map(|(idx, line)| {
+                let bad_entry = || format!("line {}: not a session entry", idx + 1);
+                let entry: Value = serde_json::from_str(line)
+                    .map_err(|err| format!("line {}: {}", idx + 1, err))?;
+
+                Ok(Entry {
+                    input: entry["input"].as_str().ok_or_else(bad_entry)?.to_string(),
+                    rolls: entry["rolls"]
+                        .as_array()
+                        .ok_or_else(bad_entry)?
+                        .iter()
+                        .map(SessionRoll::from_json)
+                        .collect::<Option<_>>()
+                        .ok_or_else(bad_entry)?,
+                    reseed: entry["seed"].as_u64(),
+                })
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(Self {
+            seed,
+            crit_rule,
+            entries,
+        })
+    }
+}
+
+// Where a replay first rolled something other than what was recorded
+#[derive(Debug, PartialEq)]
+pub(crate) struct Divergence {
+    input_number: usize,
+    input: String,
+    roll_number: usize,
+    recorded: Option<String>,
+    replayed: Option<String>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "input {} (`{}`) diverged at roll {}: ",
+            self.input_number, self.input, self.roll_number
+        )?;
+
+        match (&self.recorded, &self.replayed) {
+            (Some(recorded), Some(replayed)) => {
+                write!(f, "recorded {}, but replayed {}", recorded, replayed)
+            }
+            (Some(recorded), None) => {
+                write!(f, "recorded {}, but replay made no such roll", recorded)
+            }
+            (None, Some(replayed)) => {
+                write!(f, "replay rolled {}, which wasn't recorded", replayed)
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum ReplayResult {
+    // How many inputs and rolls were replayed
+    Matched(usize, usize),
+    Diverged(Divergence),
+}
+
+// Runs every input through the REPL again, starting from the recorded seed, and compares the
+// rolls it makes with the recorded ones
+pub(crate) fn replay(text: &str) -> Result<ReplayResult, String> {
+    let session = Session::parse(text)?;
+
+    let mut env = rollang::Env::with_seed(session.seed);
+    env.set_crit_rule(session.crit_rule);
+    let mut recorder = Recorder::default();
+    let mut roll_count = 0;
+
+    for (idx, entry) in session.entries.iter().enumerate() {
+        let reply =
+            repl::respond(&entry.input, &mut env, &mut recorder).map_err(|err| err.to_string())?;
+        let replayed: Vec<_> = reply.rolls.iter().map(SessionRoll::from).collect();
+
+        let mismatch = (0..entry.rolls.len().max(replayed.len()))
+            .find(|&i| entry.rolls.get(i) != replayed.get(i));
+        if let Some(i) = mismatch {
+            return Ok(ReplayResult::Diverged(Divergence {
+                input_number: idx + 1,
+                input: entry.input.clone(),
+                roll_number: i + 1,
+                recorded: entry.rolls.get(i).map(ToString::to_string),
+                replayed: replayed.get(i).map(ToString::to_string),
+            }));
+        }
+
+        if let Some(seed) = entry.reseed {
+            env.set_seed(seed);
+        }
+        roll_count += replayed.len();
+    }
+
+    Ok(ReplayResult::Matched(session.entries.len(), roll_count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(seed: u64, inputs: &[&str]) -> String {
+        let mut env = rollang::Env::with_seed(seed);
+        let mut recorder = Recorder::default();
+        let mut text = header(&env).to_string();
+
+        for input in inputs {
+            let before = env.seed();
+            let reply = repl::respond(input, &mut env, &mut recorder).unwrap();
+            let reseed = Some(env.seed()).filter(|&seed| seed != before);
+            text += &format!("\n{}", entry(input, &reply.rolls, reseed));
+        }
+
+        text
+    }
+
+    #[test]
+    fn replay_matches_recording() {
+        let session = record(
+            7,
+            &[
+                "Orc :: comp(hit: 1d20 + 5, damage: 1d12 + 3)",
+                "roll Orc",
+                "roll 4d6dl1",
+                ":reset",
+                "roll 1d20",
+            ],
+        );
+
+        assert_eq!(replay(&session), Ok(ReplayResult::Matched(5, 4)));
+    }
+
+    #[test]
+    fn replay_reports_first_divergence() {
+        let session = record(7, &["roll 1d20", "roll 2d6"]).replace("\"total\":", "\"total\":1");
+
+        let ReplayResult::Diverged(divergence) = replay(&session).unwrap() else {
+            panic!("the replay should have diverged");
+        };
+        assert_eq!((divergence.input_number, divergence.roll_number), (1, 1));
+        assert!(divergence
+            .to_string()
+            .starts_with("input 1 (`roll 1d20`) diverged at roll 1: recorded 1d20 "));
+    }
+
+    #[test]
+    fn replay_reports_missing_roll() {
+        let session = record(7, &["roll 1d20"]).replace("roll 1d20", "1d20");
+
+        assert_eq!(
+            replay(&session).unwrap(),
+            ReplayResult::Diverged(Divergence {
+                input_number: 1,
+                input: "1d20".to_string(),
+                roll_number: 1,
+                recorded: Some(replay_roll(7)),
+                replayed: None,
+            })
+        );
+    }
+
+    fn replay_roll(seed: u64) -> String {
+        let mut env = rollang::Env::with_seed(seed);
+        let reply = repl::respond("roll 1d20", &mut env, &mut Recorder::default()).unwrap();
+        SessionRoll::from(&reply.rolls[0]).to_string()
+    }
+
+    #[test]
+    fn replay_of_empty_file() {
+        assert_eq!(replay(""), Err("the session is empty".to_string()));
+    }
+}
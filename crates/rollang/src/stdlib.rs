@@ -0,0 +1,691 @@
+use crate::env::Env;
+use crate::formula::InfixOp;
+use crate::init::{Entry, TurnOrder};
+use crate::rollset;
+use crate::ty::Ty;
+use crate::val::Val;
+use std::fmt;
+
+// A function implemented in Rust. Arguments are checked against `signatures` (and coerced to
+// the matching parameter types) before `func` runs, so `func` can rely on their shape.
+pub struct Builtin {
+    pub name: &'static str,
+    pub doc: &'static str,
+    signatures: fn() -> Vec<Signature>,
+    func: fn(&mut Env, Vec<Val>) -> Result<Val, String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    pub params: Vec<Ty>,
+    pub ret: Ty,
+}
+
+impl Signature {
+    pub(crate) fn new(params: Vec<Ty>, ret: Ty) -> Self {
+        Self { params, ret }
+    }
+
+    // Just the parameters, e.g. `(number, dice)`
+    pub fn params_to_string(&self) -> String {
+        let params: Vec<_> = self.params.iter().map(ToString::to_string).collect();
+        format!("({})", params.join(", "))
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}", self.params_to_string(), self.ret)
+    }
+}
+
+impl Builtin {
+    pub fn signatures(&self) -> Vec<Signature> {
+        (self.signatures)()
+    }
+
+    pub fn call(&self, env: &mut Env, args: Vec<Val>) -> Result<Val, String> {
+        let args = check_args(self.name, &self.signatures(), args)?;
+        (self.func)(env, args)
+    }
+}
+
+// Finds the first signature the arguments fit and returns them coerced to its parameter types
+pub(crate) fn check_args(
+    name: &str,
+    signatures: &[Signature],
+    args: Vec<Val>,
+) -> Result<Vec<Val>, String> {
+    if let Some(error) = arity_error(name, signatures, args.len()) {
+        return Err(error);
+    }
+
+    for sig in signatures
+        .iter()
+        .filter(|sig| sig.params.len() == args.len())
+    {
+        let coerced: Option<Vec<_>> = args
+            .iter()
+            .zip(&sig.params)
+            .map(|(arg, ty)| arg.clone().coerce(ty))
+            .collect();
+
+        if let Some(args) = coerced {
+            return Ok(args);
+        }
+    }
+
+    let given: Vec<_> = args.iter().map(Val::ty).collect();
+    Err(mismatch_error(name, signatures, &given))
+}
+
+// The error for `count` arguments when no signature takes that many
+pub(crate) fn arity_error(name: &str, signatures: &[Signature], count: usize) -> Option<String> {
+    if signatures.iter().any(|sig| sig.params.len() == count) {
+        return None;
+    }
+
+    let mut arities: Vec<_> = signatures.iter().map(|sig| sig.params.len()).collect();
+    arities.sort_unstable();
+    arities.dedup();
+    let arities: Vec<_> = arities.iter().map(ToString::to_string).collect();
+
+    Some(format!(
+        "`{}` takes {} argument{} but {} {} given",
+        name,
+        arities.join(" or "),
+        if arities == ["1"] { "" } else { "s" },
+        count,
+        if count == 1 { "was" } else { "were" },
+    ))
+}
+
+// The error for arguments of types that no signature takes
+pub(crate) fn mismatch_error(name: &str, signatures: &[Signature], given: &[Ty]) -> String {
+    let given: Vec<_> = given.iter().map(ToString::to_string).collect();
+    let expected: Vec<_> = signatures.iter().map(Signature::params_to_string).collect();
+
+    format!(
+        "`{}` expects {}, but was given ({})",
+        name,
+        expected.join(" or "),
+        given.join(", "),
+    )
+}
+
+pub fn builtin(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+pub fn builtins() -> &'static [Builtin] {
+    BUILTINS
+}
+
+static BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "abs",
+        doc: "absolute value of a number",
+        signatures: || vec![Signature::new(vec![Ty::Number], Ty::Number)],
+        func: |_, args| {
+            let n = number(&args[0]);
+            n.checked_abs()
+                .map(Val::Number)
+                .ok_or_else(|| "number is too large".to_string())
+        },
+    },
+    Builtin {
+        name: "max",
+        doc: "the larger of two numbers, or the largest number in a vector",
+        signatures: extreme_signatures,
+        func: |_, args| extreme("max", args, Iterator::max),
+    },
+    Builtin {
+        name: "min",
+        doc: "the smaller of two numbers, or the smallest number in a vector",
+        signatures: extreme_signatures,
+        func: |_, args| extreme("min", args, Iterator::min),
+    },
+    Builtin {
+        name: "ceildiv",
+        doc: "division that rounds up instead of down",
+        signatures: || vec![Signature::new(vec![Ty::Number, Ty::Number], Ty::Number)],
+        func: |_, args| {
+            let (lhs, rhs) = (number(&args[0]), number(&args[1]));
+            // -(-a / b) rounds up, since `/` rounds down
+            let negated = lhs.checked_neg().ok_or("number is too large")?;
+            let quotient = InfixOp::Div
+                .apply(negated, rhs)
+                .map_err(|e| e.to_string())?;
+            Ok(Val::Number(-quotient))
+        },
+    },
+    Builtin {
+        name: "len",
+        doc: "number of characters in a string, or items in a vector",
+        signatures: || {
+            vec![
+                Signature::new(vec![Ty::Str], Ty::Number),
+                Signature::new(vec![Ty::vec(Ty::Any)], Ty::Number),
+            ]
+        },
+        func: |_, args| {
+            let len = match &args[0] {
+                Val::Str(s) => s.chars().count(),
+                Val::Vec(items) => items.len(),
+                _ => unreachable!(),
+            };
+            Ok(Val::Number(len as i64))
+        },
+    },
+    Builtin {
+        name: "sum",
+        doc: "sum of a vector of numbers",
+        signatures: || vec![Signature::new(vec![Ty::vec(Ty::Number)], Ty::Number)],
+        func: |_, args| {
+            numbers(&args[0])
+                .try_fold(0i64, |acc, n| acc.checked_add(n))
+                .map(Val::Number)
+                .ok_or_else(|| "number is too large".to_string())
+        },
+    },
+    Builtin {
+        name: "sort",
+        doc: "a vector of numbers in ascending order",
+        signatures: || {
+            vec![Signature::new(
+                vec![Ty::vec(Ty::Number)],
+                Ty::vec(Ty::Number),
+            )]
+        },
+        func: |_, args| {
+            let mut sorted: Vec<_> = numbers(&args[0]).collect();
+            sorted.sort_unstable();
+            Ok(Val::Vec(sorted.into_iter().map(Val::Number).collect()))
+        },
+    },
+    Builtin {
+        name: "count",
+        doc: "how many times a number appears in a vector, e.g. `count(roll 8d6, 6)`",
+        signatures: || {
+            vec![Signature::new(
+                vec![Ty::vec(Ty::Number), Ty::Number],
+                Ty::Number,
+            )]
+        },
+        func: |_, args| {
+            let needle = number(&args[1]);
+            let count = numbers(&args[0]).filter(|&n| n == needle).count();
+            Ok(Val::Number(count as i64))
+        },
+    },
+    Builtin {
+        name: "mod",
+        doc: "ability modifier for an ability score, e.g. `mod(15)` is 2",
+        signatures: || vec![Signature::new(vec![Ty::Number], Ty::Number)],
+        func: |_, args| {
+            let score = number(&args[0]);
+            let offset = score.checked_sub(10).ok_or("number is too large")?;
+            InfixOp::Div
+                .apply(offset, 2)
+                .map(Val::Number)
+                .map_err(|e| e.to_string())
+        },
+    },
+    Builtin {
+        name: "prof",
+        doc: "proficiency bonus for a character level between 1 and 20",
+        signatures: || vec![Signature::new(vec![Ty::Number], Ty::Number)],
+        func: |_, args| match number(&args[0]) {
+            level @ 1..=20 => Ok(Val::Number(2 + (level - 1) / 4)),
+            level => Err(format!("level must be between 1 and 20, not {}", level)),
+        },
+    },
+    Builtin {
+        name: "tostring",
+        doc: "a value as a string, e.g. `tostring 1d20` is \"1d20\"",
+        signatures: || vec![Signature::new(vec![Ty::Any], Ty::Str)],
+        func: |_, args| Ok(Val::Str(to_string(&args[0]))),
+    },
+    Builtin {
+        name: "repr",
+        doc: "a value as a string, along with its type",
+        signatures: || vec![Signature::new(vec![Ty::Any], Ty::Str)],
+        func: |_, args| Ok(Val::Str(format!("{}: {}", args[0].ty(), args[0]))),
+    },
+    Builtin {
+        name: "print",
+        doc: "writes a value followed by a newline, or by the string given as the second argument",
+        signatures: || {
+            vec![
+                Signature::new(vec![Ty::Any], Ty::Unit),
+                Signature::new(vec![Ty::Any, Ty::Str], Ty::Unit),
+            ]
+        },
+        func: |env, args| {
+            let end = match args.get(1) {
+                Some(Val::Str(end)) => end.as_str(),
+                _ => "\n",
+            };
+            env.write_output(&to_string(&args[0]));
+            env.write_output(end);
+            Ok(Val::Unit)
+        },
+    },
+    Builtin {
+        name: "point_buy",
+        doc: "points spent on six ability scores under the point buy rules, or an error if they break them",
+        signatures: || {
+            vec![
+                Signature::new(vec![Ty::Comp], Ty::Number),
+                Signature::new(vec![Ty::vec(Ty::Number)], Ty::Number),
+            ]
+        },
+        func: |_, args| {
+            let scores = match &args[0] {
+                Val::Comp(comp) => comp
+                    .fields
+                    .iter()
+                    .map(|(name, val)| match val {
+                        Val::Number(n) => Ok((name.clone(), *n)),
+                        val => Err(format!("{} should be a number, not {}", name, val.ty())),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+                // A vector is taken in the usual order, STR to CHA
+                items => numbers(items)
+                    .enumerate()
+                    .map(|(i, n)| {
+                        let name = rollset::ABILITIES
+                            .get(i)
+                            .map_or_else(|| format!("score {}", i + 1), ToString::to_string);
+                        (name, n)
+                    })
+                    .collect(),
+            };
+
+            rollset::point_buy_cost(&scores).map(Val::Number)
+        },
+    },
+    Builtin {
+        name: "init",
+        doc: "rolls initiative for a list of names, with optional modifiers and dexterity scores to break ties, or shows the current order",
+        signatures: || {
+            vec![
+                Signature::new(Vec::new(), Ty::Unit),
+                Signature::new(vec![Ty::vec(Ty::Str)], Ty::Unit),
+                Signature::new(vec![Ty::vec(Ty::Str), Ty::vec(Ty::Number)], Ty::Unit),
+                Signature::new(
+                    vec![Ty::vec(Ty::Str), Ty::vec(Ty::Number), Ty::vec(Ty::Number)],
+                    Ty::Unit,
+                ),
+                Signature::new(vec![Ty::Comp], Ty::Unit),
+            ]
+        },
+        func: |env, args| {
+            if args.is_empty() {
+                let order = env.initiative().ok_or(NO_INITIATIVE)?.to_string();
+                env.write_output(&order);
+                env.write_output("\n");
+                return Ok(Val::Unit);
+            }
+
+            let entries = initiative_entries(args)?;
+            if entries.is_empty() {
+                return Err("`init` needs at least one name".to_string());
+            }
+
+            let order = TurnOrder::roll(entries, env)?;
+            env.write_output(&order.to_string());
+            env.write_output("\n");
+            env.set_initiative(order);
+            Ok(Val::Unit)
+        },
+    },
+    Builtin {
+        name: "next",
+        doc: "starts the next turn in the initiative order",
+        signatures: || vec![Signature::new(Vec::new(), Ty::Unit)],
+        func: |env, _| {
+            let order = env.initiative_mut().ok_or(NO_INITIATIVE)?;
+            let combatant = order.advance().clone();
+            let turn = format!(
+                "Round {}: {} ({})\n",
+                order.round(),
+                combatant.name,
+                combatant.roll.total
+            );
+
+            env.write_output(&turn);
+            Ok(Val::Unit)
+        },
+    },
+    Builtin {
+        name: "group_check",
+        doc: "rolls everyone in a group against a DC; the group passes if at least half of them succeed",
+        signatures: || vec![Signature::new(vec![Ty::Comp, Ty::Number], Ty::Bool)],
+        func: |env, args| {
+            let dc = number(&args[1]);
+            let Val::Comp(group) = args[0].clone().roll(env)? else {
+                unreachable!()
+            };
+
+            if group.fields.is_empty() {
+                return Err("a group check needs at least one member".to_string());
+            }
+
+            let mut successes = 0;
+            for (name, val) in &group.fields {
+                let total = match val {
+                    Val::Roll(roll) => roll.total,
+                    val => {
+                        return Err(format!(
+                            "{} can't take part in a group check: it's {}, not a roll",
+                            name,
+                            val.ty()
+                        ))
+                    }
+                };
+
+                if total >= dc {
+                    successes += 1;
+                }
+            }
+
+            let summary = format!(
+                "{}\n{} of {} succeeded against DC {}\n",
+                group,
+                successes,
+                group.fields.len(),
+                dc
+            );
+            env.write_output(&summary);
+
+            Ok(Val::Bool(successes * 2 >= group.fields.len()))
+        },
+    },
+];
+
+const NO_INITIATIVE: &str = "there is no initiative order yet; start one with `init`";
+
+// `init(names)`, `init(names, modifiers)` and `init(names, modifiers, dex_scores)` roll a d20 plus
+// the modifier for each name, while `init(Party)` takes each field as a modifier or a whole roll
+fn initiative_entries(args: Vec<Val>) -> Result<Vec<Entry>, String> {
+    if let [Val::Comp(comp)] = args.as_slice() {
+        return comp
+            .fields
+            .iter()
+            .map(|(name, val)| match val {
+                Val::Number(modifier) => Ok(Entry::with_modifier(name.clone(), *modifier, None)),
+                Val::Formula(formula) => Ok(Entry {
+                    name: name.clone(),
+                    formula: formula.clone(),
+                    modifier: 0,
+                    dex: None,
+                }),
+                val => Err(format!(
+                    "{} should be a modifier or dice to roll, not {}",
+                    name,
+                    val.ty()
+                )),
+            })
+            .collect();
+    }
+
+    let names: Vec<_> = match &args[0] {
+        Val::Vec(names) => names.iter().map(to_string).collect(),
+        _ => unreachable!(),
+    };
+    let modifiers: Vec<_> = match args.get(1) {
+        Some(modifiers) => numbers(modifiers).collect(),
+        None => vec![0; names.len()],
+    };
+    let dex: Vec<_> = match args.get(2) {
+        Some(dex) => numbers(dex).map(Some).collect(),
+        None => vec![None; names.len()],
+    };
+
+    for (what, len) in [
+        ("modifiers", modifiers.len()),
+        ("dexterity scores", dex.len()),
+    ] {
+        if len != names.len() {
+            return Err(format!(
+                "`init` was given {} names but {} {}",
+                names.len(),
+                len,
+                what
+            ));
+        }
+    }
+
+    Ok(names
+        .into_iter()
+        .zip(modifiers)
+        .zip(dex)
+        .map(|((name, modifier), dex)| Entry::with_modifier(name, modifier, dex))
+        .collect())
+}
+
+// Like `Display`, but without quotes around strings
+fn to_string(val: &Val) -> String {
+    match val {
+        Val::Str(s) => s.clone(),
+        val => val.to_string(),
+    }
+}
+
+fn extreme_signatures() -> Vec<Signature> {
+    vec![
+        Signature::new(vec![Ty::Number, Ty::Number], Ty::Number),
+        Signature::new(vec![Ty::vec(Ty::Number)], Ty::Number),
+    ]
+}
+
+fn extreme(
+    name: &str,
+    args: Vec<Val>,
+    pick: fn(std::vec::IntoIter<i64>) -> Option<i64>,
+) -> Result<Val, String> {
+    let candidates: Vec<_> = match args.as_slice() {
+        [items] => numbers(items).collect(),
+        [lhs, rhs] => vec![number(lhs), number(rhs)],
+        _ => unreachable!(),
+    };
+
+    pick(candidates.into_iter())
+        .map(Val::Number)
+        .ok_or_else(|| format!("`{}` of an empty vector", name))
+}
+
+// Arguments have already been coerced, so these can't fail
+fn number(val: &Val) -> i64 {
+    match val {
+        Val::Number(n) => *n,
+        _ => unreachable!(),
+    }
+}
+
+fn numbers(val: &Val) -> impl Iterator<Item = i64> + '_ {
+    match val {
+        Val::Vec(items) => items.iter().map(number),
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formula::{Dice, Formula};
+
+    fn call(name: &str, args: Vec<Val>) -> Result<Val, String> {
+        let mut env = Env::with_seed(0);
+        builtin(name).unwrap().call(&mut env, args)
+    }
+
+    fn numbers(ns: &[i64]) -> Val {
+        Val::Vec(ns.iter().copied().map(Val::Number).collect())
+    }
+
+    #[test]
+    fn every_builtin_has_a_signature() {
+        for builtin in builtins() {
+            assert!(!builtin.signatures().is_empty(), "{}", builtin.name);
+        }
+    }
+
+    #[test]
+    fn abs_of_negative() {
+        assert_eq!(call("abs", vec![Val::Number(-3)]), Ok(Val::Number(3)));
+    }
+
+    #[test]
+    fn max_of_two_numbers() {
+        assert_eq!(
+            call("max", vec![Val::Number(3), Val::Number(9)]),
+            Ok(Val::Number(9))
+        );
+    }
+
+    #[test]
+    fn min_of_vector() {
+        assert_eq!(call("min", vec![numbers(&[4, -2, 7])]), Ok(Val::Number(-2)));
+    }
+
+    #[test]
+    fn max_of_empty_vector() {
+        assert_eq!(
+            call("max", vec![Val::Vec(Vec::new())]),
+            Err("`max` of an empty vector".to_string())
+        );
+    }
+
+    #[test]
+    fn max_of_roll_picks_highest_die() {
+        let mut env = Env::with_seed(3);
+        let roll = Formula::Dice(Dice::new(4, 6)).roll(env.rng()).unwrap();
+        let highest = roll.outcome.faces().into_iter().max().unwrap();
+
+        assert_eq!(
+            call("max", vec![Val::Roll(roll)]),
+            Ok(Val::Number(highest.into()))
+        );
+    }
+
+    #[test]
+    fn ceildiv_rounds_up() {
+        assert_eq!(
+            call("ceildiv", vec![Val::Number(7), Val::Number(2)]),
+            Ok(Val::Number(4))
+        );
+        assert_eq!(
+            call("ceildiv", vec![Val::Number(6), Val::Number(2)]),
+            Ok(Val::Number(3))
+        );
+    }
+
+    #[test]
+    fn ceildiv_by_zero() {
+        assert_eq!(
+            call("ceildiv", vec![Val::Number(7), Val::Number(0)]),
+            Err("division by zero".to_string())
+        );
+    }
+
+    #[test]
+    fn len_of_string_and_vector() {
+        assert_eq!(
+            call("len", vec![Val::Str("Hello".to_string())]),
+            Ok(Val::Number(5))
+        );
+        assert_eq!(call("len", vec![numbers(&[0, -3, 7])]), Ok(Val::Number(3)));
+    }
+
+    #[test]
+    fn sum_and_sort() {
+        assert_eq!(call("sum", vec![numbers(&[1, 2, 3])]), Ok(Val::Number(6)));
+        assert_eq!(
+            call("sort", vec![numbers(&[3, 1, 2])]),
+            Ok(numbers(&[1, 2, 3]))
+        );
+    }
+
+    #[test]
+    fn count_occurrences() {
+        assert_eq!(
+            call("count", vec![numbers(&[6, 1, 6, 3]), Val::Number(6)]),
+            Ok(Val::Number(2))
+        );
+    }
+
+    #[test]
+    fn ability_modifiers() {
+        for (score, modifier) in [(1, -5), (8, -1), (10, 0), (11, 0), (15, 2), (20, 5)] {
+            assert_eq!(
+                call("mod", vec![Val::Number(score)]),
+                Ok(Val::Number(modifier))
+            );
+        }
+    }
+
+    #[test]
+    fn proficiency_bonus() {
+        assert_eq!(call("prof", vec![Val::Number(1)]), Ok(Val::Number(2)));
+        assert_eq!(call("prof", vec![Val::Number(5)]), Ok(Val::Number(3)));
+        assert_eq!(call("prof", vec![Val::Number(20)]), Ok(Val::Number(6)));
+        assert_eq!(
+            call("prof", vec![Val::Number(21)]),
+            Err("level must be between 1 and 20, not 21".to_string())
+        );
+    }
+
+    #[test]
+    fn tostring_and_repr() {
+        let dice = Val::Formula(Formula::Dice(Dice::new(1, 20)));
+        assert_eq!(
+            call("tostring", vec![dice.clone()]),
+            Ok(Val::Str("1d20".to_string()))
+        );
+        assert_eq!(
+            call("repr", vec![dice]),
+            Ok(Val::Str("dice: 1d20".to_string()))
+        );
+    }
+
+    #[test]
+    fn print_writes_to_output() {
+        let mut env = Env::with_seed(0);
+        let print = builtin("print").unwrap();
+        print
+            .call(&mut env, vec![Val::Str("Attack".to_string())])
+            .unwrap();
+        print
+            .call(&mut env, vec![Val::Number(17), Val::Str(String::new())])
+            .unwrap();
+
+        assert_eq!(env.take_output(), "Attack\n17");
+        assert_eq!(env.take_output(), "");
+    }
+
+    #[test]
+    fn wrong_number_of_arguments() {
+        assert_eq!(
+            call("abs", vec![Val::Number(1), Val::Number(2)]),
+            Err("`abs` takes 1 argument but 2 were given".to_string())
+        );
+        assert_eq!(
+            call("max", vec![]),
+            Err("`max` takes 1 or 2 arguments but 0 were given".to_string())
+        );
+    }
+
+    #[test]
+    fn wrong_argument_type() {
+        assert_eq!(
+            call("sum", vec![Val::Str("abc".to_string())]),
+            Err("`sum` expects ([number]), but was given (str)".to_string())
+        );
+        assert_eq!(
+            call("len", vec![Val::Number(3)]),
+            Err("`len` expects (str) or ([any]), but was given (number)".to_string())
+        );
+    }
+}
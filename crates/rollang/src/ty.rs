@@ -0,0 +1,77 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ty {
+    Unit,
+    Bool,
+    Number,
+    Dice,
+    Roll,
+    Str,
+    Vec(Box<Ty>),
+    Comp,
+    // Only used in builtin signatures: matches a value of any type
+    Any,
+}
+
+impl Ty {
+    pub fn vec(elem: Ty) -> Self {
+        Self::Vec(Box::new(elem))
+    }
+
+    pub(crate) fn has_any(&self) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Vec(elem) => elem.has_any(),
+            _ => false,
+        }
+    }
+
+    // Whether a value of this type can be given where `ty` is expected, following `Val::coerce`.
+    // `any` on this side is a type that isn't known, e.g. the items of an empty vector, so it
+    // fits anything.
+    pub(crate) fn fits(&self, ty: &Ty) -> bool {
+        match (self, ty) {
+            (Self::Any, _) | (_, Self::Any) => true,
+            (Self::Roll, Self::Number) | (Self::Number, Self::Dice) => true,
+            (Self::Vec(elem), Self::Vec(expected)) => elem.fits(expected),
+            (Self::Roll, Self::Vec(expected)) => matches!(**expected, Self::Number | Self::Any),
+            (given, expected) => given == expected,
+        }
+    }
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unit => f.write_str("unit"),
+            Self::Bool => f.write_str("bool"),
+            Self::Number => f.write_str("number"),
+            Self::Dice => f.write_str("dice"),
+            Self::Roll => f.write_str("roll"),
+            Self::Str => f.write_str("str"),
+            Self::Vec(elem) => write!(f, "[{}]", elem),
+            Self::Comp => f.write_str("comp"),
+            Self::Any => f.write_str("any"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_nested_vector() {
+        assert_eq!(Ty::vec(Ty::vec(Ty::Number)).to_string(), "[[number]]");
+    }
+
+    #[test]
+    fn types_fit_like_values_coerce() {
+        assert!(Ty::Roll.fits(&Ty::Number));
+        assert!(Ty::Roll.fits(&Ty::vec(Ty::Number)));
+        assert!(Ty::vec(Ty::Any).fits(&Ty::vec(Ty::Str)));
+        assert!(!Ty::Dice.fits(&Ty::Number));
+        assert!(!Ty::vec(Ty::Str).fits(&Ty::vec(Ty::Number)));
+    }
+}
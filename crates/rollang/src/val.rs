@@ -0,0 +1,231 @@
+use crate::comp::Comp;
+use crate::formula::{ArithError, Formula, InfixOp, Roll, Roller};
+use crate::ty::Ty;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Val {
+    Unit,
+    Bool(bool),
+    Number(i64),
+    Formula(Formula),
+    Roll(Roll),
+    Str(String),
+    Vec(Vec<Val>),
+    Comp(Comp),
+}
+
+impl Val {
+    pub fn ty(&self) -> Ty {
+        match self {
+            Self::Unit => Ty::Unit,
+            Self::Bool(_) => Ty::Bool,
+            Self::Number(_) => Ty::Number,
+            Self::Formula(_) => Ty::Dice,
+            Self::Roll(_) => Ty::Roll,
+            Self::Str(_) => Ty::Str,
+            Self::Vec(items) => Ty::vec(items.first().map_or(Ty::Any, Val::ty)),
+            Self::Comp(_) => Ty::Comp,
+        }
+    }
+
+    // Converts a value so that it fits `ty`, e.g. a roll passed where a number is expected
+    // stands for its total, and where a vector of numbers is expected it stands for its faces
+    pub fn coerce(self, ty: &Ty) -> Option<Val> {
+        match (self, ty) {
+            (val, Ty::Any) => Some(val),
+            (val @ Val::Number(_), Ty::Number) => Some(val),
+            (Val::Roll(roll), Ty::Number) => Some(Val::Number(roll.total)),
+            (val @ Val::Formula(_), Ty::Dice) => Some(val),
+            (Val::Number(n), Ty::Dice) => Some(Val::Formula(Formula::Number(n))),
+            (val @ Val::Roll(_), Ty::Roll) => Some(val),
+            (val @ Val::Str(_), Ty::Str) => Some(val),
+            (Val::Unit, Ty::Unit) => Some(Val::Unit),
+            (val @ Val::Bool(_), Ty::Bool) => Some(val),
+            (val @ Val::Comp(_), Ty::Comp) => Some(val),
+            (Val::Vec(items), Ty::Vec(elem)) => items
+                .into_iter()
+                .map(|item| item.coerce(elem))
+                .collect::<Option<_>>()
+                .map(Val::Vec),
+            (Val::Roll(roll), Ty::Vec(elem)) if **elem == Ty::Number || **elem == Ty::Any => {
+                let faces = roll.outcome.faces();
+                Some(Val::Vec(
+                    faces
+                        .into_iter()
+                        .map(|face| Val::Number(face.into()))
+                        .collect(),
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn roll(self, roller: &mut impl Roller) -> Result<Val, String> {
+        self.roll_as(None, roller)
+    }
+
+    // Members of a composite are labelled with their field names
+    fn roll_as(self, label: Option<&str>, roller: &mut impl Roller) -> Result<Val, String> {
+        match self {
+            Self::Number(n) => Ok(Self::Roll(Formula::Number(n).roll(roller).unwrap())),
+            Self::Formula(formula) => {
+                let roll = formula.roll(roller)?;
+                if formula.has_dice() {
+                    roller.record(label, &formula, &roll);
+                }
+                Ok(Self::Roll(roll))
+            }
+            // Members that can't be rolled, or already have been, are kept as they are
+            Self::Comp(comp) => {
+                let fields = comp
+                    .fields
+                    .into_iter()
+                    .map(|(name, val)| match val {
+                        Self::Number(_) | Self::Formula(_) | Self::Comp(_) => {
+                            val.roll_as(Some(&name), roller).map(|val| (name, val))
+                        }
+                        val => Ok((name, val)),
+                    })
+                    .collect::<Result<_, _>>()?;
+
+                Ok(Self::Comp(Comp {
+                    name: comp.name,
+                    fields,
+                }))
+            }
+            Self::Roll(_) => Err("this value has already been rolled".to_string()),
+            val => Err(format!("cannot roll {}", val.ty())),
+        }
+    }
+
+    pub(crate) fn infix(self, op: InfixOp, rhs: Val) -> Result<Val, String> {
+        match (self.into_operand()?, rhs.into_operand()?) {
+            (Formula::Number(lhs), Formula::Number(rhs)) => op
+                .apply(lhs, rhs)
+                .map(Val::Number)
+                .map_err(|e| e.to_string()),
+            (lhs, rhs) => Ok(Val::Formula(Formula::Infix {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            })),
+        }
+    }
+
+    pub(crate) fn neg(self) -> Result<Val, String> {
+        match self.into_operand()? {
+            Formula::Number(n) => n
+                .checked_neg()
+                .map(Val::Number)
+                .ok_or_else(|| ArithError::Overflow.to_string()),
+            formula => Ok(Val::Formula(Formula::Neg(Box::new(formula)))),
+        }
+    }
+
+    // A rolled value takes part in arithmetic through its total
+    fn into_operand(self) -> Result<Formula, String> {
+        match self {
+            Self::Number(n) => Ok(Formula::Number(n)),
+            Self::Formula(formula) => Ok(formula),
+            Self::Roll(roll) => Ok(Formula::Number(roll.total)),
+            val => Err(format!("cannot do arithmetic on {}", val.ty())),
+        }
+    }
+}
+
+impl From<Formula> for Val {
+    fn from(formula: Formula) -> Self {
+        match formula.constant() {
+            Some(n) => Self::Number(n),
+            None => Self::Formula(formula),
+        }
+    }
+}
+
+impl fmt::Display for Val {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unit => Ok(()),
+            Self::Bool(true) => f.write_str("pass"),
+            Self::Bool(false) => f.write_str("fail"),
+            Self::Number(n) => write!(f, "{}", n),
+            Self::Formula(formula) => write!(f, "{}", formula),
+            Self::Roll(roll) => write!(f, "{}", roll),
+            Self::Str(s) => write!(f, "{:?}", s),
+            Self::Vec(items) => {
+                let items: Vec<_> = items.iter().map(ToString::to_string).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+            Self::Comp(comp) => write!(f, "{}", comp),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formula::Dice;
+
+    #[test]
+    fn numbers_are_folded() {
+        assert_eq!(
+            Val::Number(2).infix(InfixOp::Mul, Val::Number(3)),
+            Ok(Val::Number(6))
+        );
+    }
+
+    #[test]
+    fn dice_stay_pending() {
+        let d8 = Val::Formula(Formula::Dice(Dice::new(1, 8)));
+        let val = d8.infix(InfixOp::Add, Val::Number(2)).unwrap();
+        assert_eq!(val.to_string(), "1d8+2");
+    }
+
+    #[test]
+    fn constant_formula_becomes_number() {
+        let formula = Formula::Infix {
+            op: InfixOp::Mul,
+            lhs: Box::new(Formula::Number(5)),
+            rhs: Box::new(Formula::Number(2)),
+        };
+        assert_eq!(Val::from(formula), Val::Number(10));
+    }
+
+    #[test]
+    fn roll_coerces_to_total_or_faces() {
+        let mut rng = crate::rng::Rng::new(1);
+        let roll = Formula::Dice(Dice::new(3, 6)).roll(&mut rng).unwrap();
+        let total = roll.total;
+
+        assert_eq!(
+            Val::Roll(roll.clone()).coerce(&Ty::Number),
+            Some(Val::Number(total))
+        );
+
+        let Some(Val::Vec(faces)) = Val::Roll(roll).coerce(&Ty::vec(Ty::Number)) else {
+            panic!("expected faces");
+        };
+        assert_eq!(faces.len(), 3);
+    }
+
+    #[test]
+    fn vector_coercion_checks_every_item() {
+        let items = Val::Vec(vec![Val::Number(1), Val::Str("two".to_string())]);
+        assert_eq!(items.coerce(&Ty::vec(Ty::Number)), None);
+    }
+
+    #[test]
+    fn format_vector_of_strings() {
+        let items = Val::Vec(vec![Val::Str("a".to_string()), Val::Str("b".to_string())]);
+        assert_eq!(items.to_string(), r#"["a", "b"]"#);
+    }
+
+    #[test]
+    fn division_by_zero_is_an_error() {
+        assert_eq!(
+            Val::Number(1).infix(InfixOp::Div, Val::Number(0)),
+            Err("division by zero".to_string())
+        );
+    }
+}
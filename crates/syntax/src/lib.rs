@@ -1,14 +1,28 @@
 use lexer::TokenKind;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
+use std::fmt;
+
+mod ptr;
+pub use ptr::SyntaxNodePtr;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive, Hash, PartialOrd, Ord)]
 pub enum SyntaxKind {
     Whitespace,
+    Newline,
     LetKw,
+    RollKw,
+    CritKw,
+    FnKw,
+    ReturnKw,
+    // Not a keyword to the lexer: `mod` is only special after `::`
+    ModKw,
+    // Likewise `comp`, which is also spelled `Comp`
+    CompKw,
     Dice,
     Ident,
     Number,
+    String,
     Plus,
     Minus,
     Star,
@@ -16,20 +30,87 @@ pub enum SyntaxKind {
     Equals,
     LParen,
     RParen,
+    Comma,
+    ColonColon,
+    ColonEquals,
+    Colon,
+    Dot,
+    Arrow,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Semicolon,
+    Lt,
+    Gt,
     Comment,
     Error,
     Root,
     InfixExpr,
     Literal,
+    StringLiteral,
+    DiceExpr,
     ParenExpr,
     PrefixExpr,
+    RollExpr,
+    CritExpr,
     VariableRef,
     VariableDef,
+    CallExpr,
+    ArgList,
+    Assignment,
+    FnDef,
+    ParamList,
+    Param,
+    RetType,
+    TypeExpr,
+    Block,
+    ReturnStmt,
+    ModDef,
+    ModExpr,
+    AnnotatedExpr,
+    CompDef,
+    CompField,
+    FieldExpr,
+    IndexExpr,
+    ArrayExpr,
+    GroupExpr,
+    GroupMember,
 }
 
 impl SyntaxKind {
+    // `mod` and `comp` are only keywords where a definition can start, so the lexer doesn't know
+    // about them
+    pub const KEYWORDS: [SyntaxKind; 7] = [
+        Self::LetKw,
+        Self::RollKw,
+        Self::CritKw,
+        Self::FnKw,
+        Self::ReturnKw,
+        Self::ModKw,
+        Self::CompKw,
+    ];
+
     pub fn is_trivia(self) -> bool {
-        matches!(self, Self::Whitespace | Self::Comment)
+        matches!(self, Self::Whitespace | Self::Newline | Self::Comment)
+    }
+
+    pub fn is_keyword(self) -> bool {
+        Self::KEYWORDS.contains(&self)
+    }
+
+    // How a keyword is written, e.g. `roll`
+    pub fn keyword_text(self) -> Option<&'static str> {
+        Some(match self {
+            Self::LetKw => "let",
+            Self::RollKw => "roll",
+            Self::CritKw => "crit",
+            Self::FnKw => "fn",
+            Self::ReturnKw => "return",
+            Self::ModKw => "mod",
+            Self::CompKw => "comp",
+            _ => return None,
+        })
     }
 }
 
@@ -37,10 +118,16 @@ impl From<TokenKind> for SyntaxKind {
     fn from(token_kind: TokenKind) -> Self {
         match token_kind {
             TokenKind::Whitespace => Self::Whitespace,
+            TokenKind::Newline => Self::Newline,
             TokenKind::Let => Self::LetKw,
+            TokenKind::Roll => Self::RollKw,
+            TokenKind::Crit => Self::CritKw,
+            TokenKind::Fn => Self::FnKw,
+            TokenKind::Return => Self::ReturnKw,
             TokenKind::Dice => Self::Dice,
             TokenKind::Ident => Self::Ident,
             TokenKind::Number => Self::Number,
+            TokenKind::String => Self::String,
             TokenKind::Plus => Self::Plus,
             TokenKind::Minus => Self::Minus,
             TokenKind::Star => Self::Star,
@@ -48,13 +135,71 @@ impl From<TokenKind> for SyntaxKind {
             TokenKind::Equals => Self::Equals,
             TokenKind::LParen => Self::LParen,
             TokenKind::RParen => Self::RParen,
+            TokenKind::Comma => Self::Comma,
+            TokenKind::ColonColon => Self::ColonColon,
+            TokenKind::ColonEquals => Self::ColonEquals,
+            TokenKind::Colon => Self::Colon,
+            TokenKind::Dot => Self::Dot,
+            TokenKind::Arrow => Self::Arrow,
+            TokenKind::LBrace => Self::LBrace,
+            TokenKind::RBrace => Self::RBrace,
+            TokenKind::LBracket => Self::LBracket,
+            TokenKind::RBracket => Self::RBracket,
+            TokenKind::Semicolon => Self::Semicolon,
+            TokenKind::Lt => Self::Lt,
+            TokenKind::Gt => Self::Gt,
             TokenKind::Comment => Self::Comment,
             TokenKind::Error => Self::Error,
         }
     }
 }
 
+impl fmt::Display for SyntaxKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Whitespace => "whitespace",
+            Self::Newline => "newline",
+            Self::LetKw => "‘let’",
+            Self::RollKw => "‘roll’",
+            Self::CritKw => "‘crit’",
+            Self::FnKw => "‘fn’",
+            Self::ReturnKw => "‘return’",
+            Self::ModKw => "‘mod’",
+            Self::CompKw => "‘comp’",
+            Self::Dice => "dice",
+            Self::Ident => "identifier",
+            Self::Number => "number",
+            Self::String => "string",
+            Self::Plus => "‘+’",
+            Self::Minus => "‘-’",
+            Self::Star => "‘*’",
+            Self::Slash => "‘/’",
+            Self::Equals => "‘=’",
+            Self::LParen => "‘(’",
+            Self::RParen => "‘)’",
+            Self::Comma => "‘,’",
+            Self::ColonColon => "‘::’",
+            Self::ColonEquals => "‘:=’",
+            Self::Colon => "‘:’",
+            Self::Dot => "‘.’",
+            Self::Arrow => "‘->’",
+            Self::LBrace => "‘{’",
+            Self::RBrace => "‘}’",
+            Self::LBracket => "‘[’",
+            Self::RBracket => "‘]’",
+            Self::Semicolon => "‘;’",
+            Self::Lt => "‘<’",
+            Self::Gt => "‘>’",
+            Self::Comment => "comment",
+            Self::Error => "an unrecognized token",
+            _ => return fmt::Debug::fmt(self, f),
+        })
+    }
+}
+
 pub type SyntaxNode = rowan::SyntaxNode<RollangLanguage>;
+pub type SyntaxToken = rowan::SyntaxToken<RollangLanguage>;
+pub type SyntaxElement = rowan::SyntaxElement<RollangLanguage>;
 
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub enum RollangLanguage {}
@@ -70,3 +215,17 @@ impl rowan::Language for RollangLanguage {
         rowan::SyntaxKind(kind.to_u16().unwrap())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_keyword_has_its_text() {
+        for keyword in SyntaxKind::KEYWORDS {
+            let text = keyword.keyword_text().unwrap();
+            assert_eq!(keyword.to_string(), format!("‘{}’", text));
+        }
+        assert_eq!(SyntaxKind::Ident.keyword_text(), None);
+    }
+}
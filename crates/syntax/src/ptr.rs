@@ -0,0 +1,121 @@
+use crate::{SyntaxKind, SyntaxNode};
+use text_size::TextRange;
+
+// Points at a node by its kind and where it is, so that it can be kept without keeping the whole
+// tree alive and found again in another tree of the same text, e.g. after an edit elsewhere
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SyntaxNodePtr {
+    kind: SyntaxKind,
+    range: TextRange,
+}
+
+impl SyntaxNodePtr {
+    pub fn new(node: &SyntaxNode) -> Self {
+        Self {
+            kind: node.kind(),
+            range: node.text_range(),
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn range(&self) -> TextRange {
+        self.range
+    }
+
+    // The node in `root` with the same kind and range, if there still is one
+    pub fn to_node(&self, root: &SyntaxNode) -> Option<SyntaxNode> {
+        std::iter::successors(Some(root.clone()), |node| {
+            node.child_or_token_at_range(self.range)?.into_node()
+        })
+        .take_while(|node| node.text_range().contains_range(self.range))
+        .find(|node| node.text_range() == self.range && node.kind() == self.kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RollangLanguage;
+    use rowan::{GreenNodeBuilder, Language};
+
+    // The tree the parser makes for `x := 1+2`
+    fn tree() -> SyntaxNode {
+        fn start(builder: &mut GreenNodeBuilder, kind: SyntaxKind) {
+            builder.start_node(RollangLanguage::kind_to_raw(kind));
+        }
+        fn token(builder: &mut GreenNodeBuilder, kind: SyntaxKind, text: &str) {
+            builder.token(RollangLanguage::kind_to_raw(kind), text);
+        }
+
+        let mut builder = GreenNodeBuilder::new();
+        start(&mut builder, SyntaxKind::Root);
+        start(&mut builder, SyntaxKind::VariableDef);
+        token(&mut builder, SyntaxKind::Ident, "x");
+        token(&mut builder, SyntaxKind::Whitespace, " ");
+        token(&mut builder, SyntaxKind::ColonEquals, ":=");
+        token(&mut builder, SyntaxKind::Whitespace, " ");
+        start(&mut builder, SyntaxKind::InfixExpr);
+        start(&mut builder, SyntaxKind::Literal);
+        token(&mut builder, SyntaxKind::Number, "1");
+        builder.finish_node();
+        token(&mut builder, SyntaxKind::Plus, "+");
+        start(&mut builder, SyntaxKind::Literal);
+        token(&mut builder, SyntaxKind::Number, "2");
+        builder.finish_node();
+        builder.finish_node();
+        builder.finish_node();
+        builder.finish_node();
+
+        SyntaxNode::new_root(builder.finish())
+    }
+
+    #[test]
+    fn every_node_round_trips() {
+        let root = tree();
+        for node in root.descendants() {
+            assert_eq!(SyntaxNodePtr::new(&node).to_node(&root), Some(node));
+        }
+    }
+
+    // The root and the definition cover the same text, so only the kind tells them apart
+    #[test]
+    fn nodes_with_the_same_range() {
+        let root = tree();
+        let def = root.first_child().unwrap();
+        assert_eq!(def.text_range(), root.text_range());
+
+        let ptr = SyntaxNodePtr::new(&def);
+        assert_eq!(ptr.kind(), SyntaxKind::VariableDef);
+        assert_eq!(ptr.to_node(&root).unwrap().kind(), SyntaxKind::VariableDef);
+        assert_eq!(SyntaxNodePtr::new(&root).to_node(&root), Some(root));
+    }
+
+    #[test]
+    fn kind_mismatch() {
+        let root = tree();
+        let ptr = SyntaxNodePtr {
+            kind: SyntaxKind::CallExpr,
+            range: root.first_child().unwrap().text_range(),
+        };
+        assert_eq!(ptr.to_node(&root), None);
+    }
+
+    #[test]
+    fn range_of_a_token() {
+        let root = tree();
+        let plus = root
+            .descendants_with_tokens()
+            .find(|element| element.kind() == SyntaxKind::Plus)
+            .unwrap();
+
+        let ptr = SyntaxNodePtr {
+            kind: SyntaxKind::Literal,
+            range: plus.text_range(),
+        };
+        assert_eq!(ptr.range(), TextRange::new(6.into(), 7.into()));
+        assert_eq!(ptr.to_node(&root), None);
+    }
+}